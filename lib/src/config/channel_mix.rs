@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Highest absolute value of [`ChannelMixSettings::balance`]
+pub const BALANCE_MAX: i16 = 100;
+
+/// How the channels of the sound are mixed before it is played
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct ChannelMixSettings {
+    /// Mix all channels together and play the mix on every channel
+    pub mono: bool,
+    /// Balance between the left and right channel, from `-100` (only left) to `100` (only right)
+    pub balance: i16,
+}
+
+impl ChannelMixSettings {
+    /// Get the balance clamped to `-100..=100`
+    #[must_use]
+    pub fn balance(&self) -> i16 {
+        self.balance.clamp(-BALANCE_MAX, BALANCE_MAX)
+    }
+
+    /// Get the factors the left and right channel are multiplied with for the balance
+    ///
+    /// The side the balance is moved to keeps its level, the other one is turned down.
+    #[must_use]
+    pub fn gains(&self) -> (f32, f32) {
+        let balance = f32::from(self.balance()) / f32::from(BALANCE_MAX);
+        ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0))
+    }
+
+    /// Whether the sound is played unchanged
+    #[must_use]
+    pub fn is_neutral(&self) -> bool {
+        !self.mono && self.balance() == 0
+    }
+}
@@ -0,0 +1,157 @@
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::track::{MediaType, Track};
+
+/// How the volume of two tracks changes while crossfading
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum CrossfadeCurve {
+    Linear,
+    /// Keeps the sum of the power of both tracks constant, avoids the dip in the middle of linear
+    #[default]
+    EqualPower,
+    /// Slow at the start and the end, fast in the middle
+    SCurve,
+}
+
+impl CrossfadeCurve {
+    /// Get the volume factor of the track fading in at `progress`, from `0.0` to `1.0`
+    ///
+    /// The track fading out uses `gain(1.0 - progress)`.
+    #[must_use]
+    pub fn gain(self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => progress,
+            Self::EqualPower => (progress * FRAC_PI_2).sin(),
+            Self::SCurve => progress * progress * (3.0 - 2.0 * progress),
+        }
+    }
+}
+
+/// A single crossfaded transition between two tracks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crossfade {
+    pub duration: Duration,
+    pub curve: CrossfadeCurve,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct CrossfadeSettings {
+    /// Time both tracks overlap in milliseconds, `0` disables crossfading
+    pub duration_ms: u64,
+    pub curve: CrossfadeCurve,
+}
+
+impl CrossfadeSettings {
+    /// Get the crossfade duration, [`None`] if disabled
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        (self.duration_ms > 0).then(|| Duration::from_millis(self.duration_ms))
+    }
+
+    /// Get the crossfade from `previous` to `next`, [`None`] if they should not be crossfaded
+    ///
+    /// Consecutive tracks of the same album are played without crossfade,
+    /// as albums often continue from one track into the next.
+    #[must_use]
+    pub fn between(&self, previous: &Track, next: &Track) -> Option<Crossfade> {
+        let duration = self.duration()?;
+        let same_album = previous.media_type == Some(MediaType::Music)
+            && next.media_type == Some(MediaType::Music)
+            && previous.album().is_some()
+            && previous.album() == next.album()
+            && previous.directory() == next.directory();
+        if same_album || next.media_type == Some(MediaType::LiveRadio) {
+            return None;
+        }
+
+        Some(Crossfade {
+            duration,
+            curve: self.curve,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn music(path: &str, album: &str) -> Track {
+        let mut track = Track::new_radio(path);
+        track.media_type = Some(MediaType::Music);
+        track.set_album(album);
+        track
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_curve_gain() {
+        for curve in [
+            CrossfadeCurve::Linear,
+            CrossfadeCurve::EqualPower,
+            CrossfadeCurve::SCurve,
+        ] {
+            assert_near(curve.gain(0.0), 0.0);
+            assert_near(curve.gain(1.0), 1.0);
+            // out of range progress is clamped
+            assert_near(curve.gain(-1.0), 0.0);
+            assert_near(curve.gain(2.0), 1.0);
+        }
+
+        assert_near(CrossfadeCurve::Linear.gain(0.25), 0.25);
+        assert_near(CrossfadeCurve::SCurve.gain(0.5), 0.5);
+        // equal power keeps the summed power of both tracks at 1
+        for progress in [0.1, 0.5, 0.9] {
+            let fade_in = CrossfadeCurve::EqualPower.gain(progress);
+            let fade_out = CrossfadeCurve::EqualPower.gain(1.0 - progress);
+            assert_near(fade_in * fade_in + fade_out * fade_out, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        let settings = CrossfadeSettings {
+            duration_ms: 3000,
+            curve: CrossfadeCurve::SCurve,
+        };
+        let crossfade = Some(Crossfade {
+            duration: Duration::from_secs(3),
+            curve: CrossfadeCurve::SCurve,
+        });
+
+        let first = music("/music/a/1.mp3", "A");
+        assert_eq!(
+            settings.between(&first, &music("/music/a/2.mp3", "A")),
+            None
+        );
+        assert_eq!(
+            settings.between(&first, &music("/music/a/2.mp3", "B")),
+            crossfade
+        );
+        // same album name in another directory is another album
+        assert_eq!(
+            settings.between(&first, &music("/music/b/2.mp3", "A")),
+            crossfade
+        );
+        assert_eq!(
+            settings.between(&first, &Track::new_radio("http://radio.example/stream")),
+            None
+        );
+
+        let disabled = CrossfadeSettings::default();
+        assert_eq!(
+            disabled.between(&first, &music("/music/b/2.mp3", "B")),
+            None
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+/// Number of bands of the equalizer
+pub const EQ_BAND_COUNT: usize = 10;
+/// Center frequencies in Hz of the bands, one octave apart
+pub const EQ_BAND_FREQUENCIES: [f32; EQ_BAND_COUNT] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+/// Lowest gain in dB a band can be set to
+pub const EQ_GAIN_MIN: f32 = -12.0;
+/// Highest gain in dB a band can be set to
+pub const EQ_GAIN_MAX: f32 = 12.0;
+
+/// Gains in dB, one per band in [`EQ_BAND_FREQUENCIES`]
+pub type EqualizerGains = [f32; EQ_BAND_COUNT];
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum EqPreset {
+    #[default]
+    Flat,
+    Rock,
+    Pop,
+    Jazz,
+    Classical,
+    Bass,
+    Treble,
+    Vocal,
+    /// Use [`EqualizerSettings::custom_gains`]
+    Custom,
+}
+
+impl EqPreset {
+    /// All presets, in the order of their player protocol value
+    pub const ALL: [Self; 9] = [
+        Self::Flat,
+        Self::Rock,
+        Self::Pop,
+        Self::Jazz,
+        Self::Classical,
+        Self::Bass,
+        Self::Treble,
+        Self::Vocal,
+        Self::Custom,
+    ];
+
+    /// Get the gains of this preset, [`None`] for [`EqPreset::Custom`]
+    #[must_use]
+    pub fn gains(self) -> Option<EqualizerGains> {
+        let gains = match self {
+            Self::Flat => [0.0; EQ_BAND_COUNT],
+            Self::Rock => [5.0, 4.0, 3.0, 1.0, -1.0, -1.0, 1.0, 3.0, 4.0, 5.0],
+            Self::Pop => [-1.0, 2.0, 4.0, 5.0, 3.0, 0.0, -1.0, -1.0, -1.0, -1.0],
+            Self::Jazz => [3.0, 2.0, 1.0, 2.0, -1.0, -1.0, 0.0, 1.0, 2.0, 3.0],
+            Self::Classical => [4.0, 3.0, 2.0, 1.0, -1.0, -1.0, 0.0, 2.0, 3.0, 4.0],
+            Self::Bass => [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            Self::Treble => [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0],
+            Self::Vocal => [-2.0, -3.0, -3.0, 1.0, 4.0, 4.0, 3.0, 1.0, 0.0, -2.0],
+            Self::Custom => return None,
+        };
+
+        Some(gains)
+    }
+
+    /// Get the value used for this preset in the player protocol
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn as_u32(self) -> u32 {
+        // always found, "ALL" contains every variant
+        Self::ALL.iter().position(|v| *v == self).unwrap_or(0) as u32
+    }
+
+    /// Get the preset from its player protocol value, if valid
+    #[must_use]
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Rock => "rock",
+            Self::Pop => "pop",
+            Self::Jazz => "jazz",
+            Self::Classical => "classical",
+            Self::Bass => "bass",
+            Self::Treble => "treble",
+            Self::Vocal => "vocal",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+pub struct EqualizerSettings {
+    pub enabled: bool,
+    pub preset: EqPreset,
+    /// Gains in dB used with [`EqPreset::Custom`]
+    pub custom_gains: EqualizerGains,
+}
+
+impl Default for EqualizerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preset: EqPreset::Flat,
+            custom_gains: [0.0; EQ_BAND_COUNT],
+        }
+    }
+}
+
+impl EqualizerSettings {
+    /// Get the gains of the selected preset, limited to [`EQ_GAIN_MIN`] and [`EQ_GAIN_MAX`]
+    #[must_use]
+    pub fn gains(&self) -> EqualizerGains {
+        self.preset
+            .gains()
+            .unwrap_or(self.custom_gains)
+            .map(|v| v.clamp(EQ_GAIN_MIN, EQ_GAIN_MAX))
+    }
+
+    /// Get the gains to apply, [`None`] if disabled
+    #[must_use]
+    pub fn active_gains(&self) -> Option<EqualizerGains> {
+        self.enabled.then(|| self.gains())
+    }
+}
@@ -74,7 +74,15 @@ pub struct Keys {
     pub playlist_swap_up: BindingForEvent,
     pub playlist_cmus_lqueue: BindingForEvent,
     pub playlist_cmus_tqueue: BindingForEvent,
+    pub playlist_most_played: BindingForEvent,
+    pub playlist_recently_played: BindingForEvent,
+    pub playlist_never_played: BindingForEvent,
     pub database_add_all: BindingForEvent,
+    pub database_sort: BindingForEvent,
+    pub database_export_m3u: BindingForEvent,
+    pub track_rating_up: BindingForEvent,
+    pub track_rating_down: BindingForEvent,
+    pub track_favorite_toggle: BindingForEvent,
     pub config_save: BindingForEvent,
     pub podcast_mark_played: BindingForEvent,
     pub podcast_mark_all_played: BindingForEvent,
@@ -140,6 +148,9 @@ impl Keys {
             .chain(once(self.library_switch_root))
             .chain(once(self.library_add_root))
             .chain(once(self.library_remove_root))
+            .chain(once(self.track_rating_up))
+            .chain(once(self.track_rating_down))
+            .chain(once(self.track_favorite_toggle))
     }
 
     fn iter_playlist(&self) -> impl Iterator<Item = BindingForEvent> {
@@ -153,6 +164,12 @@ impl Keys {
             .chain(once(self.playlist_swap_up))
             .chain(once(self.playlist_cmus_lqueue))
             .chain(once(self.playlist_cmus_tqueue))
+            .chain(once(self.playlist_most_played))
+            .chain(once(self.playlist_recently_played))
+            .chain(once(self.playlist_never_played))
+            .chain(once(self.track_rating_up))
+            .chain(once(self.track_rating_down))
+            .chain(once(self.track_favorite_toggle))
     }
 
     fn iter_podcast(&self) -> impl Iterator<Item = BindingForEvent> {
@@ -501,6 +518,18 @@ impl Default for Keys {
                 code: Key::Char('s'),
                 modifier: KeyModifiers::NONE,
             },
+            playlist_most_played: BindingForEvent {
+                code: Key::Char('P'),
+                modifier: KeyModifiers::SHIFT,
+            },
+            playlist_recently_played: BindingForEvent {
+                code: Key::Char('H'),
+                modifier: KeyModifiers::SHIFT,
+            },
+            playlist_never_played: BindingForEvent {
+                code: Key::Char('U'),
+                modifier: KeyModifiers::SHIFT,
+            },
             global_layout_treeview: BindingForEvent {
                 code: Key::Char('1'),
                 modifier: KeyModifiers::NONE,
@@ -513,6 +542,26 @@ impl Default for Keys {
                 code: Key::Char('L'),
                 modifier: KeyModifiers::SHIFT,
             },
+            database_sort: BindingForEvent {
+                code: Key::Char('o'),
+                modifier: KeyModifiers::NONE,
+            },
+            database_export_m3u: BindingForEvent {
+                code: Key::Char('e'),
+                modifier: KeyModifiers::NONE,
+            },
+            track_rating_up: BindingForEvent {
+                code: Key::Char(']'),
+                modifier: KeyModifiers::NONE,
+            },
+            track_rating_down: BindingForEvent {
+                code: Key::Char('['),
+                modifier: KeyModifiers::NONE,
+            },
+            track_favorite_toggle: BindingForEvent {
+                code: Key::Char('*'),
+                modifier: KeyModifiers::SHIFT,
+            },
             global_player_toggle_gapless: BindingForEvent {
                 code: Key::Char('g'),
                 modifier: KeyModifiers::CONTROL,
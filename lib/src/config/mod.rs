@@ -21,11 +21,25 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
+mod channel_mix;
+mod crossfade;
+mod equalizer;
 mod key;
+mod replay_gain;
+mod silence;
+mod smart_playlist;
 mod theme;
+mod volume;
 
-use crate::utils::get_app_config_path;
+use crate::track::MediaType;
+use crate::utils::{get_app_config_path, get_app_runtime_path, get_profile, write_atomic};
 use anyhow::{bail, Result};
+pub use channel_mix::{ChannelMixSettings, BALANCE_MAX};
+pub use crossfade::{Crossfade, CrossfadeCurve, CrossfadeSettings};
+pub use equalizer::{
+    EqPreset, EqualizerGains, EqualizerSettings, EQ_BAND_COUNT, EQ_BAND_FREQUENCIES, EQ_GAIN_MAX,
+    EQ_GAIN_MIN,
+};
 use figment::{
     providers::{Format, Serialized, Toml},
     Figment,
@@ -33,10 +47,14 @@ use figment::{
 use image::DynamicImage;
 pub use key::{BindingForEvent, Keys, ALT_SHIFT, CONTROL_ALT, CONTROL_ALT_SHIFT, CONTROL_SHIFT};
 use lazy_static::lazy_static;
+pub use replay_gain::{ReplayGainMode, ReplayGainSettings};
 use serde::{Deserialize, Serialize};
+pub use silence::{SilenceSettings, SilenceTrim};
+pub use smart_playlist::{SmartField, SmartOperator, SmartPlaylist, SmartRule};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::{fs, net::IpAddr};
 pub use theme::{load_alacritty, ColorTermusic, StyleColorSymbol};
+pub use volume::VolumeCurve;
 
 // pub const MUSIC_DIR: [&str; 2] = ["~/Music/mp3", "~/Music"];
 // pub const PODCAST_DIR: &str = "~/.cache/termusic/podcast";
@@ -60,7 +78,7 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
 pub enum Loop {
     Single,
     #[default]
@@ -70,6 +88,27 @@ pub enum Loop {
 
 #[allow(clippy::non_ascii_literal)]
 impl Loop {
+    /// Get the value used for this mode in the player protocol
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::Single => 0,
+            Self::Playlist => 1,
+            Self::Random => 2,
+        }
+    }
+
+    /// Get the mode from its player protocol value, if valid
+    #[must_use]
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Single),
+            1 => Some(Self::Playlist),
+            2 => Some(Self::Random),
+            _ => None,
+        }
+    }
+
     pub fn display(self, display_symbol: bool) -> String {
         if display_symbol {
             match self {
@@ -87,6 +126,33 @@ impl Loop {
     }
 }
 
+/// Keep the pitch when playing faster or slower, per media type
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct PreservePitch {
+    pub music: bool,
+    pub podcast: bool,
+}
+
+impl Default for PreservePitch {
+    fn default() -> Self {
+        Self {
+            music: false,
+            podcast: true,
+        }
+    }
+}
+
+impl PreservePitch {
+    /// Whether the pitch of tracks of `media_type` is kept
+    #[must_use]
+    pub fn for_media_type(self, media_type: Option<&MediaType>) -> bool {
+        match media_type {
+            Some(MediaType::Podcast) => self.podcast,
+            _ => self.music,
+        }
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Xywh {
     pub x_between_1_100: u32,
@@ -276,6 +342,48 @@ impl std::fmt::Display for LastPosition {
     }
 }
 
+/// Transport used between the server and its clients
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PlayerProtocol {
+    /// TCP on `player_interface`:`player_port`
+    #[default]
+    Http,
+    /// Unix domain socket at `player_uds_path`, only accessible by the current user
+    Uds,
+}
+
+impl std::fmt::Display for PlayerProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let protocol = match self {
+            Self::Http => "http",
+            Self::Uds => "uds",
+        };
+        write!(f, "{protocol}")
+    }
+}
+
+/// Port of the default profile
+const DEFAULT_PLAYER_PORT: u16 = 50101;
+
+/// Get the default port, which differs per profile so that profiles do not clash by default
+fn default_player_port() -> u16 {
+    let Some(profile) = get_profile() else {
+        return DEFAULT_PLAYER_PORT;
+    };
+
+    // FNV-1a, so that the port of a profile stays the same across versions
+    let hash = profile.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    // always less than 1000
+    #[allow(clippy::cast_possible_truncation)]
+    let offset = (hash % 1000) as u16;
+    DEFAULT_PLAYER_PORT + 1 + offset
+}
+
+/// Filename of the default unix socket in [`get_app_runtime_path`]
+const DEFAULT_UDS_FILENAME: &str = "termusic.socket";
+
 #[derive(Clone, Deserialize, Serialize, Debug)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Settings {
@@ -290,9 +398,24 @@ pub struct Settings {
     pub max_depth_cli: usize,
     pub player_port: u16,
     pub player_interface: IpAddr,
+    pub player_protocol: PlayerProtocol,
+    /// Custom unix socket path, see [`Settings::get_uds_path`] for the default
+    pub player_uds_path: Option<PathBuf>,
+    /// Require clients to send the token generated into the config dir
+    pub player_use_token: bool,
+    /// Interval in seconds in which the server saves playlist, position and volume, `0` to only save on quit
+    pub player_autosave_interval_secs: u64,
     pub player_loop_mode: Loop,
     pub player_volume: u16,
-    pub player_speed: i32,
+    /// Highest volume in percent, above `100` the sound is amplified and softly limited
+    pub player_volume_max: u16,
+    pub player_volume_curve: VolumeCurve,
+    /// Playback speed in percent, `100` is normal speed
+    pub player_speed_percent: i32,
+    /// Percent the speed changes by with speed up and down
+    pub player_speed_step: i32,
+    /// Time stretch instead of resampling when changing the speed, rusty backend only
+    pub player_preserve_pitch: PreservePitch,
     pub player_gapless: bool,
     pub podcast_simultanious_download: usize,
     pub podcast_max_retries: usize,
@@ -303,10 +426,38 @@ pub struct Settings {
     pub playlist_display_symbol: bool,
     pub playlist_select_random_track_quantity: u32,
     pub playlist_select_random_album_quantity: u32,
+    /// Let the server watch the music directories and update the library database on changes,
+    /// instead of a full scan by the tui, changes of `music_dir` apply after a server restart
+    pub library_watch: bool,
+    /// Let the server analyze the loudness of new tracks when scanning the library
+    pub library_analyze_loudness: bool,
+    /// Also write the analyzed loudness as `ReplayGain` tags into the files
+    pub library_loudness_write_tags: bool,
+    /// Percent of a track that has to be played for it to count as played in the play statistics
+    pub library_play_count_percent: u8,
+    /// Also write ratings into the files, as `POPM`, `FMPS_Rating` or `RATING` tags
+    pub library_rating_write_tags: bool,
+    /// Playlists of the tracks matching saved rules, listed in the database view
+    pub library_smart_playlists: Vec<SmartPlaylist>,
     pub theme_selected: String,
     pub kill_daemon_when_quit: bool,
     pub player_use_mpris: bool,
     pub player_use_discord: bool,
+    /// Equalizer applied by the rusty backend
+    pub player_equalizer: EqualizerSettings,
+    /// Loudness normalization by the `ReplayGain` tags of tracks
+    pub player_replaygain: ReplayGainSettings,
+    /// Overlap of consecutive tracks, rusty and `GStreamer` backends only
+    pub player_crossfade: CrossfadeSettings,
+    /// Removal of silence at the start and end of tracks and within podcasts, rusty backend only
+    pub player_silence: SilenceSettings,
+    /// Mono downmix and balance between the left and right channel
+    pub player_channel_mix: ChannelMixSettings,
+    /// Name of the output device of the rusty backend, empty for the default device
+    pub player_output_device: String,
+    /// Play local tracks at their own sample rate and channel count if the output device supports
+    /// it, instead of resampling them, rusty backend only
+    pub player_native_output: bool,
     pub album_photo_xywh: Xywh,
     pub style_color_symbol: StyleColorSymbol,
     pub keys: Keys,
@@ -323,7 +474,11 @@ impl Default for Settings {
             music_dir_from_cli: None,
             player_loop_mode: Loop::Random,
             player_volume: 70,
-            player_speed: 10,
+            player_volume_max: 100,
+            player_volume_curve: VolumeCurve::default(),
+            player_speed_percent: 100,
+            player_speed_step: 10,
+            player_preserve_pitch: PreservePitch::default(),
             player_gapless: true,
             player_remember_last_played_position: LastPosition::Auto,
             enable_exit_confirmation: true,
@@ -334,6 +489,12 @@ impl Default for Settings {
             album_photo_xywh: Xywh::default(),
             playlist_select_random_track_quantity: 20,
             playlist_select_random_album_quantity: 5,
+            library_watch: true,
+            library_analyze_loudness: false,
+            library_loudness_write_tags: false,
+            library_play_count_percent: 50,
+            library_rating_write_tags: false,
+            library_smart_playlists: SmartPlaylist::defaults(),
             disable_album_art_from_cli: false,
             disable_discord_rpc_from_cli: false,
             max_depth_cli: 4,
@@ -344,19 +505,61 @@ impl Default for Settings {
             kill_daemon_when_quit: true,
             player_use_mpris: true,
             player_use_discord: true,
-            player_port: 50101,
+            player_port: default_player_port(),
             player_interface: "::".parse().unwrap(),
+            player_protocol: PlayerProtocol::default(),
+            player_uds_path: None,
+            player_use_token: true,
+            player_autosave_interval_secs: 60,
+            player_equalizer: EqualizerSettings::default(),
+            player_replaygain: ReplayGainSettings::default(),
+            player_crossfade: CrossfadeSettings::default(),
+            player_silence: SilenceSettings::default(),
+            player_channel_mix: ChannelMixSettings::default(),
+            player_output_device: String::new(),
+            player_native_output: false,
         }
     }
 }
 
 impl Settings {
+    /// Get the highest volume in percent, never below `100`
+    #[must_use]
+    pub fn volume_max(&self) -> u16 {
+        self.player_volume_max.max(100)
+    }
+
+    /// Get the music directories with `~` expanded, the one given on the command line first
+    #[must_use]
+    pub fn music_dirs(&self) -> Vec<PathBuf> {
+        self.music_dir_from_cli
+            .iter()
+            .chain(&self.music_dir)
+            .map(|dir| PathBuf::from(shellexpand::tilde(dir).to_string()))
+            .collect()
+    }
+
+    /// Get the unix socket path to use for [`PlayerProtocol::Uds`]
+    ///
+    /// Defaults to `termusic.socket` in the per-user runtime directory.
+    pub fn get_uds_path(&self) -> Result<PathBuf> {
+        if let Some(path) = &self.player_uds_path {
+            return Ok(PathBuf::from(
+                shellexpand::tilde(&path.to_string_lossy()).to_string(),
+            ));
+        }
+
+        let mut path = get_app_runtime_path()?;
+        path.push(DEFAULT_UDS_FILENAME);
+        Ok(path)
+    }
+
     pub fn save(&self) -> Result<()> {
         let mut path = get_app_config_path()?;
         path.push("config.toml");
         let string = toml::to_string(self)?;
 
-        fs::write(path.to_string_lossy().as_ref(), string)?;
+        write_atomic(&path, string.as_bytes())?;
 
         Ok(())
     }
@@ -369,11 +572,71 @@ impl Settings {
             config.save()?;
         }
 
-        let config: Settings = Figment::new()
+        let mut config: Settings = Figment::new()
             .merge(Serialized::defaults(Settings::default()))
-            .merge(Toml::file(path))
+            .merge(Toml::file(&path))
             .extract()?;
+        config.migrate(&Figment::from(Toml::file(&path)));
+        config.validate()?;
         *self = config;
         Ok(())
     }
+
+    /// Apply values only present in the form of older versions in the config `file`
+    fn migrate(&mut self, file: &Figment) {
+        // the speed used to be stored in tenths as "player_speed"
+        if file.find_value("player_speed_percent").is_err() {
+            if let Ok(tenths) = file.extract_inner::<i32>("player_speed") {
+                self.player_speed_percent = tenths * 10;
+            }
+        }
+    }
+
+    /// Reject values the player can not work with
+    fn validate(&self) -> Result<()> {
+        if self.player_speed_step <= 0 {
+            bail!(
+                "player_speed_step needs to be above 0, not {}",
+                self.player_speed_step
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Load the config like [`Settings::load`] does, from the `file` contents
+    fn load(file: &str) -> Settings {
+        let mut config: Settings = Figment::new()
+            .merge(Serialized::defaults(Settings::default()))
+            .merge(Toml::string(file))
+            .extract()
+            .unwrap();
+        config.migrate(&Figment::from(Toml::string(file)));
+        config
+    }
+
+    #[test]
+    fn test_migrate_speed_tenths() {
+        assert_eq!(load("player_speed = 15").player_speed_percent, 150);
+        assert_eq!(load("player_speed = 5").player_speed_percent, 50);
+        // the new field takes precedence over the old one
+        assert_eq!(
+            load("player_speed = 15\nplayer_speed_percent = 125").player_speed_percent,
+            125
+        );
+        assert_eq!(load("").player_speed_percent, 100);
+    }
+
+    #[test]
+    fn test_validate_speed_step() {
+        let mut config = Settings::default();
+        assert!(config.validate().is_ok());
+        config.player_speed_step = 0;
+        assert!(config.validate().is_err());
+    }
 }
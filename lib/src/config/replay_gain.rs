@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use super::Loop;
+
+/// Which `ReplayGain` tags are used to normalize the loudness of tracks
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+    /// Album gain, unless the playlist is played in random order
+    Auto,
+}
+
+impl ReplayGainMode {
+    /// Resolve [`ReplayGainMode::Auto`] to track or album gain for `loop_mode`
+    #[must_use]
+    pub fn resolve(self, loop_mode: Loop) -> Self {
+        match self {
+            Self::Auto if loop_mode == Loop::Random => Self::Track,
+            Self::Auto => Self::Album,
+            mode => mode,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+pub struct ReplayGainSettings {
+    pub mode: ReplayGainMode,
+    /// Added to the gain of every track, in dB
+    pub preamp: f32,
+    /// Lower the gain of tracks whose peak would clip otherwise
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainSettings {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+impl ReplayGainSettings {
+    /// Get these settings with [`ReplayGainMode::Auto`] resolved for `loop_mode`
+    #[must_use]
+    pub fn resolve(self, loop_mode: Loop) -> Self {
+        Self {
+            mode: self.mode.resolve(loop_mode),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::track::MediaType;
+
+/// How silence is removed from a single track
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SilenceTrim {
+    /// Samples with a lower absolute value count as silence
+    pub threshold: f32,
+    /// Pauses within the track are shortened to this, [`None`] to keep them
+    pub max_pause: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+pub struct SilenceSettings {
+    /// Trim the silence at the start and end of music tracks
+    pub trim_music: bool,
+    /// Trim the silence at the start and end of podcast episodes and shorten the pauses within them
+    pub skip_podcast: bool,
+    /// Level in dBFS below which the sound counts as silence
+    pub threshold_db: f32,
+    /// Pauses within podcast episodes are shortened to this, in milliseconds
+    pub max_pause_ms: u64,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            trim_music: false,
+            skip_podcast: false,
+            threshold_db: -50.0,
+            max_pause_ms: 500,
+        }
+    }
+}
+
+impl SilenceSettings {
+    /// Get how silence is removed from tracks of `media_type`, [`None`] if it is kept
+    ///
+    /// Live radio is never trimmed, as it has no start or end.
+    #[must_use]
+    pub fn for_media_type(&self, media_type: Option<&MediaType>) -> Option<SilenceTrim> {
+        let max_pause = match media_type {
+            Some(MediaType::Music) if self.trim_music => None,
+            Some(MediaType::Podcast) if self.skip_podcast => {
+                Some(Duration::from_millis(self.max_pause_ms))
+            }
+            _ => return None,
+        };
+
+        Some(SilenceTrim {
+            threshold: 10_f32.powf(self.threshold_db / 20.0),
+            max_pause,
+        })
+    }
+}
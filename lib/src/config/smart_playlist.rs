@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// A saved playlist of all library tracks matching its rules, see
+/// [`crate::sqlite::DataBase::get_smart_playlist`]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct SmartPlaylist {
+    pub name: String,
+    /// Match tracks matching any of the rules, instead of all of them
+    #[serde(default)]
+    pub match_any: bool,
+    pub rules: Vec<SmartRule>,
+    /// Order of the tracks, by name if not set
+    #[serde(default)]
+    pub sort: Option<SmartField>,
+    #[serde(default)]
+    pub descending: bool,
+    /// Highest number of tracks, all matching tracks if not set
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// A condition on a field of a track, like `genre is Jazz`
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct SmartRule {
+    pub field: SmartField,
+    pub operator: SmartOperator,
+    /// Text, number, `true` or `false`, durations also as `90s`, `5min` or `1h`
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartField {
+    Artist,
+    Title,
+    Album,
+    Genre,
+    File,
+    Directory,
+    Ext,
+    /// Length of the track in seconds
+    Duration,
+    PlayCount,
+    SkipCount,
+    /// Time of the last play, compared with [`SmartOperator::InLastDays`]
+    LastPlayed,
+    /// Star rating of 0 to [`crate::track::MAX_RATING`]
+    Rating,
+    Favorite,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartOperator {
+    Is,
+    IsNot,
+    Contains,
+    NotContains,
+    Greater,
+    Less,
+    /// Within the last `value` days, never for tracks that were not played
+    InLastDays,
+    /// Not within the last `value` days, including tracks that were never played
+    NotInLastDays,
+}
+
+impl SmartPlaylist {
+    /// Get the example smart playlists of a new config
+    #[must_use]
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self {
+                name: "Favorites".to_string(),
+                match_any: false,
+                rules: vec![SmartRule {
+                    field: SmartField::Favorite,
+                    operator: SmartOperator::Is,
+                    value: "true".to_string(),
+                }],
+                sort: Some(SmartField::Rating),
+                descending: true,
+                limit: None,
+            },
+            Self {
+                name: "Forgotten favorites".to_string(),
+                match_any: false,
+                rules: vec![
+                    SmartRule {
+                        field: SmartField::Rating,
+                        operator: SmartOperator::Greater,
+                        value: "3".to_string(),
+                    },
+                    SmartRule {
+                        field: SmartField::LastPlayed,
+                        operator: SmartOperator::NotInLastDays,
+                        value: "30".to_string(),
+                    },
+                ],
+                sort: Some(SmartField::LastPlayed),
+                descending: false,
+                limit: Some(50),
+            },
+        ]
+    }
+}
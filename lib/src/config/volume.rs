@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Range of the [`VolumeCurve::Logarithmic`] curve in dB
+const LOGARITHMIC_RANGE_DB: f32 = 60.0;
+
+/// How the volume in percent maps to the amplitude of the sound
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum VolumeCurve {
+    #[default]
+    Linear,
+    /// Equal steps in dB over a range of 60dB, closest to how loudness is perceived
+    Logarithmic,
+    /// The cube of the linear volume, finer steps at low volumes
+    Cubic,
+}
+
+impl VolumeCurve {
+    /// Get the factor the samples are multiplied with for `volume` in percent
+    ///
+    /// The curve maps `0..=100` to `0.0..=1.0`, above that the factor grows linearly,
+    /// so `200` doubles the amplitude.
+    #[must_use]
+    pub fn factor(self, volume: u16) -> f32 {
+        let linear = f32::from(volume) / 100.0;
+        if volume == 0 || volume >= 100 {
+            return linear;
+        }
+        match self {
+            Self::Linear => linear,
+            Self::Logarithmic => 10_f32.powf((linear - 1.0) * LOGARITHMIC_RANGE_DB / 20.0),
+            Self::Cubic => linear.powi(3),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Versioned schema changes of the library database.
+//!
+//! The version of a database is stored in its `user_version`. Every schema change is a new
+//! [`Migration`] at the end of [`MIGRATIONS`], released migrations are never changed.
+use rusqlite::{Connection, Result, TransactionBehavior};
+use std::path::Path;
+
+/// A schema change, migrating the database to `version`
+struct Migration {
+    version: u32,
+    sql: &'static str,
+}
+
+/// All schema changes in order of their version
+const MIGRATIONS: &[Migration] = &[
+    // the schema of the releases before the migrations
+    Migration {
+        version: 2,
+        sql: "CREATE TABLE IF NOT EXISTS tracks(
+            id integer primary key,
+            artist TEXT,
+            title TEXT,
+            album TEXT,
+            genre TEXT,
+            file TEXT NOT NULL,
+            duration INTERGER,
+            name TEXT,
+            ext TEXT,
+            directory TEXT,
+            last_modified TEXT,
+            last_position INTERGER
+        );",
+    },
+    // EBU R128 loudness of the tracks, analyzed when they have no ReplayGain tags
+    Migration {
+        version: 3,
+        sql: "ALTER TABLE tracks ADD COLUMN loudness REAL;
+        ALTER TABLE tracks ADD COLUMN true_peak REAL;",
+    },
+    // history of plays (event 0) and skips (event 1) with their unix time
+    Migration {
+        version: 4,
+        sql: "CREATE TABLE IF NOT EXISTS history(
+            id integer primary key,
+            file TEXT NOT NULL,
+            event INTEGER NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS history_file ON history(file);
+        CREATE VIEW IF NOT EXISTS tracks_with_stats AS SELECT
+            tracks.id, artist, title, album, genre, tracks.file, duration, name, ext, directory,
+            last_modified, last_position,
+            COALESCE(stats.play_count, 0) AS play_count,
+            COALESCE(stats.skip_count, 0) AS skip_count,
+            stats.last_played,
+            date(stats.last_played, 'unixepoch', 'localtime') AS last_played_day
+        FROM tracks LEFT JOIN (
+            SELECT file,
+                SUM(event = 0) AS play_count,
+                SUM(event = 1) AS skip_count,
+                MAX(CASE WHEN event = 0 THEN time END) AS last_played
+            FROM history GROUP BY file
+        ) AS stats ON stats.file = tracks.file;",
+    },
+    // star ratings and favorites, like the history kept when a track is removed from the library
+    Migration {
+        version: 5,
+        sql: "CREATE TABLE IF NOT EXISTS ratings(
+            file TEXT PRIMARY KEY,
+            rating INTEGER NOT NULL DEFAULT 0,
+            favorite INTEGER NOT NULL DEFAULT 0
+        );
+        DROP VIEW IF EXISTS tracks_with_stats;
+        CREATE VIEW tracks_with_stats AS SELECT
+            tracks.id, artist, title, album, genre, tracks.file, duration, name, ext, directory,
+            last_modified, last_position,
+            COALESCE(stats.play_count, 0) AS play_count,
+            COALESCE(stats.skip_count, 0) AS skip_count,
+            stats.last_played,
+            date(stats.last_played, 'unixepoch', 'localtime') AS last_played_day,
+            COALESCE(ratings.rating, 0) AS rating,
+            COALESCE(ratings.favorite, 0) AS favorite
+        FROM tracks LEFT JOIN (
+            SELECT file,
+                SUM(event = 0) AS play_count,
+                SUM(event = 1) AS skip_count,
+                MAX(CASE WHEN event = 0 THEN time END) AS last_played
+            FROM history GROUP BY file
+        ) AS stats ON stats.file = tracks.file
+        LEFT JOIN ratings ON ratings.file = tracks.file;",
+    },
+];
+
+/// The version of a fully migrated database
+pub const DB_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Get the schema version of the database
+pub fn user_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("SELECT user_version FROM pragma_user_version", [], |r| {
+        r.get(0)
+    })
+}
+
+/// Run all migrations newer than the version of the database, in one transaction
+///
+/// A database file with data is first copied to `<file>.v<version>.bak`, while the transaction
+/// keeps other connections from writing to it. The migration continues if that fails.
+///
+/// The tracks of databases older than the first migration are dropped, as their schema is
+/// unknown, and read again from the music directory.
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    // read again within the transaction, another process may have migrated it meanwhile
+    let mut version = user_version(&tx)?;
+    if version > DB_VERSION {
+        warn!("library database version {version} is newer than the supported {DB_VERSION}");
+        return Ok(());
+    }
+    if version == DB_VERSION {
+        return Ok(());
+    }
+    if version > 0 {
+        backup(&tx);
+    }
+
+    if version < MIGRATIONS[0].version {
+        tx.execute("DROP TABLE IF EXISTS tracks", [])?;
+    }
+    let start = version;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > start) {
+        info!(
+            "migrating library database to version {}",
+            migration.version
+        );
+        tx.execute_batch(migration.sql)?;
+        version = migration.version;
+    }
+    tx.pragma_update(None, "user_version", version)?;
+    tx.commit()
+}
+
+/// Copy the database file of `conn` next to it, logging errors
+fn backup(conn: &Connection) {
+    let Some(path) = conn.path().filter(|path| !path.is_empty()) else {
+        return;
+    };
+    let version = user_version(conn).unwrap_or_default();
+    let path = Path::new(path);
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(format!(".v{version}.bak"));
+    info!(
+        "backing up library database to {}",
+        backup_path.to_string_lossy()
+    );
+    if let Err(e) = std::fs::copy(path, &backup_path) {
+        error!(
+            "Error backing up library database to {}: {e}",
+            backup_path.to_string_lossy()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn track_count(conn: &Connection) -> u32 {
+        conn.query_row("SELECT COUNT(*) FROM tracks", [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn should_migrate_new_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), DB_VERSION);
+        assert_eq!(track_count(&conn), 0);
+    }
+
+    #[test]
+    fn should_keep_tracks_of_migrated_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO tracks (file, last_position) VALUES ('a.mp3', 42)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), DB_VERSION);
+        assert_eq!(track_count(&conn), 1);
+    }
+
+    #[test]
+    fn should_keep_tracks_of_released_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "{}
+            INSERT INTO tracks (file, last_position) VALUES ('a.mp3', 42);
+            PRAGMA user_version = 2;",
+            MIGRATIONS[0].sql
+        ))
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), DB_VERSION);
+        assert_eq!(track_count(&conn), 1);
+        let position: u32 = conn
+            .query_row("SELECT last_position FROM tracks", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(position, 42);
+    }
+
+    #[test]
+    fn should_drop_tracks_of_unknown_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tracks(id integer primary key, file TEXT);
+            INSERT INTO tracks (file) VALUES ('a.mp3');
+            PRAGMA user_version = 1;",
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), DB_VERSION);
+        assert_eq!(track_count(&conn), 0);
+    }
+}
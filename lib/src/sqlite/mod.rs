@@ -0,0 +1,1021 @@
+/**
+ * MIT License
+ *
+ * termusic - Copyright (c) 2021 Larry Hao
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+// database
+mod migrations;
+mod smart_playlist;
+
+use crate::config::{Settings, SmartPlaylist};
+use crate::track::{Loudness, Track, MAX_RATING};
+use crate::utils::{filetype_supported, get_app_config_path, get_parent_folder, get_pin_yin};
+use chrono::{DateTime, Local, Utc};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, Error, Result, Row};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct DataBase {
+    conn: Arc<Mutex<Connection>>,
+    max_depth: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct TrackForDB {
+    pub id: u64,
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub genre: String,
+    pub file: String,
+    pub duration: Duration,
+    pub name: String,
+    pub ext: String,
+    pub directory: String,
+    pub last_modified: String,
+    pub last_position: Duration,
+    /// How often the track was played, see [`DataBase::add_history`]
+    pub play_count: u32,
+    /// How often the track was skipped before it counted as played
+    pub skip_count: u32,
+    /// Unix time in seconds of the last play
+    pub last_played: Option<i64>,
+    /// Star rating of 0 to [`MAX_RATING`], 0 if unrated
+    pub rating: u8,
+    pub favorite: bool,
+}
+
+impl TrackForDB {
+    /// Get the local date of the last play, empty if never played
+    #[must_use]
+    pub fn last_played_formatted(&self) -> String {
+        self.last_played
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+
+    /// Get the rating as stars like `★★★☆☆`, with a trailing `♥` for favorites
+    #[must_use]
+    pub fn rating_formatted(&self) -> String {
+        let rating = usize::from(self.rating.min(MAX_RATING));
+        let mut stars = "★".repeat(rating) + &"☆".repeat(usize::from(MAX_RATING) - rating);
+        if self.favorite {
+            stars.push('♥');
+        }
+        stars
+    }
+}
+
+/// Kind of an entry in the play history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryEvent {
+    /// The track played long enough to count as played
+    Play,
+    /// The track was skipped before it counted as played
+    Skip,
+}
+
+impl HistoryEvent {
+    const fn as_u32(self) -> u32 {
+        match self {
+            Self::Play => 0,
+            Self::Skip => 1,
+        }
+    }
+}
+
+/// Tracks selected by their play statistics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartSelection {
+    MostPlayed,
+    RecentlyPlayed,
+    /// Random tracks that were never played
+    NeverPlayed,
+}
+
+/// Order of the tracks of a search result
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrackSort {
+    #[default]
+    Name,
+    PlayCount,
+    SkipCount,
+    LastPlayed,
+}
+
+impl TrackSort {
+    /// Get the order after this one, back to [`TrackSort::Name`] after the last one
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Name => Self::PlayCount,
+            Self::PlayCount => Self::SkipCount,
+            Self::SkipCount => Self::LastPlayed,
+            Self::LastPlayed => Self::Name,
+        }
+    }
+
+    /// Sort `tracks` by this order, the statistics descending
+    pub fn sort(self, tracks: &mut [TrackForDB]) {
+        match self {
+            Self::Name => tracks.sort_by_cached_key(|k| get_pin_yin(&k.name)),
+            Self::PlayCount => tracks.sort_by_key(|k| std::cmp::Reverse(k.play_count)),
+            Self::SkipCount => tracks.sort_by_key(|k| std::cmp::Reverse(k.skip_count)),
+            Self::LastPlayed => tracks.sort_by_key(|k| std::cmp::Reverse(k.last_played)),
+        }
+    }
+}
+
+impl std::fmt::Display for TrackSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name => write!(f, "name"),
+            Self::PlayCount => write!(f, "plays"),
+            Self::SkipCount => write!(f, "skips"),
+            Self::LastPlayed => write!(f, "last played"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub enum SearchCriteria {
+    Artist,
+    Album,
+    Genre,
+    Directory,
+    Playlist,
+    PlayCount,
+    /// Day of the last play
+    LastPlayed,
+    /// Names of the [`SmartPlaylist`]s of the config
+    SmartPlaylist,
+}
+
+impl From<usize> for SearchCriteria {
+    fn from(u_index: usize) -> Self {
+        match u_index {
+            1 => Self::Album,
+            2 => Self::Genre,
+            3 => Self::Directory,
+            4 => Self::Playlist,
+            5 => Self::PlayCount,
+            6 => Self::LastPlayed,
+            7 => Self::SmartPlaylist,
+            _ => Self::Artist,
+            // 0 | _ => Self::Artist,
+        }
+    }
+}
+
+impl std::fmt::Display for SearchCriteria {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Artist => write!(f, "artist"),
+            Self::Album => write!(f, "album"),
+            Self::Genre => write!(f, "genre"),
+            Self::Directory => write!(f, "directory"),
+            Self::Playlist => write!(f, "playlist"),
+            Self::PlayCount => write!(f, "play_count"),
+            Self::LastPlayed => write!(f, "last_played_day"),
+            Self::SmartPlaylist => write!(f, "smart_playlist"),
+        }
+    }
+}
+
+impl DataBase {
+    /// Open the library database, migrating it to the current schema first
+    ///
+    /// A database with data is copied to `library.db.v<version>.bak` before it is migrated.
+    ///
+    /// # Panics
+    ///
+    /// - if app config path creation fails
+    /// - if any required database operation fails
+    pub fn new(config: &Settings) -> Self {
+        let mut db_path = get_app_config_path().expect("failed to get app configuration path");
+        db_path.push("library.db");
+        let mut conn = Connection::open(db_path).expect("open db failed");
+        // the tui and the server may open the database at the same time
+        conn.busy_timeout(Duration::from_secs(5))
+            .expect("set busy timeout error");
+
+        let user_version = migrations::user_version(&conn).expect("get user_version error");
+        if user_version < migrations::DB_VERSION {
+            migrations::migrate(&mut conn).expect("library database migration failed");
+        }
+
+        let max_depth = config.max_depth_cli;
+
+        let conn = Arc::new(Mutex::new(conn));
+        Self { conn, max_depth }
+    }
+
+    fn add_records(conn: &Arc<Mutex<Connection>>, tracks: Vec<Track>) -> Result<()> {
+        let mut conn = conn.lock();
+        let tx = conn.transaction()?;
+
+        for track in tracks {
+            let file = track.file().unwrap_or("Unknown File");
+            let duration = track.duration().as_secs();
+            let last_modified = track
+                .last_modified
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .to_string();
+            let values = params![
+                track.artist().unwrap_or("Unknown Artist"),
+                track.title().unwrap_or("Unknown Title"),
+                track.album().unwrap_or("empty"),
+                track.genre().unwrap_or("no type"),
+                file,
+                duration,
+                track.name().unwrap_or_default(),
+                track.ext().unwrap_or_default(),
+                track.directory().unwrap_or_default(),
+                last_modified,
+            ];
+
+            // a modified file keeps its last position and loudness analysis
+            let updated = tx.execute(
+                "UPDATE tracks SET artist = ?1, title = ?2, album = ?3, genre = ?4, duration = ?6, name = ?7, ext = ?8, directory = ?9, last_modified = ?10
+                WHERE file = ?5",
+                values,
+            )?;
+            if updated > 0 {
+                continue;
+            }
+
+            tx.execute(
+                "INSERT INTO tracks (artist, title, album, genre, file, duration, name, ext, directory, last_modified, last_position)
+                values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0)",
+                values,
+            )?;
+            // a rating from the tags of a new track, the stored one always wins
+            if let Some(rating) = track.rating() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO ratings (file, rating) VALUES (?1, ?2)",
+                    params![file, rating],
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Whether the file at `path` changed since its tracks were read, `false` if it is gone
+    fn need_update(conn: &Arc<Mutex<Connection>>, path: &Path) -> Result<bool> {
+        let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) else {
+            // removed since the directory was read, which is handled as a removal
+            return Ok(false);
+        };
+        let timestamp_u64 = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let conn = conn.lock();
+        let filename = path
+            .file_name()
+            .ok_or_else(|| Error::InvalidParameterName("file name missing".to_string()))?
+            .to_string_lossy();
+        let mut stmt = conn.prepare("SELECT last_modified FROM tracks WHERE name = ? ")?;
+        let rows = stmt.query_map([filename], |row| {
+            let last_modified: String = row.get(0)?;
+
+            Ok(last_modified)
+        })?;
+
+        for r in rows.flatten() {
+            // a track with an unreadable time is read again
+            if r.parse::<u64>().is_ok_and(|r_u64| timestamp_u64 <= r_u64) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn need_delete(conn: &Arc<Mutex<Connection>>) -> Result<Vec<String>> {
+        let conn = conn.lock();
+        let mut stmt = conn.prepare("SELECT file FROM tracks")?;
+        let track_vec: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .flatten()
+            .filter(|file: &String| !Path::new(file).exists())
+            .collect();
+        Ok(track_vec)
+    }
+
+    fn delete_records(conn: &Arc<Mutex<Connection>>, tracks: Vec<String>) -> Result<()> {
+        let mut conn = conn.lock();
+        let tx = conn.transaction()?;
+
+        for track in tracks {
+            tx.execute("DELETE FROM tracks WHERE file = ?", params![track])?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn sync_database(&mut self, path: &Path) {
+        let conn = self.conn.clone();
+        let path = path.to_path_buf();
+        let max_depth = self.max_depth;
+
+        std::thread::spawn(move || Self::sync_records(&conn, &path, max_depth));
+    }
+
+    /// Like [`DataBase::sync_database`], but only returns once done
+    ///
+    /// # Errors
+    ///
+    /// if any database operation fails
+    pub fn sync_database_blocking(&mut self, path: &Path) -> Result<()> {
+        Self::sync_records(&self.conn, path, self.max_depth)
+    }
+
+    /// Read the new and modified tracks at or below `path`
+    fn tracks_to_update(
+        conn: &Arc<Mutex<Connection>>,
+        path: &Path,
+        max_depth: usize,
+    ) -> Vec<Track> {
+        let mut track_vec: Vec<Track> = vec![];
+        let all_items = walkdir::WalkDir::new(path)
+            .follow_links(true)
+            .max_depth(max_depth);
+
+        for record in all_items
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|f| f.file_type().is_file())
+            .filter(|f| filetype_supported(&f.path().to_string_lossy()))
+        {
+            match Self::need_update(conn, record.path()) {
+                Ok(true) => {
+                    if let Ok(track) = Track::read_from_path(record.path(), true) {
+                        track_vec.push(track);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!("Error in need_update: {e}");
+                }
+            }
+        }
+        track_vec
+    }
+
+    fn sync_records(conn: &Arc<Mutex<Connection>>, path: &Path, max_depth: usize) -> Result<()> {
+        // add updated records
+        let track_vec = Self::tracks_to_update(conn, path, max_depth);
+        if !track_vec.is_empty() {
+            Self::add_records(conn, track_vec)?;
+        }
+
+        // delete records where local file are missing
+
+        match Self::need_delete(conn) {
+            Ok(string_vec) => {
+                if !string_vec.is_empty() {
+                    Self::delete_records(conn, string_vec)?;
+                }
+            }
+            Err(e) => {
+                error!("Error in need_delete: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply changes of files and directories below the library root `root`, instead of a full
+    /// [`DataBase::sync_database`]
+    ///
+    /// Existing `paths` are added or updated, directories with their content up to the depth a full
+    /// sync would read, missing `paths` are removed with everything that was below them. Returns
+    /// the number of changed tracks.
+    ///
+    /// # Errors
+    ///
+    /// if any database operation fails
+    pub fn update_paths(&mut self, root: &Path, paths: &[PathBuf]) -> Result<usize> {
+        let mut track_vec = Vec::new();
+        let mut missing = Vec::new();
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let depth = relative.components().count();
+            if depth > self.max_depth {
+                continue;
+            }
+            if path.exists() {
+                track_vec.extend(Self::tracks_to_update(
+                    &self.conn,
+                    path,
+                    self.max_depth - depth,
+                ));
+            } else {
+                missing.push(path.as_path());
+            }
+        }
+
+        let mut changed = track_vec.len();
+        if !track_vec.is_empty() {
+            Self::add_records(&self.conn, track_vec)?;
+        }
+        let conn = self.conn.lock();
+        for path in missing {
+            let path = path.to_string_lossy();
+            changed += conn.execute(
+                "DELETE FROM tracks WHERE file = ?1 OR file LIKE ?2 ESCAPE '\\'",
+                params![path, like_prefix(&path)],
+            )?;
+        }
+        Ok(changed)
+    }
+
+    /// Move the tracks at or below `from` to `to`, keeping their play history, rating and
+    /// loudness, then read the tags at `to` again if needed. Returns the number of changed tracks.
+    ///
+    /// # Errors
+    ///
+    /// if any database operation fails
+    pub fn rename_path(&mut self, root: &Path, from: &Path, to: &Path) -> Result<usize> {
+        if to.is_file() && !filetype_supported(&to.to_string_lossy()) {
+            return self.update_paths(root, &[from.to_path_buf(), to.to_path_buf()]);
+        }
+
+        let to_file = to
+            .is_file()
+            .then(|| {
+                to.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .flatten();
+        let renamed = {
+            let mut conn = self.conn.lock();
+            let tx = conn.transaction()?;
+            let from = from.to_string_lossy();
+            let to = to.to_string_lossy();
+            let prefix = like_prefix(&from);
+            let values = params![from, to, prefix];
+            // tracks replaced by the renamed ones
+            tx.execute(
+                "DELETE FROM tracks WHERE (file = ?2 OR file LIKE ?4 ESCAPE '\\')
+                AND EXISTS (SELECT 1 FROM tracks WHERE file = ?1 OR file LIKE ?3 ESCAPE '\\')",
+                params![from, to, prefix, like_prefix(&to)],
+            )?;
+            let renamed = tx.execute(
+                "UPDATE tracks SET file = ?2 || substr(file, length(?1) + 1)
+                WHERE file = ?1 OR file LIKE ?3 ESCAPE '\\'",
+                values,
+            )?;
+            tx.execute(
+                "UPDATE tracks SET directory = ?2 || substr(directory, length(?1) + 1)
+                WHERE directory = ?1 OR directory LIKE ?3 ESCAPE '\\'",
+                values,
+            )?;
+            if let Some(name) = to_file {
+                tx.execute(
+                    "UPDATE tracks SET name = ?1, directory = ?2 WHERE file = ?3",
+                    params![name, get_parent_folder(&to), to],
+                )?;
+            }
+            for table in ["history", "ratings"] {
+                tx.execute(
+                    &format!(
+                        "UPDATE OR REPLACE {table} SET file = ?2 || substr(file, length(?1) + 1)
+                        WHERE file = ?1 OR file LIKE ?3 ESCAPE '\\'"
+                    ),
+                    values,
+                )?;
+            }
+            tx.commit()?;
+            renamed
+        };
+
+        // the name and directory of a renamed file, and tracks not yet in the database
+        Ok(renamed.max(self.update_paths(root, &[to.to_path_buf()])?))
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_all_records(&mut self) -> Result<Vec<TrackForDB>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT * FROM tracks_with_stats")?;
+        let vec: Vec<TrackForDB> = stmt
+            .query_map([], |row| Ok(Self::track_db(row)))?
+            .flatten()
+            .collect();
+        Ok(vec)
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_record_by_criteria(
+        &mut self,
+        str: &str,
+        cri: &SearchCriteria,
+    ) -> Result<Vec<TrackForDB>> {
+        let search_str = format!("SELECT * FROM tracks_with_stats WHERE CAST({cri} AS TEXT) = ?");
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&search_str)?;
+
+        let mut vec_records: Vec<TrackForDB> = stmt
+            .query_map([str], |row| Ok(Self::track_db(row)))?
+            .flatten()
+            .collect();
+
+        // Left for debug
+        // error!("str: {}", str);
+        // error!("cri: {}", cri);
+        // error!("vec: {:?}", vec_records);
+
+        vec_records.sort_by_cached_key(|k| get_pin_yin(&k.name));
+        Ok(vec_records)
+    }
+
+    fn track_db(row: &Row<'_>) -> TrackForDB {
+        let d_u64: u64 = row.get(6).unwrap();
+        let last_position_u64: u64 = row.get(11).unwrap();
+        TrackForDB {
+            // id: row.get(0).unwrap(),
+            id: row.get_unwrap(0),
+            artist: row.get_unwrap(1),
+            title: row.get_unwrap(2),
+            album: row.get(3).unwrap(),
+            genre: row.get(4).unwrap(),
+            file: row.get(5).unwrap(),
+            duration: Duration::from_secs(d_u64),
+            name: row.get(7).unwrap(),
+            ext: row.get(8).unwrap(),
+            directory: row.get(9).unwrap(),
+            last_modified: row.get(10).unwrap(),
+            last_position: Duration::from_secs(last_position_u64),
+            play_count: row.get(12).unwrap(),
+            skip_count: row.get(13).unwrap(),
+            last_played: row.get(14).unwrap(),
+            rating: row.get(16).unwrap(),
+            favorite: row.get(17).unwrap(),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_criterias(&mut self, cri: &SearchCriteria) -> Result<Vec<String>> {
+        let search_str = format!("SELECT DISTINCT CAST({cri} AS TEXT) FROM tracks_with_stats");
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&search_str)?;
+
+        let mut vec: Vec<String> = stmt
+            .query_map([], |row| {
+                let criteria: String = row.get(0)?;
+                Ok(criteria)
+            })?
+            .flatten()
+            .collect();
+
+        match cri {
+            SearchCriteria::PlayCount => {
+                vec.sort_by_key(|k| std::cmp::Reverse(k.parse::<u32>().unwrap_or_default()));
+            }
+            // dates, the most recent first
+            SearchCriteria::LastPlayed => vec.sort_by(|a, b| b.cmp(a)),
+            _ => vec.sort_by_cached_key(|k| get_pin_yin(k)),
+        }
+        Ok(vec)
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_last_position(&mut self, track: &Track) -> Result<Duration> {
+        let query = "SELECT last_position FROM tracks WHERE name = ?1";
+
+        let mut last_position: Duration = Duration::from_secs(0);
+        let conn = self.conn.lock();
+        conn.query_row(
+            query,
+            params![track.name().unwrap_or("Unknown File").to_string(),],
+            |row| {
+                let last_position_u64: u64 = row.get(0)?;
+                // error!("last_position_u64 is {last_position_u64}");
+                last_position = Duration::from_secs(last_position_u64);
+                Ok(last_position)
+            },
+        )?;
+        // .expect("get last position failed.");
+        // error!("get last pos as {}", last_position.as_secs());
+        Ok(last_position)
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn set_last_position(&mut self, track: &Track, last_position: Duration) {
+        let query = "UPDATE tracks SET last_position = ?1 WHERE name = ?2";
+        let conn = self.conn.lock();
+        conn.execute(
+            query,
+            params![
+                last_position.as_secs(),
+                track.name().unwrap_or("Unknown File Name").to_string(),
+            ],
+        )
+        .expect("update last position failed.");
+        // error!("set last position as {}", last_position.as_secs());
+    }
+
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_record_by_path(&mut self, str: &str) -> Result<TrackForDB> {
+        let search_str = "SELECT * FROM tracks_with_stats WHERE file = ?";
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(search_str)?;
+
+        let vec_records: Vec<TrackForDB> = stmt
+            .query_map([str], |row| Ok(Self::track_db(row)))?
+            .flatten()
+            .collect();
+
+        // Left for debug
+        // error!("str: {}", str);
+        // error!("cri: {}", cri);
+        if let Some(record) = vec_records.first() {
+            return Ok(record.clone());
+        }
+
+        Err(Error::QueryReturnedNoRows)
+    }
+
+    /// Get the stored loudness analysis of the track at `file`, if it was analyzed
+    ///
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_loudness(&mut self, file: &str) -> Option<Loudness> {
+        let query =
+            "SELECT loudness, true_peak FROM tracks WHERE file = ?1 AND loudness IS NOT NULL";
+        let conn = self.conn.lock();
+        conn.query_row(query, params![file], |row| {
+            Ok(Loudness {
+                integrated: row.get(0)?,
+                true_peak: row.get(1)?,
+            })
+        })
+        .ok()
+    }
+
+    /// Store the loudness analysis of the track at `file`
+    ///
+    /// # Errors
+    ///
+    /// if the database update fails
+    pub fn set_loudness(&mut self, file: &str, loudness: Loudness) -> Result<()> {
+        let query = "UPDATE tracks SET loudness = ?1, true_peak = ?2 WHERE file = ?3";
+        let conn = self.conn.lock();
+        conn.execute(
+            query,
+            params![loudness.integrated, loudness.true_peak, file],
+        )?;
+        Ok(())
+    }
+
+    /// Get the files of all tracks at or below `path`, only the not yet analyzed ones unless `all`
+    ///
+    /// # Errors
+    ///
+    /// if the database query fails
+    pub fn get_files_to_analyze(&mut self, path: &Path, all: bool) -> Result<Vec<String>> {
+        let query = if all {
+            "SELECT file FROM tracks WHERE file = ?1 OR file LIKE ?2 ESCAPE '\\'"
+        } else {
+            "SELECT file FROM tracks WHERE (file = ?1 OR file LIKE ?2 ESCAPE '\\') AND loudness IS NULL"
+        };
+        let path = path.to_string_lossy();
+        let prefix = like_prefix(&path);
+
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(query)?;
+        let files: Vec<String> = stmt
+            .query_map(params![path, prefix], |row| row.get(0))?
+            .flatten()
+            .collect();
+        Ok(files)
+    }
+
+    /// Add `event` of the track at `file` to the play history
+    ///
+    /// # Errors
+    ///
+    /// if the database insert fails
+    pub fn add_history(&mut self, file: &str, event: HistoryEvent) -> Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT INTO history (file, event, time) VALUES (?1, ?2, ?3)",
+            params![file, event.as_u32(), time],
+        )?;
+        Ok(())
+    }
+
+    /// Get the star rating and the favorite flag of the track at `file`, unrated if not stored
+    ///
+    /// # Panics
+    ///
+    /// if the connection is unavailable
+    pub fn get_rating(&mut self, file: &str) -> (u8, bool) {
+        let conn = self.conn.lock();
+        conn.query_row(
+            "SELECT rating, favorite FROM ratings WHERE file = ?1",
+            params![file],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, false))
+    }
+
+    /// Set the star rating of the track at `file`, at most [`MAX_RATING`]
+    ///
+    /// # Errors
+    ///
+    /// if the database update fails
+    pub fn set_rating(&mut self, file: &str, rating: u8) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT INTO ratings (file, rating) VALUES (?1, ?2)
+            ON CONFLICT(file) DO UPDATE SET rating = excluded.rating",
+            params![file, rating.min(MAX_RATING)],
+        )?;
+        Ok(())
+    }
+
+    /// Flip the favorite flag of the track at `file`, returning the new flag
+    ///
+    /// # Errors
+    ///
+    /// if the database update fails
+    pub fn toggle_favorite(&mut self, file: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+        conn.query_row(
+            "INSERT INTO ratings (file, favorite) VALUES (?1, 1)
+            ON CONFLICT(file) DO UPDATE SET favorite = NOT favorite
+            RETURNING favorite",
+            params![file],
+            |row| row.get(0),
+        )
+    }
+
+    /// Get the tracks matching the rules of `playlist`, in its order
+    ///
+    /// # Errors
+    ///
+    /// if a rule is invalid or the database query fails
+    pub fn get_smart_playlist(
+        &mut self,
+        playlist: &SmartPlaylist,
+    ) -> anyhow::Result<Vec<TrackForDB>> {
+        let conn = self.conn.lock();
+        smart_playlist::get_tracks(&conn, playlist)
+    }
+
+    /// Get up to `quantity` tracks of `selection`
+    ///
+    /// # Errors
+    ///
+    /// if the database query fails
+    pub fn get_smart_selection(
+        &mut self,
+        selection: SmartSelection,
+        quantity: u32,
+    ) -> Result<Vec<TrackForDB>> {
+        let query = match selection {
+            SmartSelection::MostPlayed => {
+                "SELECT * FROM tracks_with_stats WHERE play_count > 0
+                ORDER BY play_count DESC, last_played DESC LIMIT ?"
+            }
+            SmartSelection::RecentlyPlayed => {
+                "SELECT * FROM tracks_with_stats WHERE last_played IS NOT NULL
+                ORDER BY last_played DESC LIMIT ?"
+            }
+            SmartSelection::NeverPlayed => {
+                "SELECT * FROM tracks_with_stats WHERE play_count = 0 ORDER BY RANDOM() LIMIT ?"
+            }
+        };
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(query)?;
+        let vec: Vec<TrackForDB> = stmt
+            .query_map([quantity], |row| Ok(Self::track_db(row)))?
+            .flatten()
+            .collect();
+        Ok(vec)
+    }
+}
+
+/// Escape the wildcards of `value` for a `LIKE` with `ESCAPE '\'`
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Get the `LIKE` pattern of everything below the directory `path`
+fn like_prefix(path: &str) -> String {
+    format!("{}/%", escape_like(path.trim_end_matches('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// A music directory in the temp dir, removed again when dropped
+    struct MusicDir(PathBuf);
+
+    impl MusicDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("termusic-test-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn join(&self, path: &str) -> PathBuf {
+            self.0.join(path)
+        }
+    }
+
+    impl Drop for MusicDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn database() -> DataBase {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&mut conn).unwrap();
+        DataBase {
+            conn: Arc::new(Mutex::new(conn)),
+            max_depth: 4,
+        }
+    }
+
+    /// Create an empty file at `path` and add it as a track with a play and a rating
+    ///
+    /// The track is newer than the file, so its tags are not read again.
+    fn add_track(db: &DataBase, path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, []).unwrap();
+        let file = path.to_string_lossy();
+        let conn = db.conn.lock();
+        conn.execute(
+            "INSERT INTO tracks (file, name, directory, last_modified, last_position)
+            VALUES (?1, ?2, ?3, ?4, 0)",
+            params![
+                file,
+                path.file_name().unwrap().to_string_lossy(),
+                path.parent().unwrap().to_string_lossy(),
+                u64::MAX.to_string(),
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO history (file, event, time) VALUES (?1, 0, 0)",
+            [&file],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO ratings (file, rating) VALUES (?1, 3)", [&file])
+            .unwrap();
+    }
+
+    /// Get the sorted `column` of `table`
+    fn column(db: &DataBase, table: &str, column: &str) -> Vec<String> {
+        let conn = db.conn.lock();
+        let mut stmt = conn
+            .prepare(&format!("SELECT {column} FROM {table} ORDER BY {column}"))
+            .unwrap();
+        let values = stmt.query_map([], |row| row.get(0)).unwrap();
+        values.flatten().collect()
+    }
+
+    fn paths(paths: &[PathBuf]) -> Vec<String> {
+        let mut paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn should_rename_file() {
+        let music = MusicDir::new("rename-file");
+        let mut db = database();
+        let (from, to) = (music.join("album/a.mp3"), music.join("album/b.mp3"));
+        add_track(&db, &from);
+        std::fs::rename(&from, &to).unwrap();
+
+        assert_eq!(db.rename_path(&music.0, &from, &to).unwrap(), 1);
+        let expected = paths(&[to]);
+        assert_eq!(column(&db, "tracks", "file"), expected);
+        assert_eq!(column(&db, "tracks", "name"), vec!["b.mp3"]);
+        assert_eq!(column(&db, "history", "file"), expected);
+        assert_eq!(column(&db, "ratings", "file"), expected);
+    }
+
+    #[test]
+    fn should_rename_directory_without_siblings() {
+        let music = MusicDir::new("rename-dir");
+        let mut db = database();
+        // "_" is a wildcard of LIKE, and "a_b2" starts with "a_b"
+        for file in ["a_b/1.mp3", "a_b/cd/2.mp3", "aXb/3.mp3", "a_b2/4.mp3"] {
+            add_track(&db, &music.join(file));
+        }
+        std::fs::rename(music.join("a_b"), music.join("c")).unwrap();
+
+        let renamed = db
+            .rename_path(&music.0, &music.join("a_b"), &music.join("c"))
+            .unwrap();
+        assert_eq!(renamed, 2);
+        let expected = paths(&[
+            music.join("c/1.mp3"),
+            music.join("c/cd/2.mp3"),
+            music.join("aXb/3.mp3"),
+            music.join("a_b2/4.mp3"),
+        ]);
+        assert_eq!(column(&db, "tracks", "file"), expected);
+        assert_eq!(
+            column(&db, "tracks", "directory"),
+            paths(&[
+                music.join("c"),
+                music.join("c/cd"),
+                music.join("aXb"),
+                music.join("a_b2"),
+            ])
+        );
+        assert_eq!(column(&db, "history", "file"), expected);
+        assert_eq!(column(&db, "ratings", "file"), expected);
+    }
+
+    #[test]
+    fn should_remove_deleted_paths() {
+        let music = MusicDir::new("delete");
+        let mut db = database();
+        for file in ["a/1.mp3", "a/b/2.mp3", "a2/3.mp3", "4.mp3", "5.mp3"] {
+            add_track(&db, &music.join(file));
+        }
+        std::fs::remove_dir_all(music.join("a")).unwrap();
+        std::fs::remove_file(music.join("4.mp3")).unwrap();
+
+        let removed = db
+            .update_paths(&music.0, &[music.join("a"), music.join("4.mp3")])
+            .unwrap();
+        assert_eq!(removed, 3);
+        assert_eq!(
+            column(&db, "tracks", "file"),
+            paths(&[music.join("a2/3.mp3"), music.join("5.mp3")])
+        );
+        // like the rating, the history is kept in case the track comes back
+        assert_eq!(column(&db, "history", "file").len(), 5);
+    }
+
+    #[test]
+    fn should_skip_vanished_files() {
+        let music = MusicDir::new("vanished");
+        assert!(!DataBase::need_update(&database().conn, &music.join("gone.mp3")).unwrap());
+    }
+}
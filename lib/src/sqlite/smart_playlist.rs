@@ -0,0 +1,276 @@
+//! Queries for the tracks of a [`SmartPlaylist`].
+//!
+//! The rules become the `WHERE` clause of a query on `tracks_with_stats`, with their values as
+//! parameters. Text is compared case-insensitively.
+use super::{escape_like, DataBase, TrackForDB};
+use crate::config::{SmartField, SmartOperator, SmartPlaylist, SmartRule};
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::fmt::Write;
+
+const SECS_PER_DAY: i64 = 86400;
+
+impl SmartField {
+    const fn column(self) -> &'static str {
+        match self {
+            Self::Artist => "artist",
+            Self::Title => "title",
+            Self::Album => "album",
+            Self::Genre => "genre",
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Ext => "ext",
+            Self::Duration => "duration",
+            Self::PlayCount => "play_count",
+            Self::SkipCount => "skip_count",
+            Self::LastPlayed => "last_played",
+            Self::Rating => "rating",
+            Self::Favorite => "favorite",
+        }
+    }
+
+    const fn is_text(self) -> bool {
+        matches!(
+            self,
+            Self::Artist
+                | Self::Title
+                | Self::Album
+                | Self::Genre
+                | Self::File
+                | Self::Directory
+                | Self::Ext
+        )
+    }
+}
+
+/// Get the tracks of `playlist`
+pub fn get_tracks(conn: &Connection, playlist: &SmartPlaylist) -> Result<Vec<TrackForDB>> {
+    let (query, values) = build_query(playlist)?;
+    let mut stmt = conn.prepare(&query)?;
+    let vec: Vec<TrackForDB> = stmt
+        .query_map(params_from_iter(values), |row| Ok(DataBase::track_db(row)))?
+        .flatten()
+        .collect();
+    Ok(vec)
+}
+
+/// Build the query of `playlist` and its parameters
+fn build_query(playlist: &SmartPlaylist) -> Result<(String, Vec<Value>)> {
+    let mut query = String::from("SELECT * FROM tracks_with_stats");
+    let mut values = Vec::new();
+
+    let mut conditions = Vec::with_capacity(playlist.rules.len());
+    for rule in &playlist.rules {
+        let condition = build_condition(rule, &mut values)
+            .with_context(|| format!("invalid rule in smart playlist \"{}\"", playlist.name))?;
+        conditions.push(condition);
+    }
+    if !conditions.is_empty() {
+        let separator = if playlist.match_any { " OR " } else { " AND " };
+        query.push_str(" WHERE ");
+        query.push_str(&conditions.join(separator));
+    }
+
+    let order = if playlist.descending { "DESC" } else { "ASC" };
+    let sort = playlist.sort.map_or("name", SmartField::column);
+    write!(query, " ORDER BY {sort} {order}, name ASC")?;
+
+    if let Some(limit) = playlist.limit {
+        query.push_str(" LIMIT ?");
+        values.push(Value::Integer(limit.into()));
+    }
+    Ok((query, values))
+}
+
+/// Build the condition of `rule`, adding its value to `values`
+fn build_condition(rule: &SmartRule, values: &mut Vec<Value>) -> Result<String> {
+    let column = rule.field.column();
+    let value = rule.value.trim();
+
+    let condition = match (rule.field, rule.operator) {
+        (field, SmartOperator::Is | SmartOperator::IsNot) if field.is_text() => {
+            values.push(Value::Text(escape_like(value)));
+            let not = if rule.operator == SmartOperator::IsNot {
+                "NOT "
+            } else {
+                ""
+            };
+            format!("{column} {not}LIKE ? ESCAPE '\\'")
+        }
+        (field, SmartOperator::Contains | SmartOperator::NotContains) if field.is_text() => {
+            values.push(Value::Text(format!("%{}%", escape_like(value))));
+            let not = if rule.operator == SmartOperator::NotContains {
+                "NOT "
+            } else {
+                ""
+            };
+            format!("{column} {not}LIKE ? ESCAPE '\\'")
+        }
+        (SmartField::Favorite, SmartOperator::Is | SmartOperator::IsNot) => {
+            let favorite: bool = value
+                .parse()
+                .map_err(|_| anyhow!("favorite needs true or false, not \"{value}\""))?;
+            values.push(Value::Integer(favorite.into()));
+            let operator = if rule.operator == SmartOperator::Is {
+                "="
+            } else {
+                "!="
+            };
+            format!("{column} {operator} ?")
+        }
+        (
+            SmartField::Duration
+            | SmartField::PlayCount
+            | SmartField::SkipCount
+            | SmartField::Rating,
+            SmartOperator::Is | SmartOperator::IsNot | SmartOperator::Greater | SmartOperator::Less,
+        ) => {
+            let number = if rule.field == SmartField::Duration {
+                parse_duration(value)?
+            } else {
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("{column} needs a number, not \"{value}\""))?
+            };
+            values.push(Value::Integer(number));
+            let operator = match rule.operator {
+                SmartOperator::Is => "=",
+                SmartOperator::IsNot => "!=",
+                SmartOperator::Greater => ">",
+                _ => "<",
+            };
+            format!("{column} {operator} ?")
+        }
+        (SmartField::LastPlayed, SmartOperator::InLastDays | SmartOperator::NotInLastDays) => {
+            let days: i64 = value
+                .parse()
+                .map_err(|_| anyhow!("{column} needs a number of days, not \"{value}\""))?;
+            values.push(Value::Integer(days.saturating_mul(SECS_PER_DAY)));
+            if rule.operator == SmartOperator::InLastDays {
+                format!("{column} >= CAST(strftime('%s', 'now') AS INTEGER) - ?")
+            } else {
+                format!(
+                    "({column} IS NULL OR {column} < CAST(strftime('%s', 'now') AS INTEGER) - ?)"
+                )
+            }
+        }
+        (field, operator) => bail!("{field:?} does not support {operator:?}"),
+    };
+    Ok(condition)
+}
+
+/// Parse a duration in seconds like `300`, `90s`, `5m`, `5min` or `1h`
+fn parse_duration(value: &str) -> Result<i64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| anyhow!("duration needs a number, not \"{value}\""))?;
+    let factor = match unit.trim() {
+        "" | "s" | "sec" => 1,
+        "m" | "min" => 60,
+        "h" => 3600,
+        _ => bail!("unknown unit of duration \"{value}\""),
+    };
+    Ok(number.saturating_mul(factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::migrations;
+    use pretty_assertions::assert_eq;
+
+    fn rule(field: SmartField, operator: SmartOperator, value: &str) -> SmartRule {
+        SmartRule {
+            field,
+            operator,
+            value: value.to_string(),
+        }
+    }
+
+    fn playlist(rules: Vec<SmartRule>) -> SmartPlaylist {
+        SmartPlaylist {
+            name: "test".to_string(),
+            match_any: false,
+            rules,
+            sort: None,
+            descending: false,
+            limit: None,
+        }
+    }
+
+    fn library() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tracks (artist, title, album, genre, file, duration, name, ext, directory, last_modified, last_position)
+            VALUES ('A', 'Long', 'X', 'Jazz', '/m/long.mp3', 400, 'long.mp3', 'mp3', '/m', '0', 0),
+                ('B', 'Short', 'X', 'jazz', '/m/short.mp3', 120, 'short.mp3', 'mp3', '/m', '0', 0),
+                ('C', 'Rock', 'Y', 'Rock', '/m/rock.flac', 500, 'rock.flac', 'flac', '/m', '0', 0);
+            INSERT INTO history (file, event, time) VALUES ('/m/long.mp3', 0, CAST(strftime('%s', 'now') AS INTEGER));
+            INSERT INTO ratings (file, rating, favorite) VALUES ('/m/rock.flac', 4, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn names(conn: &Connection, playlist: &SmartPlaylist) -> Vec<String> {
+        get_tracks(conn, playlist)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    }
+
+    #[test]
+    fn should_match_all_rules() {
+        let conn = library();
+        let playlist = playlist(vec![
+            rule(SmartField::Genre, SmartOperator::Is, "jazz"),
+            rule(SmartField::Duration, SmartOperator::Greater, "5min"),
+        ]);
+        assert_eq!(names(&conn, &playlist), vec!["long.mp3"]);
+    }
+
+    #[test]
+    fn should_match_any_rule() {
+        let conn = library();
+        let mut playlist = playlist(vec![
+            rule(SmartField::Favorite, SmartOperator::Is, "true"),
+            rule(SmartField::LastPlayed, SmartOperator::InLastDays, "30"),
+        ]);
+        playlist.match_any = true;
+        assert_eq!(names(&conn, &playlist), vec!["long.mp3", "rock.flac"]);
+    }
+
+    #[test]
+    fn should_sort_and_limit() {
+        let conn = library();
+        let mut playlist = playlist(vec![rule(
+            SmartField::LastPlayed,
+            SmartOperator::NotInLastDays,
+            "30",
+        )]);
+        playlist.sort = Some(SmartField::Duration);
+        playlist.descending = true;
+        playlist.limit = Some(1);
+        assert_eq!(names(&conn, &playlist), vec!["rock.flac"]);
+    }
+
+    #[test]
+    fn should_reject_invalid_rules() {
+        let conn = library();
+        let playlist = playlist(vec![rule(
+            SmartField::Genre,
+            SmartOperator::Greater,
+            "Jazz",
+        )]);
+        assert!(get_tracks(&conn, &playlist).is_err());
+        assert!(parse_duration("5 weeks").is_err());
+        assert_eq!(parse_duration("1h").unwrap(), 3600);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::{ReplayGainMode, ReplayGainSettings};
 use crate::podcast::Episode;
 /**
  * MIT License
@@ -26,10 +27,12 @@ use crate::songtag::lrc::Lyric;
 use crate::utils::get_parent_folder;
 use anyhow::{bail, Context, Result};
 use id3::frame::Lyrics;
-use lofty::id3::v2::{Frame, FrameFlags, FrameValue, Id3v2Tag, UnsynchronizedTextFrame};
+use lofty::id3::v2::{
+    Frame, FrameFlags, FrameValue, Id3v2Tag, Popularimeter, UnsynchronizedTextFrame,
+};
 use lofty::{
     mpeg::MpegFile, Accessor, AudioFile, FileType, ItemKey, ItemValue, Picture, PictureType,
-    TagExt, TagItem, TaggedFileExt, TextEncoding,
+    TagExt, TagItem, TagType, TaggedFileExt, TextEncoding,
 };
 use std::convert::From;
 use std::ffi::OsStr;
@@ -74,6 +77,9 @@ pub struct Track {
     // Comment
     pub media_type: Option<MediaType>,
     pub podcast_localfile: Option<String>,
+    replay_gain: ReplayGain,
+    /// Star rating of 0 to [`MAX_RATING`] read from the tags, `None` if untagged
+    rating: Option<u8>,
 }
 
 impl PartialEq for Track {
@@ -89,6 +95,229 @@ pub enum MediaType {
     LiveRadio,
 }
 
+/// `ReplayGain` tags of a track, gains in dB and peaks as linear sample amplitude
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Whether there is neither a track nor an album gain
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.track_gain.is_none() && self.album_gain.is_none()
+    }
+
+    /// Read the `REPLAYGAIN_*` tags, falling back to the `R128_*` gains used by Opus
+    fn from_tag(tag: &lofty::Tag) -> Self {
+        let get = |key: ItemKey| tag.get_string(&key).and_then(parse_replay_gain);
+        // R128 gains are Q7.8 fixed point numbers relative to -23 LUFS instead of the -18 LUFS of ReplayGain
+        let get_r128 = |key: &str| {
+            tag.get_string(&ItemKey::Unknown(key.to_string()))
+                .and_then(|v| v.trim().parse::<i16>().ok())
+                .map(|v| f32::from(v) / 256.0 + 5.0)
+        };
+
+        Self {
+            track_gain: get(ItemKey::ReplayGainTrackGain).or_else(|| get_r128("R128_TRACK_GAIN")),
+            track_peak: get(ItemKey::ReplayGainTrackPeak),
+            album_gain: get(ItemKey::ReplayGainAlbumGain).or_else(|| get_r128("R128_ALBUM_GAIN")),
+            album_peak: get(ItemKey::ReplayGainAlbumPeak),
+        }
+    }
+
+    /// Get the linear volume factor to apply for `settings`, `1.0` if disabled or untagged.
+    ///
+    /// [`ReplayGainMode::Auto`] has to be resolved already and is treated like album gain.
+    #[must_use]
+    pub fn factor(&self, settings: &ReplayGainSettings) -> f32 {
+        let track = (self.track_gain, self.track_peak);
+        let album = (self.album_gain, self.album_peak);
+        let (gain, peak) = match settings.mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track if track.0.is_some() => track,
+            ReplayGainMode::Track => album,
+            ReplayGainMode::Album | ReplayGainMode::Auto if album.0.is_some() => album,
+            ReplayGainMode::Album | ReplayGainMode::Auto => track,
+        };
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+
+        let factor = 10_f32.powf((gain + settings.preamp) / 20.0);
+        match peak {
+            Some(peak) if settings.prevent_clipping && peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
+}
+
+/// Loudness all `ReplayGain` 2.0 gains normalize to, in LUFS
+pub const REPLAY_GAIN_REFERENCE_LUFS: f32 = -18.0;
+
+/// Result of an EBU R128 loudness analysis of a track
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS
+    pub integrated: f32,
+    /// True peak as linear sample amplitude
+    pub true_peak: f32,
+}
+
+impl Loudness {
+    /// Get the track gain and peak equivalent to this analysis
+    #[must_use]
+    pub fn replay_gain(&self) -> ReplayGain {
+        ReplayGain {
+            track_gain: Some(REPLAY_GAIN_REFERENCE_LUFS - self.integrated),
+            track_peak: Some(self.true_peak),
+            album_gain: None,
+            album_peak: None,
+        }
+    }
+}
+
+/// Write the track gain and peak of `replay_gain` to the tags of the file at `path`, keeping all other tags
+///
+/// # Errors
+///
+/// - if the file cannot be read or written
+pub fn write_replay_gain_tags(path: &Path, replay_gain: &ReplayGain) -> Result<()> {
+    let mut tagged_file = lofty::Probe::open(path)?.read()?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(lofty::Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .context("file does not support tags")?;
+
+    if let Some(gain) = replay_gain.track_gain {
+        tag.insert_text(ItemKey::ReplayGainTrackGain, format!("{gain:.2} dB"));
+    }
+    if let Some(peak) = replay_gain.track_peak {
+        tag.insert_text(ItemKey::ReplayGainTrackPeak, format!("{peak:.6}"));
+    }
+    tag.save_to_path(path)?;
+
+    Ok(())
+}
+
+/// Highest star rating of a track
+pub const MAX_RATING: u8 = 5;
+
+/// `POPM` rating byte for each star rating, as used by most players
+const POPM_RATINGS: [u8; MAX_RATING as usize + 1] = [0, 1, 64, 128, 196, 255];
+
+/// Vorbis comment key of the rating as a fraction of 0.0 to 1.0
+const FMPS_RATING: &str = "FMPS_RATING";
+
+/// Read the star rating from the `POPM` (`ID3v2`), `RATING` (0-100 or 0-5) or `FMPS_RATING` tags
+fn rating_from_tag(tag: &lofty::Tag) -> Option<u8> {
+    let from_popularimeter = tag
+        .get(&ItemKey::Popularimeter)
+        .and_then(|item| match item.value() {
+            ItemValue::Binary(bytes) => Popularimeter::parse(&mut &bytes[..])
+                .ok()
+                .map(|popm| popm_to_stars(popm.rating)),
+            ItemValue::Text(text) => text.trim().parse::<f32>().ok().map(|v| {
+                if v <= f32::from(MAX_RATING) {
+                    stars_from_f32(v)
+                } else {
+                    stars_from_f32(v / 20.0)
+                }
+            }),
+            ItemValue::Locator(_) => None,
+        });
+
+    from_popularimeter.or_else(|| {
+        tag.get_string(&ItemKey::Unknown(FMPS_RATING.to_string()))
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .map(|v| stars_from_f32(v * f32::from(MAX_RATING)))
+    })
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn stars_from_f32(stars: f32) -> u8 {
+    stars.round().clamp(0.0, f32::from(MAX_RATING)) as u8
+}
+
+/// Map a `POPM` rating byte to the closest star rating
+const fn popm_to_stars(rating: u8) -> u8 {
+    match rating {
+        0 => 0,
+        1..=31 => 1,
+        32..=95 => 2,
+        96..=159 => 3,
+        160..=223 => 4,
+        _ => 5,
+    }
+}
+
+fn popularimeter(rating: u8) -> Popularimeter {
+    Popularimeter {
+        email: String::from("termusic"),
+        rating: POPM_RATINGS[usize::from(rating.min(MAX_RATING))],
+        counter: 0,
+    }
+}
+
+/// Set the rating items of `tag` fitting its type to the star `rating`
+fn insert_rating_items(tag: &mut lofty::Tag, rating: u8) {
+    let rating = rating.min(MAX_RATING);
+    match tag.tag_type() {
+        TagType::Id3v2 => {
+            tag.insert(TagItem::new(
+                ItemKey::Popularimeter,
+                ItemValue::Binary(popularimeter(rating).as_bytes()),
+            ));
+        }
+        tag_type => {
+            tag.insert_text(ItemKey::Popularimeter, (u32::from(rating) * 20).to_string());
+            if tag_type == TagType::VorbisComments {
+                tag.insert_text(
+                    ItemKey::Unknown(FMPS_RATING.to_string()),
+                    format!("{:.1}", f32::from(rating) / f32::from(MAX_RATING)),
+                );
+            }
+        }
+    }
+}
+
+/// Write the star `rating` to the tags of the file at `path`, keeping all other tags
+///
+/// # Errors
+///
+/// - if the file cannot be read or written
+pub fn write_rating_tags(path: &Path, rating: u8) -> Result<()> {
+    let mut tagged_file = lofty::Probe::open(path)?.read()?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(lofty::Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .context("file does not support tags")?;
+
+    insert_rating_items(tag, rating);
+    tag.save_to_path(path)?;
+
+    Ok(())
+}
+
+/// Parse a `ReplayGain` value like `-6.57 dB` or `0.988831`
+fn parse_replay_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok()
+}
+
 impl Track {
     #[allow(clippy::cast_sign_loss)]
     pub fn from_episode(ep: &Episode) -> Self {
@@ -120,6 +349,8 @@ impl Track {
             genre: None,
             media_type: Some(MediaType::Podcast),
             podcast_localfile,
+            replay_gain: ReplayGain::default(),
+            rating: None,
         }
     }
 
@@ -147,6 +378,8 @@ impl Track {
                 song.title = tag.title().map(std::borrow::Cow::into_owned);
                 song.genre = tag.genre().map(std::borrow::Cow::into_owned);
                 song.media_type = Some(MediaType::Music);
+                song.replay_gain = ReplayGain::from_tag(tag);
+                song.rating = rating_from_tag(tag);
 
                 if for_db {
                     return Ok(song);
@@ -266,6 +499,8 @@ impl Track {
             genre,
             media_type: Some(MediaType::Music),
             podcast_localfile: None,
+            replay_gain: ReplayGain::default(),
+            rating: None,
         }
     }
 
@@ -383,6 +618,26 @@ impl Track {
         self.genre = Some(genre.to_string());
     }
 
+    pub fn replay_gain(&self) -> &ReplayGain {
+        &self.replay_gain
+    }
+
+    /// Star rating of 0 to [`MAX_RATING`] from the tags, `None` if untagged
+    #[must_use]
+    pub const fn rating(&self) -> Option<u8> {
+        self.rating
+    }
+
+    /// Set the rating written by [`Track::save_tag`], `None` to not write any
+    pub fn set_rating(&mut self, rating: Option<u8>) {
+        self.rating = rating.map(|v| v.min(MAX_RATING));
+    }
+
+    /// Use gains not read from the tags of this track, like the ones of a loudness analysis
+    pub fn set_replay_gain(&mut self, replay_gain: ReplayGain) {
+        self.replay_gain = replay_gain;
+    }
+
     /// Optionally return the title of the song
     /// If `None` it wasn't able to read the tags
     pub fn title(&self) -> Option<&str> {
@@ -461,6 +716,20 @@ impl Track {
                         tag.insert_picture(any_picture);
                     }
 
+                    if let Some(rating) = self.rating {
+                        if let Ok(frame) = Frame::new(
+                            "POPM",
+                            FrameValue::Popularimeter(popularimeter(rating)),
+                            FrameFlags::default(),
+                        ) {
+                            tag.insert(frame);
+                        }
+                        tag.insert_user_text(
+                            String::from("FMPS_Rating"),
+                            format!("{:.1}", f32::from(rating) / f32::from(MAX_RATING)),
+                        );
+                    }
+
                     tag.save_to_path(file_path)?;
                 }
             }
@@ -487,6 +756,10 @@ impl Track {
                         tag.push_picture(any_picture);
                     }
 
+                    if let Some(rating) = self.rating {
+                        insert_rating_items(&mut tag, rating);
+                    }
+
                     tag.save_to_path(file_path)?;
                 }
             }
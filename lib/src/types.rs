@@ -2,6 +2,7 @@ use crate::config::{BindingForEvent, ColorTermusic};
 use crate::invidious::{Instance, YoutubeVideo};
 use crate::podcast::{EpData, PodcastFeed, PodcastNoId};
 use crate::songtag::SongTag;
+use crate::sqlite::SmartSelection;
 use anyhow::{anyhow, Result};
 use image::DynamicImage;
 
@@ -163,6 +164,17 @@ pub enum ConfigEditorMsg {
     PlayerPortBlurUp,
     CurrentlyPlayingTrackSymbolBlurDown,
     CurrentlyPlayingTrackSymbolBlurUp,
+    EqualizerEnabledBlurDown,
+    EqualizerEnabledBlurUp,
+    EqualizerPresetBlurDown,
+    EqualizerPresetBlurUp,
+    /// Index of the band in [`EQ_BAND_FREQUENCIES`](crate::config::EQ_BAND_FREQUENCIES)
+    EqualizerBandBlurDown(usize),
+    EqualizerBandBlurUp(usize),
+    ChannelMixMonoBlurDown,
+    ChannelMixMonoBlurUp,
+    ChannelMixBalanceBlurDown,
+    ChannelMixBalanceBlurUp,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -293,10 +305,28 @@ pub enum KFMsg {
     PodcastRefreshFeedBlurUp,
     PodcastRefreshAllFeedsBlurDown,
     PodcastRefreshAllFeedsBlurUp,
+    PlaylistMostPlayedBlurDown,
+    PlaylistMostPlayedBlurUp,
+    PlaylistRecentlyPlayedBlurDown,
+    PlaylistRecentlyPlayedBlurUp,
+    PlaylistNeverPlayedBlurDown,
+    PlaylistNeverPlayedBlurUp,
+    DatabaseSortBlurDown,
+    DatabaseSortBlurUp,
+    DatabaseExportM3uBlurDown,
+    DatabaseExportM3uBlurUp,
+    TrackRatingUpBlurDown,
+    TrackRatingUpBlurUp,
+    TrackRatingDownBlurDown,
+    TrackRatingDownBlurUp,
+    TrackFavoriteBlurDown,
+    TrackFavoriteBlurUp,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LIMsg {
+    /// Change the rating of the track at the path
+    Rate(String, RatingChange),
     TreeExtendDir(String),
     TreeGoToUpperDir,
     TreeBlur,
@@ -307,10 +337,20 @@ pub enum LIMsg {
     RemoveRoot,
 }
 
+/// Change to the rating of a track, see [`DataBase::set_rating`](crate::sqlite::DataBase::set_rating)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RatingChange {
+    Up,
+    Down,
+    ToggleFavorite,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum DBMsg {
     AddAllToPlaylist,
     AddPlaylist(usize),
+    /// Change the rating of the track at the index of the search result
+    Rate(usize, RatingChange),
     CriteriaBlurDown,
     CriteriaBlurUp,
     SearchResult(usize),
@@ -319,6 +359,10 @@ pub enum DBMsg {
     SearchTrack(usize),
     SearchTracksBlurDown,
     SearchTracksBlurUp,
+    /// Sort the tracks by the next [`TrackSort`](crate::sqlite::TrackSort)
+    SortTracks,
+    /// Export the smart playlist at the index of the search results to M3U
+    ExportM3u(usize),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -393,6 +437,10 @@ pub enum PLMsg {
     /// Start choosing random tracks to be added to the playlist
     // TODO: the shortform "CmusTQueue" should also be explained
     CmusTQueue,
+    /// Add tracks selected by their play statistics to the playlist
+    SmartSelection(SmartSelection),
+    /// Change the rating of the track at the index of the playlist
+    Rate(usize, RatingChange),
 }
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GSMsg {
@@ -546,6 +594,12 @@ pub enum IdConfigEditor {
     ProgressLabel,
     SaveLastPosition,
     SeekStep,
+    EqualizerEnabled,
+    EqualizerPreset,
+    /// Index of the band in [`EQ_BAND_FREQUENCIES`](crate::config::EQ_BAND_FREQUENCIES)
+    EqualizerBand(usize),
+    ChannelMixMono,
+    ChannelMixBalance,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
@@ -613,6 +667,14 @@ pub enum IdKey {
     PodcastSearchAddFeed,
     PodcastRefreshFeed,
     PodcastRefreshAllFeeds,
+    PlaylistMostPlayed,
+    PlaylistRecentlyPlayed,
+    PlaylistNeverPlayed,
+    DatabaseSort,
+    DatabaseExportM3u,
+    TrackRatingUp,
+    TrackRatingDown,
+    TrackFavorite,
 }
 pub enum SearchLyricState {
     Finish(Vec<SongTag>),
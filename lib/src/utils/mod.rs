@@ -1,11 +1,12 @@
 use crate::config::Settings;
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use lazy_static::lazy_static;
 use pinyin::ToPinyin;
 use regex::Regex;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::OnceLock;
 use std::time::Instant;
 use std::{
     ffi::OsStr,
@@ -358,9 +359,44 @@ pub fn get_parent_folder(filename: &str) -> String {
     parent_folder.to_string_lossy().to_string()
 }
 
+/// Name of the selected profile, see [`set_profile`]
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Select a named profile, which isolates all state (config, database, playlist, socket)
+/// from the default and all other profiles
+///
+/// Has to be called before any path is requested, and can only be called once.
+///
+/// # Errors
+/// if `name` is not a valid profile name or a profile has already been selected
+pub fn set_profile(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Invalid profile name \"{name}\", only ascii letters, numbers, \"-\" and \"_\" are allowed");
+    }
+
+    PROFILE
+        .set(name.to_string())
+        .map_err(|_| anyhow!("A profile has already been selected"))
+}
+
+/// Get the selected profile, `None` means the default profile
+#[must_use]
+pub fn get_profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+/// Get the config directory, like `~/.config/termusic` or `~/.config/termusic/profiles/NAME`
 pub fn get_app_config_path() -> Result<PathBuf> {
     let mut path = dirs::config_dir().ok_or_else(|| anyhow!("failed to find os config dir."))?;
     path.push("termusic");
+    if let Some(profile) = get_profile() {
+        path.push("profiles");
+        path.push(profile);
+    }
 
     if !path.exists() {
         std::fs::create_dir_all(&path)?;
@@ -368,6 +404,130 @@ pub fn get_app_config_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Get the per-user runtime directory, like `$XDG_RUNTIME_DIR/termusic` or `$XDG_RUNTIME_DIR/termusic/profiles/NAME`
+///
+/// Falls back to a user-specific directory in the temp dir if the os has no runtime dir.
+/// The directory is created with permissions only allowing the current user access.
+///
+/// # Errors
+/// if the directory cannot be created, or already exists but is owned or accessible by another
+/// user
+pub fn get_app_runtime_path() -> Result<PathBuf> {
+    let base = dirs::runtime_dir().map_or_else(
+        || {
+            let user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string());
+            std::env::temp_dir().join(format!("termusic-{user}"))
+        },
+        |path| path.join("termusic"),
+    );
+
+    create_private_dir(&base)?;
+
+    let mut path = base;
+    if let Some(profile) = get_profile() {
+        path.push("profiles");
+        path.push(profile);
+        create_private_dir(&path)?;
+    }
+    Ok(path)
+}
+
+/// Write `contents` to `path` so that `path` always contains either the old or the new contents,
+/// even if the process crashes or the power is lost mid-write
+///
+/// This is done by writing to a temporary file next to `path` first, then renaming it over `path`.
+///
+/// # Errors
+/// if writing or renaming fails
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("path \"{}\" has no filename", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    // make sure the data is on disk before the rename makes it visible
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_path, path)?;
+
+    // persist the rename itself, not supported on all platforms, so errors are ignored
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = std::fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Filename of the file the server writes its process id to, in [`get_app_runtime_path`]
+const SERVER_PID_FILENAME: &str = "server.pid";
+
+/// Get the path of the file containing the process id of the server of the selected profile
+pub fn get_server_pid_path() -> Result<PathBuf> {
+    let mut path = get_app_runtime_path()?;
+    path.push(SERVER_PID_FILENAME);
+    Ok(path)
+}
+
+/// Create the directory `path` only accessible by the current user, if it does not exist yet
+///
+/// An existing directory has to be owned by the current user and must not be accessible by
+/// anyone else. Otherwise another user, who created it first in the shared temp dir, could
+/// replace the socket in it and read the token of the clients.
+///
+/// # Errors
+/// if the directory cannot be created, or is not private
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path)
+        .with_context(|| format!("Error creating directory {}", path.display()))?;
+
+    // not following links, the directory itself has to be private
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        bail!("{} is not a directory", path.display());
+    }
+    if metadata.uid() != rustix::process::getuid().as_raw() {
+        bail!(
+            "Directory {} is owned by another user, remove it or set XDG_RUNTIME_DIR",
+            path.display()
+        );
+    }
+    if metadata.mode() & 0o077 != 0 {
+        bail!(
+            "Directory {} is accessible by other users, restrict it with \"chmod 700\"",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Create the directory `path`, if it does not exist yet
+///
+/// # Errors
+/// if the directory cannot be created
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path)
+        .with_context(|| format!("Error creating directory {}", path.display()))
+}
+
 fn get_podcast_save_path(config: &Settings) -> Result<PathBuf> {
     let full_path = shellexpand::tilde(&config.podcast_dir).to_string();
     let full_path_pathbuf = PathBuf::from(full_path);
@@ -424,6 +584,17 @@ fn playlist_get_absolute_pathbuf(item: &str, p_base: &Path) -> Result<PathBuf> {
     Ok(pathbuf)
 }
 
+/// Convert a `file://` uri to a plain path, other uris (like `http`) and paths are returned unchanged
+///
+/// # Errors
+/// if the path of a `file://` uri is not valid percent-encoded utf8
+pub fn uri_to_location(uri: &str) -> Result<String> {
+    match uri.strip_prefix("file://") {
+        Some(path) => Ok(urlencoding::decode(path)?.into_owned()),
+        None => Ok(uri.to_string()),
+    }
+}
+
 /// Some helper functions for dealing with Unicode strings.
 #[allow(clippy::module_name_repetitions)]
 pub trait StringUtils {
@@ -499,4 +670,30 @@ mod tests {
         assert_eq!(child.width, 271);
         assert_eq!(child.height, 54);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_utils_create_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("termusic-test-{}", std::process::id()));
+        let path = dir.join("runtime");
+        create_private_dir(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // an existing private directory is used as it is
+        create_private_dir(&path).unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(create_private_dir(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_profile_invalid_name() {
+        assert!(set_profile("").is_err());
+        assert!(set_profile("../escape").is_err());
+        assert!(set_profile("with space").is_err());
+        assert_eq!(get_profile(), None);
+    }
 }
@@ -0,0 +1,288 @@
+//! Shared-secret token authentication between the server and its clients
+//!
+//! The server generates a token into the config dir on first run, clients read it from there
+//! and send it as metadata with every request.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use termusiclib::utils::get_app_config_path;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Metadata key the token is sent under
+pub const AUTH_METADATA_KEY: &str = "termusic-token";
+/// Filename of the token in the config dir
+const TOKEN_FILENAME: &str = "auth.token";
+/// Length of newly generated tokens
+const TOKEN_LENGTH: usize = 48;
+/// Minimal length of tokens read from the token file
+const MIN_TOKEN_LENGTH: usize = 32;
+
+/// Get the path of the token file
+///
+/// # Errors
+///
+/// if the config dir cannot be determined
+pub fn get_token_path() -> Result<PathBuf> {
+    let mut path = get_app_config_path()?;
+    path.push(TOKEN_FILENAME);
+    Ok(path)
+}
+
+/// Read the token, generating a new one if it does not exist yet
+///
+/// # Errors
+///
+/// if the token file cannot be written or, if it already exists, see [`read_token`]
+pub fn get_or_create_token() -> Result<String> {
+    let path = get_token_path()?;
+    if path.exists() {
+        return read_token();
+    }
+
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+
+    write_token(&path, &token)
+        .with_context(|| format!("Error writing token file {}", path.display()))?;
+    info!("Generated new auth token at {}", path.display());
+
+    Ok(token)
+}
+
+/// Read the existing token, as generated by the server
+///
+/// # Errors
+///
+/// - if the token file cannot be read
+/// - if the token file is accessible by other users
+/// - if the token is shorter than [`MIN_TOKEN_LENGTH`]
+pub fn read_token() -> Result<String> {
+    let path = get_token_path()?;
+    let token = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "Error reading token file {}, has the server been started with \"player_use_token\" enabled?",
+            path.display()
+        )
+    })?;
+    check_permissions(&path)?;
+    let token = token.trim();
+    validate_token(token).with_context(|| format!("Invalid token file {}", path.display()))?;
+
+    Ok(token.to_string())
+}
+
+/// Check that `token` is long enough to not be guessable
+fn validate_token(token: &str) -> Result<()> {
+    if token.len() < MIN_TOKEN_LENGTH {
+        bail!(
+            "auth token is {} characters long, but at least {MIN_TOKEN_LENGTH} are required, delete the file to generate a new one",
+            token.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that only the current user can access the token file
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)
+        .with_context(|| format!("Error reading metadata of {}", path.display()))?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        bail!(
+            "Token file {} is accessible by other users (mode {:o}), restrict it with \"chmod 600\"",
+            path.display(),
+            mode & 0o777
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+fn check_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_token(path: &Path, token: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(token.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_token(path: &Path, token: &str) -> std::io::Result<()> {
+    std::fs::write(path, token)
+}
+
+/// Compare in constant time, to not leak how much of the token matched
+fn token_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Server-side interceptor rejecting every request without the correct token
+///
+/// An interceptor without a token accepts everything.
+#[derive(Debug, Clone)]
+pub struct ServerAuthInterceptor {
+    token: Option<String>,
+}
+
+impl ServerAuthInterceptor {
+    #[must_use]
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+}
+
+impl Interceptor for ServerAuthInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(token) = &self.token else {
+            return Ok(request);
+        };
+
+        match request.metadata().get(AUTH_METADATA_KEY) {
+            Some(value) if token_eq(value.as_bytes(), token.as_bytes()) => Ok(request),
+            Some(_) => {
+                warn!("Rejected request with an invalid auth token");
+                Err(Status::unauthenticated("invalid auth token"))
+            }
+            None => {
+                warn!("Rejected request without an auth token");
+                Err(Status::unauthenticated(format!(
+                    "missing auth token, expected in metadata \"{AUTH_METADATA_KEY}\""
+                )))
+            }
+        }
+    }
+}
+
+/// Client-side interceptor adding the token to every request
+///
+/// An interceptor without a token leaves requests unchanged.
+#[derive(Debug, Clone)]
+pub struct ClientAuthInterceptor {
+    token: Option<MetadataValue<Ascii>>,
+}
+
+impl ClientAuthInterceptor {
+    /// # Errors
+    ///
+    /// if `token` cannot be sent as metadata
+    pub fn new(token: Option<&str>) -> Result<Self> {
+        let token = token
+            .map(MetadataValue::try_from)
+            .transpose()
+            .context("auth token contains invalid characters")?;
+
+        Ok(Self { token })
+    }
+}
+
+impl Interceptor for ClientAuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(token) = &self.token {
+            request
+                .metadata_mut()
+                .insert(AUTH_METADATA_KEY, token.clone());
+        }
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const TOKEN: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+    fn request(token: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request
+                .metadata_mut()
+                .insert(AUTH_METADATA_KEY, token.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn test_token_eq() {
+        assert!(token_eq(b"", b""));
+        assert!(token_eq(TOKEN.as_bytes(), TOKEN.as_bytes()));
+        assert!(!token_eq(b"abc", b"abd"));
+        assert!(!token_eq(b"abc", b"abcd"));
+        assert!(!token_eq(b"", b"a"));
+    }
+
+    #[test]
+    fn test_validate_token() {
+        assert!(validate_token("").is_err());
+        assert!(validate_token("short").is_err());
+        assert!(validate_token(TOKEN).is_ok());
+    }
+
+    #[test]
+    fn test_server_interceptor() {
+        let mut interceptor = ServerAuthInterceptor::new(Some(TOKEN.to_string()));
+
+        let err = interceptor.call(request(None)).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+
+        let err = interceptor.call(request(Some("wrong"))).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+
+        assert!(interceptor.call(request(Some(TOKEN))).is_ok());
+    }
+
+    #[test]
+    fn test_server_interceptor_without_token() {
+        let mut interceptor = ServerAuthInterceptor::new(None);
+
+        assert!(interceptor.call(request(None)).is_ok());
+        assert!(interceptor.call(request(Some("anything"))).is_ok());
+    }
+
+    #[test]
+    fn test_client_interceptor() {
+        let mut interceptor = ClientAuthInterceptor::new(Some(TOKEN)).unwrap();
+        let request = interceptor.call(request(None)).unwrap();
+        assert_eq!(
+            request
+                .metadata()
+                .get(AUTH_METADATA_KEY)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            TOKEN
+        );
+
+        assert!(ClientAuthInterceptor::new(Some("invalid\n")).is_err());
+    }
+}
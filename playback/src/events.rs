@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Result};
+use termusiclib::config::Loop;
+use tokio::sync::broadcast;
+
+use crate::player::stream_updates::Update;
+use crate::player::{
+    StreamUpdates, TrackInfo, UpdateGaplessChanged, UpdateLibraryChanged, UpdateLoopModeChanged,
+    UpdateMissedEvents, UpdatePlayStateChanged, UpdatePlaylistChanged, UpdateProgress,
+    UpdateRadioTitleChanged, UpdateSpeedChanged, UpdateTrackChanged, UpdateVolumeChanged,
+};
+use crate::{speed_from_fields, speed_to_tenths, PlayerProgress, Status};
+
+/// Amount of events a subscriber may lag behind before it starts missing events
+pub const STREAM_CHANNEL_CAPACITY: usize = 64;
+
+/// Sender half of the channel all server events get broadcasted on
+pub type StreamTX = broadcast::Sender<UpdateEvents>;
+/// Receiver half of the channel all server events get broadcasted on
+pub type StreamRX = broadcast::Receiver<UpdateEvents>;
+
+/// Create a new broadcast channel for [`UpdateEvents`]
+#[must_use]
+pub fn stream_channel() -> (StreamTX, StreamRX) {
+    broadcast::channel(STREAM_CHANNEL_CAPACITY)
+}
+
+/// Events the server pushes to every subscribed client
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateEvents {
+    /// The subscriber lagged behind and `amount` events were dropped
+    MissedEvents {
+        amount: u64,
+    },
+    TrackChanged {
+        current_track_index: u32,
+        track: Option<TrackInfo>,
+    },
+    PlayStateChanged {
+        status: Status,
+    },
+    VolumeChanged {
+        volume: u16,
+    },
+    SpeedChanged {
+        speed: i32,
+    },
+    GaplessChanged {
+        gapless: bool,
+    },
+    Progress(PlayerProgress),
+    RadioTitleChanged {
+        title: String,
+    },
+    PlaylistChanged,
+    LoopModeChanged {
+        loop_mode: Loop,
+    },
+    /// Tracks of the library database were added, changed or removed
+    LibraryChanged,
+}
+
+impl UpdateEvents {
+    /// Send this event to all current subscribers
+    ///
+    /// Not having any subscribers is not a error, so it is ignored
+    pub fn send(self, tx: &StreamTX) {
+        let _ = tx.send(self);
+    }
+}
+
+impl From<UpdateEvents> for StreamUpdates {
+    fn from(value: UpdateEvents) -> Self {
+        let update = match value {
+            UpdateEvents::MissedEvents { amount } => {
+                Update::MissedEvents(UpdateMissedEvents { amount })
+            }
+            UpdateEvents::TrackChanged {
+                current_track_index,
+                track,
+            } => Update::TrackChanged(UpdateTrackChanged {
+                current_track_index,
+                track,
+            }),
+            UpdateEvents::PlayStateChanged { status } => {
+                Update::PlayStateChanged(UpdatePlayStateChanged {
+                    status: status.as_u32(),
+                })
+            }
+            UpdateEvents::VolumeChanged { volume } => Update::VolumeChanged(UpdateVolumeChanged {
+                volume: u32::from(volume),
+            }),
+            UpdateEvents::SpeedChanged { speed } => Update::SpeedChanged(UpdateSpeedChanged {
+                speed: speed_to_tenths(speed),
+                speed_percent: speed,
+            }),
+            UpdateEvents::GaplessChanged { gapless } => {
+                Update::GaplessChanged(UpdateGaplessChanged { gapless })
+            }
+            UpdateEvents::Progress(progress) => Update::ProgressChanged(UpdateProgress {
+                progress: Some(progress.into()),
+            }),
+            UpdateEvents::RadioTitleChanged { title } => {
+                Update::RadioTitleChanged(UpdateRadioTitleChanged { title })
+            }
+            UpdateEvents::PlaylistChanged => Update::PlaylistChanged(UpdatePlaylistChanged {}),
+            UpdateEvents::LoopModeChanged { loop_mode } => {
+                Update::LoopModeChanged(UpdateLoopModeChanged {
+                    loop_mode: loop_mode.as_u32(),
+                })
+            }
+            UpdateEvents::LibraryChanged => Update::LibraryChanged(UpdateLibraryChanged {}),
+        };
+
+        Self {
+            update: Some(update),
+        }
+    }
+}
+
+impl TryFrom<StreamUpdates> for UpdateEvents {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StreamUpdates) -> Result<Self> {
+        let update = value
+            .update
+            .ok_or_else(|| anyhow!("Got a StreamUpdates without a update"))?;
+
+        let res = match update {
+            Update::MissedEvents(ev) => Self::MissedEvents { amount: ev.amount },
+            Update::TrackChanged(ev) => Self::TrackChanged {
+                current_track_index: ev.current_track_index,
+                track: ev.track,
+            },
+            Update::PlayStateChanged(ev) => Self::PlayStateChanged {
+                status: Status::from_u32(ev.status),
+            },
+            // clamped to u16::MAX, also send is a u16, but protobuf does not support u16 directly
+            #[allow(clippy::cast_possible_truncation)]
+            Update::VolumeChanged(ev) => Self::VolumeChanged {
+                volume: ev.volume.min(u32::from(u16::MAX)) as u16,
+            },
+            Update::SpeedChanged(ev) => Self::SpeedChanged {
+                speed: speed_from_fields(ev.speed_percent, ev.speed),
+            },
+            Update::GaplessChanged(ev) => Self::GaplessChanged {
+                gapless: ev.gapless,
+            },
+            Update::ProgressChanged(ev) => Self::Progress(ev.progress.unwrap_or_default().into()),
+            Update::RadioTitleChanged(ev) => Self::RadioTitleChanged { title: ev.title },
+            Update::PlaylistChanged(_) => Self::PlaylistChanged,
+            Update::LoopModeChanged(ev) => Self::LoopModeChanged {
+                loop_mode: Loop::from_u32(ev.loop_mode)
+                    .ok_or_else(|| anyhow!("Got a unknown loop mode: {}", ev.loop_mode))?,
+            },
+            Update::LibraryChanged(_) => Self::LibraryChanged,
+        };
+
+        Ok(res)
+    }
+}
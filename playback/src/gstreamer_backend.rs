@@ -21,10 +21,11 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use super::{PlayerCmd, PlayerProgress, PlayerTrait};
+use super::{PlayerCmd, PlayerProgress, PlayerTrait, SPEED_MAX, SPEED_MIN, SPEED_STEP};
 use anyhow::Result;
 use async_trait::async_trait;
 use glib::FlagsClass;
+use glib::SignalHandlerId;
 use gst::bus::BusWatchGuard;
 use gst::ClockTime;
 use gst::{event::Seek, Element, SeekFlags, SeekType};
@@ -32,11 +33,14 @@ use gstreamer as gst;
 use gstreamer::prelude::*;
 use parking_lot::Mutex;
 use std::path::Path;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::time::Duration;
-use termusiclib::config::Settings;
+use termusiclib::config::{
+    ChannelMixSettings, Crossfade, ReplayGainMode, ReplayGainSettings, Settings, VolumeCurve,
+    BALANCE_MAX,
+};
 use termusiclib::track::{MediaType, Track};
 
 /// This trait allows for easy conversion of a path to a URI
@@ -55,14 +59,157 @@ impl PathToURI for Path {
 
 pub struct GStreamerBackend {
     playbin: Element,
-    volume: u16,
+    volume: Arc<AtomicU16>,
+    volume_max: u16,
+    volume_curve: VolumeCurve,
     speed: i32,
     pub gapless: bool,
     pub message_tx: Sender<PlayerCmd>,
     pub radio_title: Arc<Mutex<String>>,
+    /// `rgvolume` filter, [`None`] if the "replaygain" plugin is not installed
+    rgvolume: Option<Element>,
+    /// audio sink with the `limiter`, `mono` and `balance` filters, [`None`] if a plugin is missing
+    sink_bin: Option<gst::Bin>,
+    channel_mix: ChannelMixSettings,
+    replay_gain: ReplayGainSettings,
+    /// channel to the glib mainloop, used by the watches of every playbin
+    main_tx: async_channel::Sender<PlayerCmd>,
+    /// whether an `Eos` signal was sent since the last `StreamStart`
+    eos_watcher: Arc<AtomicBool>,
+    about_to_finish_handler: SignalHandlerId,
+    /// playbin of the previous track while crossfading
+    fading_playbin: Arc<Mutex<Option<Element>>>,
     _bus_watch_guard: BusWatchGuard,
 }
 
+/// Filters in front of the audio sink: a limiter for amplified sound, a capsfilter to downmix to
+/// mono and the balance
+const SINK_BIN: &str = "rglimiter name=limiter ! audioconvert ! capsfilter name=mono \
+    ! audioconvert ! audiopanorama name=balance method=simple ! autoaudiosink";
+
+/// Create a `playbin3` playing audio only, with an `rgvolume` filter and the filters of
+/// [`SINK_BIN`] if available
+fn new_playbin() -> (Element, Option<Element>, Option<gst::Bin>) {
+    let playbin = Box::new(gst::ElementFactory::make("playbin3"))
+        .build()
+        .expect("playbin3 make error");
+
+    let (sink, sink_bin) = match gst::parse_bin_from_description(SINK_BIN, true) {
+        Ok(bin) => (bin.clone().upcast::<Element>(), Some(bin)),
+        Err(e) => {
+            warn!("Soft limiting, mono and balance are not available: {e}");
+            let sink = gst::ElementFactory::make("autoaudiosink")
+                .build()
+                .expect("audio sink make error");
+            (sink, None)
+        }
+    };
+
+    playbin.set_property("audio-sink", &sink);
+
+    let rgvolume = gst::ElementFactory::make("rgvolume")
+        .build()
+        .map_err(|e| warn!("ReplayGain is not available: {e}"))
+        .ok();
+    // Set flags to show Audio and Video but ignore Subtitles
+    let flags = playbin.property_value("flags");
+    let flags_class = FlagsClass::with_type(flags.type_()).unwrap();
+
+    let flags = flags_class
+        .builder_with_value(flags)
+        .unwrap()
+        .set_by_nick("audio")
+        .set_by_nick("download")
+        .unset_by_nick("video")
+        .unset_by_nick("text")
+        .build()
+        .unwrap();
+    playbin.set_property_from_value("flags", &flags);
+
+    (playbin, rgvolume, sink_bin)
+}
+
+/// Handle the messages from the bus of `playbin`, until the returned guard is dropped
+fn watch_bus(
+    playbin: &Element,
+    main_tx: &async_channel::Sender<PlayerCmd>,
+    eos_watcher: Arc<AtomicBool>,
+    radio_title: Arc<Mutex<String>>,
+) -> BusWatchGuard {
+    playbin
+        .bus()
+        .expect("Failed to get GStreamer message bus")
+        .add_watch(glib::clone!(@strong main_tx=> move |_bus, msg| {
+            match msg.view() {
+                gst::MessageView::Eos(_) => {
+                    main_tx.send_blocking(PlayerCmd::Eos)
+                        .expect("Unable to send message to main()");
+                    eos_watcher.store(true, std::sync::atomic::Ordering::SeqCst);
+                },
+                gst::MessageView::StreamStart(_e) => {
+                    if !eos_watcher.load(std::sync::atomic::Ordering::SeqCst) {
+                        trace!("Sending EOS because it was not sent since last StreamStart");
+                        main_tx.send_blocking(PlayerCmd::Eos)
+                            .expect("Unable to send message to main()");
+                    }
+
+                    eos_watcher.store(false, std::sync::atomic::Ordering::SeqCst);
+                }
+                gst::MessageView::Error(e) =>
+                    error!("GStreamer Error: {}", e.error()),
+                gst::MessageView::Tag(tag) => {
+                    if let Some(title) = tag.tags().get::<gst::tags::Title>() {
+                        info!("  Title: {}", title.get());
+                        *radio_title.lock() = format!("Current Playing: {}",title.get()).to_string();
+                    }
+                    // if let Some(artist) = tag.tags().get::<gst::tags::Artist>() {
+                    //     info!("  Artist: {}", artist.get());
+                    //     // *radio_title_internal.lock() = artist.get().to_string();
+                    // }
+                    // if let Some(album) = tag.tags().get::<gst::tags::Album>() {
+                    //     info!("  Album: {}", album.get());
+                    //     // *radio_title_internal.lock() = album.get().to_string();
+                    // }
+                }
+                gst::MessageView::Buffering(buffering) => {
+                    // let (mode,_, _, left) = buffering.buffering_stats();
+                    // info!("mode is: {mode:?}, and left is: {left}");
+                    let percent = buffering.percent();
+                    if percent < 100 {
+                        let _ = main_tx.send_blocking(PlayerCmd::Pause);
+                    } else {
+                        let _ = main_tx.send_blocking(PlayerCmd::Play);
+                    }
+                    // Left for debug
+                    // let msg = buffering.message();
+                    // info!("message is: {msg:?}");
+                }
+                gst::MessageView::Warning(warning) => {
+                    info!("GStreamer Warning: {}", warning.error());
+                }
+                // Left for debug
+                // msg => {
+                //     info!("msg: {msg:?}");
+                // }
+                _ => (),
+            }
+             glib::ControlFlow::Continue
+        }))
+        .expect("Failed to connect to GStreamer message bus")
+}
+
+/// Send a signal to enqueue the next media before the current finished
+fn connect_about_to_finish(
+    playbin: &Element,
+    main_tx: async_channel::Sender<PlayerCmd>,
+) -> SignalHandlerId {
+    playbin.connect("about-to-finish", false, move |_| {
+        debug!("Sending playbin AboutToFinish");
+        main_tx.send_blocking(PlayerCmd::AboutToFinish).unwrap();
+        None
+    })
+}
+
 #[allow(clippy::cast_lossless)]
 impl GStreamerBackend {
     #[allow(clippy::too_many_lines)]
@@ -104,96 +251,14 @@ impl GStreamerBackend {
                 std::thread::sleep(std::time::Duration::from_millis(100));
             })
             .expect("failed to start gstreamer event loop thread");
-        let playbin = Box::new(gst::ElementFactory::make("playbin3"))
-            .build()
-            .expect("playbin3 make error");
-
-        let sink = gst::ElementFactory::make("autoaudiosink")
-            .build()
-            .expect("audio sink make error");
-
-        playbin.set_property("audio-sink", &sink);
-        // Set flags to show Audio and Video but ignore Subtitles
-        let flags = playbin.property_value("flags");
-        let flags_class = FlagsClass::with_type(flags.type_()).unwrap();
-
-        let flags = flags_class
-            .builder_with_value(flags)
-            .unwrap()
-            .set_by_nick("audio")
-            .set_by_nick("download")
-            .unset_by_nick("video")
-            .unset_by_nick("text")
-            .build()
-            .unwrap();
-        playbin.set_property_from_value("flags", &flags);
+        let (playbin, rgvolume, sink_bin) = new_playbin();
 
         // Asynchronous channel to communicate with main() with
         let (main_tx, main_rx) = async_channel::bounded(3);
         // Handle messages from GStreamer bus
 
         let radio_title = Arc::new(Mutex::new(String::new()));
-        let radio_title_internal = radio_title.clone();
-        let bus_watch = playbin
-            .bus()
-            .expect("Failed to get GStreamer message bus")
-            .add_watch(glib::clone!(@strong main_tx=> move |_bus, msg| {
-                match msg.view() {
-                    gst::MessageView::Eos(_) => {
-                        main_tx.send_blocking(PlayerCmd::Eos)
-                            .expect("Unable to send message to main()");
-                        eos_watcher.store(true, std::sync::atomic::Ordering::SeqCst);
-                    },
-                    gst::MessageView::StreamStart(_e) => {
-                        if !eos_watcher.load(std::sync::atomic::Ordering::SeqCst) {
-                            trace!("Sending EOS because it was not sent since last StreamStart");
-                            main_tx.send_blocking(PlayerCmd::Eos)
-                                .expect("Unable to send message to main()");
-                        }
-
-                        eos_watcher.store(false, std::sync::atomic::Ordering::SeqCst);
-                    }
-                    gst::MessageView::Error(e) =>
-                        error!("GStreamer Error: {}", e.error()),
-                    gst::MessageView::Tag(tag) => {
-                        if let Some(title) = tag.tags().get::<gst::tags::Title>() {
-                            info!("  Title: {}", title.get());
-                            *radio_title_internal.lock() = format!("Current Playing: {}",title.get()).to_string();
-                        }
-                        // if let Some(artist) = tag.tags().get::<gst::tags::Artist>() {
-                        //     info!("  Artist: {}", artist.get());
-                        //     // *radio_title_internal.lock() = artist.get().to_string();
-                        // }
-                        // if let Some(album) = tag.tags().get::<gst::tags::Album>() {
-                        //     info!("  Album: {}", album.get());
-                        //     // *radio_title_internal.lock() = album.get().to_string();
-                        // }
-                    }
-                    gst::MessageView::Buffering(buffering) => {
-                        // let (mode,_, _, left) = buffering.buffering_stats();
-                        // info!("mode is: {mode:?}, and left is: {left}");
-                        let percent = buffering.percent();
-                        if percent < 100 {
-                            let _ = main_tx.send_blocking(PlayerCmd::Pause);
-                        } else {
-                            let _ = main_tx.send_blocking(PlayerCmd::Play);
-                        }
-                        // Left for debug
-                        // let msg = buffering.message();
-                        // info!("message is: {msg:?}");
-                    }
-                    gst::MessageView::Warning(warning) => {
-                        info!("GStreamer Warning: {}", warning.error());
-                    }
-                    // Left for debug
-                    // msg => {
-                    //     info!("msg: {msg:?}");
-                    // }
-                    _ => (),
-                }
-                 glib::ControlFlow::Continue
-            }))
-            .expect("Failed to connect to GStreamer message bus");
+        let bus_watch = watch_bus(&playbin, &main_tx, eos_watcher.clone(), radio_title.clone());
 
         let tx = message_tx.clone();
         // extra thread to run the glib mainloop on
@@ -212,45 +277,156 @@ impl GStreamerBackend {
         });
 
         let volume = config.player_volume;
-        let speed = config.player_speed;
+        let speed = config.player_speed_percent;
         let gapless = config.player_gapless;
+        let about_to_finish_handler = connect_about_to_finish(&playbin, main_tx.clone());
 
         let mut this = Self {
             playbin,
-            volume,
+            volume: Arc::new(AtomicU16::new(volume)),
+            volume_max: config.volume_max(),
+            volume_curve: config.player_volume_curve,
             speed,
             gapless,
             message_tx,
             radio_title,
+            rgvolume,
+            sink_bin,
+            channel_mix: config.player_channel_mix,
+            replay_gain: ReplayGainSettings::default(),
+            main_tx,
+            eos_watcher,
+            about_to_finish_handler,
+            fading_playbin: Arc::new(Mutex::new(None)),
             _bus_watch_guard: bus_watch,
         };
 
         this.set_volume(volume);
         this.set_speed(speed);
-
-        // Send a signal to enqueue the next media before the current finished
-        this.playbin.connect("about-to-finish", false, move |_| {
-            debug!("Sending playbin AboutToFinish");
-            main_tx.send_blocking(PlayerCmd::AboutToFinish).unwrap();
-            None
-        });
+        this.set_replay_gain(config.player_replaygain.resolve(config.player_loop_mode));
+        this.update_channel_mix();
 
         this
     }
     pub fn skip_one(&mut self) {
         self.message_tx.send(PlayerCmd::Eos).ok();
     }
+    /// Insert the `rgvolume` filter if `ReplayGain` is enabled, only possible while not playing
+    fn update_audio_filter(&self) {
+        let filter = self
+            .rgvolume
+            .as_ref()
+            .filter(|_| self.replay_gain.mode != ReplayGainMode::Off);
+        self.playbin.set_property("audio-filter", filter);
+    }
     pub fn enqueue_next(&mut self, next_track: &str) {
-        if next_track.starts_with("http") {
-            self.playbin.set_property("uri", next_track);
+        self.playbin.set_property("uri", Self::to_uri(next_track));
+    }
+    fn to_uri(file: &str) -> String {
+        if file.starts_with("http") {
+            file.to_string()
         } else {
-            let path = Path::new(next_track);
-            self.playbin.set_property("uri", path.to_uri());
+            Path::new(file).to_uri()
         }
     }
+    /// Start playing `next_track` in a new playbin and fade it in, while the current one fades out
+    fn crossfade_into(&mut self, next_track: &str, crossfade: Crossfade) {
+        let (playbin, rgvolume, sink_bin) = new_playbin();
+        let bus_watch = watch_bus(
+            &playbin,
+            &self.main_tx,
+            self.eos_watcher.clone(),
+            self.radio_title.clone(),
+        );
+        let about_to_finish_handler = connect_about_to_finish(&playbin, self.main_tx.clone());
+
+        // the previous playbin must neither request a next track nor report its end anymore
+        let previous = std::mem::replace(&mut self.playbin, playbin);
+        previous.disconnect(std::mem::replace(
+            &mut self.about_to_finish_handler,
+            about_to_finish_handler,
+        ));
+        self._bus_watch_guard = bus_watch;
+        self.rgvolume = rgvolume;
+        self.sink_bin = sink_bin;
+        self.update_limiter();
+        self.update_channel_mix();
+        self.set_replay_gain(self.replay_gain);
+        self.update_audio_filter();
+
+        self.set_volume_inside(0.0);
+        self.playbin.set_property("uri", Self::to_uri(next_track));
+        self.playbin
+            .set_state(gst::State::Playing)
+            .expect("set gst state playing error");
+        if self.speed != 100 {
+            // the rate can only be changed once the new playbin is playing
+            let _ = self.playbin.state(ClockTime::from_seconds(1));
+            self.send_seek_event(self.speed);
+        }
+
+        if let Some(fading) = self.fading_playbin.lock().replace(previous) {
+            fading.set_state(gst::State::Null).ok();
+        }
+        let next = self.playbin.clone();
+        let fading_playbin = self.fading_playbin.clone();
+        let volume = self.volume.clone();
+        let volume_curve = self.volume_curve;
+        std::thread::Builder::new()
+            .name("gst crossfade".into())
+            .spawn(move || {
+                const STEP: Duration = Duration::from_millis(50);
+                let steps = (crossfade.duration.as_millis() / STEP.as_millis()).max(1);
+                for step in 0..=steps {
+                    #[allow(clippy::cast_precision_loss)]
+                    let progress = step as f32 / steps as f32;
+                    let volume = f64::from(volume_curve.factor(volume.load(Ordering::SeqCst)));
+                    if let Some(fading) = &*fading_playbin.lock() {
+                        let gain = crossfade.curve.gain(1.0 - progress);
+                        fading.set_property("volume", volume * f64::from(gain));
+                    }
+                    next.set_property("volume", volume * f64::from(crossfade.curve.gain(progress)));
+                    std::thread::sleep(STEP);
+                }
+                if let Some(fading) = fading_playbin.lock().take() {
+                    fading.set_state(gst::State::Null).ok();
+                }
+            })
+            .expect("failed to start gstreamer crossfade thread");
+    }
     fn set_volume_inside(&mut self, volume: f64) {
         self.playbin.set_property("volume", volume);
     }
+    /// Get the factor of the current volume on its curve
+    fn volume_factor(&self) -> f64 {
+        f64::from(self.volume_curve.factor(self.volume()))
+    }
+    /// Limit the sound only while it is amplified, so it is unchanged up to a volume of 100
+    fn update_limiter(&self) {
+        if let Some(limiter) = self.sink_filter("limiter") {
+            limiter.set_property("enabled", self.volume_factor() > 1.0);
+        }
+    }
+    fn update_channel_mix(&self) {
+        if let Some(mono) = self.sink_filter("mono") {
+            let caps = if self.channel_mix.mono {
+                gst::Caps::builder("audio/x-raw")
+                    .field("channels", 1)
+                    .build()
+            } else {
+                gst::Caps::new_any()
+            };
+            mono.set_property("caps", caps);
+        }
+        if let Some(balance) = self.sink_filter("balance") {
+            let panorama = f32::from(self.channel_mix.balance()) / f32::from(BALANCE_MAX);
+            balance.set_property("panorama", panorama);
+        }
+    }
+    /// Get the filter `name` of [`SINK_BIN`] in the audio sink
+    fn sink_filter(&self, name: &str) -> Option<Element> {
+        self.sink_bin.as_ref().and_then(|bin| bin.by_name(name))
+    }
 
     fn get_position(&self) -> ClockTime {
         match self.playbin.query_position::<ClockTime>() {
@@ -269,7 +445,7 @@ impl GStreamerBackend {
 
     fn send_seek_event(&mut self, rate: i32) -> bool {
         self.speed = rate;
-        let rate = rate as f64 / 10.0;
+        let rate = rate as f64 / 100.0;
         // Obtain the current position, needed for the seek event
         let position = self.get_position();
 
@@ -320,6 +496,7 @@ impl PlayerTrait for GStreamerBackend {
         self.playbin
             .set_state(gst::State::Ready)
             .expect("set gst state ready error.");
+        self.update_audio_filter();
         match track.media_type {
             Some(MediaType::Music) => {
                 if let Some(file) = track.file() {
@@ -340,33 +517,52 @@ impl PlayerTrait for GStreamerBackend {
     }
 
     fn volume_up(&mut self) {
-        self.set_volume(self.volume.saturating_add(5));
+        self.set_volume(self.volume().saturating_add(5));
     }
 
     fn volume_down(&mut self) {
-        self.set_volume(self.volume.saturating_sub(5));
+        self.set_volume(self.volume().saturating_sub(5));
     }
 
     fn volume(&self) -> u16 {
-        self.volume
+        self.volume.load(Ordering::SeqCst)
     }
 
     fn set_volume(&mut self, volume: u16) {
-        let volume = volume.min(100);
-        self.volume = volume;
-        self.set_volume_inside(f64::from(volume) / 100.0);
+        let volume = volume.min(self.volume_max);
+        self.volume.store(volume, Ordering::SeqCst);
+        self.set_volume_inside(self.volume_factor());
+        self.update_limiter();
+    }
+
+    fn set_volume_curve(&mut self, curve: VolumeCurve, max: u16) {
+        self.volume_curve = curve;
+        self.volume_max = max;
+        // apply the curve and lower the volume if it is above the new maximum
+        self.set_volume(self.volume());
+    }
+
+    fn set_channel_mix(&mut self, settings: ChannelMixSettings) {
+        self.channel_mix = settings;
+        self.update_channel_mix();
     }
 
     fn pause(&mut self) {
         self.playbin
             .set_state(gst::State::Paused)
             .expect("set gst state paused error");
+        if let Some(fading) = &*self.fading_playbin.lock() {
+            fading.set_state(gst::State::Paused).ok();
+        }
     }
 
     fn resume(&mut self) {
         self.playbin
             .set_state(gst::State::Playing)
             .expect("set gst state playing error in resume");
+        if let Some(fading) = &*self.fading_playbin.lock() {
+            fading.set_state(gst::State::Playing).ok();
+        }
     }
 
     fn is_paused(&self) -> bool {
@@ -398,7 +594,7 @@ impl PlayerTrait for GStreamerBackend {
         self.set_volume_inside(0.0);
         self.playbin
             .seek_simple(gst::SeekFlags::FLUSH, seek_pos_clock)?; // ignore any errors
-        self.set_volume_inside(f64::from(self.volume) / 100.0);
+        self.set_volume_inside(self.volume_factor());
         Ok(())
     }
 
@@ -414,7 +610,7 @@ impl PlayerTrait for GStreamerBackend {
         {
             std::thread::sleep(Duration::from_millis(100));
         }
-        self.set_volume_inside(f64::from(self.volume) / 100.0);
+        self.set_volume_inside(self.volume_factor());
     }
     fn speed(&self) -> i32 {
         self.speed
@@ -425,24 +621,20 @@ impl PlayerTrait for GStreamerBackend {
     }
 
     fn speed_up(&mut self) {
-        let mut speed = self.speed + 1;
-        if speed > 30 {
-            speed = 30;
-        }
+        let speed = (self.speed + SPEED_STEP).min(SPEED_MAX);
         if !self.send_seek_event(speed) {
             error!("error set speed");
         }
     }
 
     fn speed_down(&mut self) {
-        let mut speed = self.speed - 1;
-        if speed < 1 {
-            speed = 1;
-        }
-        self.set_speed(speed);
+        self.set_speed((self.speed - SPEED_STEP).max(SPEED_MIN));
     }
     fn stop(&mut self) {
         self.playbin.set_state(gst::State::Null).ok();
+        if let Some(fading) = self.fading_playbin.lock().take() {
+            fading.set_state(gst::State::Null).ok();
+        }
     }
 
     #[allow(clippy::cast_precision_loss)]
@@ -466,8 +658,26 @@ impl PlayerTrait for GStreamerBackend {
         self.skip_one();
     }
 
-    fn enqueue_next(&mut self, file: &str) {
-        self.enqueue_next(file);
+    fn enqueue_next(&mut self, track: &Track, crossfade: Option<Crossfade>) {
+        let Some(file) = track.file() else {
+            return;
+        };
+        match crossfade {
+            Some(crossfade) => self.crossfade_into(file, crossfade),
+            None => self.enqueue_next(file),
+        }
+    }
+
+    /// Enabling or disabling it only applies to tracks started afterwards
+    fn set_replay_gain(&mut self, settings: ReplayGainSettings) {
+        self.replay_gain = settings;
+        if let Some(rgvolume) = &self.rgvolume {
+            rgvolume.set_property("album-mode", settings.mode != ReplayGainMode::Track);
+            rgvolume.set_property("pre-amp", f64::from(settings.preamp).clamp(-60.0, 60.0));
+            // with no headroom the gain is lowered so that the peak does not clip
+            let headroom: f64 = if settings.prevent_clipping { 0.0 } else { 60.0 };
+            rgvolume.set_property("headroom", headroom);
+        }
     }
 }
 
@@ -477,5 +687,8 @@ impl Drop for GStreamerBackend {
         self.playbin
             .set_state(gst::State::Null)
             .expect("Unable to set the pipeline to the `Null` state");
+        if let Some(fading) = self.fading_playbin.lock().take() {
+            fading.set_state(gst::State::Null).ok();
+        }
     }
 }
@@ -46,6 +46,48 @@ pub mod player {
             }
         }
     }
+
+    impl From<&termusiclib::track::Track> for TrackInfo {
+        fn from(track: &termusiclib::track::Track) -> Self {
+            use termusiclib::track::MediaType;
+
+            let media_type = match track.media_type {
+                Some(MediaType::Music) | None => 0,
+                Some(MediaType::Podcast) => 1,
+                Some(MediaType::LiveRadio) => 2,
+            };
+
+            Self {
+                title: track.title().unwrap_or_default().to_string(),
+                artist: track.artist().unwrap_or_default().to_string(),
+                album: track.album().unwrap_or_default().to_string(),
+                genre: track.genre().unwrap_or_default().to_string(),
+                duration: Some(track.duration().into()),
+                file: track.file().unwrap_or_default().to_string(),
+                media_type,
+                has_album_art: track.picture().is_some() || track.album_photo().is_some(),
+            }
+        }
+    }
+
+    impl From<&termusiclib::config::EqualizerSettings> for EqualizerReply {
+        fn from(equalizer: &termusiclib::config::EqualizerSettings) -> Self {
+            Self {
+                enabled: equalizer.enabled,
+                preset: equalizer.preset.as_u32(),
+                custom_gains: equalizer.custom_gains.to_vec(),
+            }
+        }
+    }
+
+    impl From<termusiclib::config::ChannelMixSettings> for ChannelMixReply {
+        fn from(channel_mix: termusiclib::config::ChannelMixSettings) -> Self {
+            Self {
+                mono: channel_mix.mono,
+                balance: i32::from(channel_mix.balance()),
+            }
+        }
+    }
 }
 
 #[cfg(feature = "gst")]
@@ -54,21 +96,31 @@ mod gstreamer_backend;
 mod mpv_backend;
 #[cfg(feature = "rusty")]
 mod rusty_backend;
+#[cfg(feature = "rusty")]
+pub use rusty_backend::loudness;
+#[cfg(feature = "rusty")]
+pub use rusty_backend::output_device_names;
 
+pub mod auth;
 mod discord;
+pub mod events;
 mod mpris;
 pub mod playlist;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+pub use events::{StreamTX, UpdateEvents};
 pub use playlist::{Playlist, Status};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use termusiclib::config::{LastPosition, SeekStep, Settings};
+use termusiclib::config::{
+    ChannelMixSettings, Crossfade, CrossfadeSettings, EqualizerSettings, LastPosition, Loop,
+    PreservePitch, ReplayGainSettings, SeekStep, Settings, SilenceSettings, VolumeCurve,
+};
 use termusiclib::podcast::db::Database as DBPod;
-use termusiclib::sqlite::DataBase;
-use termusiclib::track::{MediaType, Track};
-use termusiclib::utils::get_app_config_path;
+use termusiclib::sqlite::{DataBase, HistoryEvent};
+use termusiclib::track::{write_rating_tags, MediaType, Track};
+use termusiclib::utils::{self, get_app_config_path};
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
 #[macro_use]
@@ -185,19 +237,61 @@ impl Backend {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum PlayerCmd {
     AboutToFinish,
+    /// Add the paths to the library and analyze the loudness of their tracks, empty for all music directories
+    AnalyzeLoudness {
+        paths: Vec<String>,
+        write_tags: bool,
+        force: bool,
+    },
     CycleLoop,
     Eos,
     GetProgress,
     PlaySelected,
+    PlayIndex(usize),
+    /// Set the star rating of the current music track
+    RateCurrentTrack(u8),
+    PlayUri {
+        uri: String,
+        mode: EnqueueMode,
+    },
     SkipPrevious,
     Pause,
     Play,
+    PlaylistAddTracks {
+        tracks: Vec<String>,
+        at_index: Option<usize>,
+    },
+    PlaylistClear,
+    PlaylistMoveTrack {
+        from: usize,
+        to: usize,
+    },
+    PlaylistRemoveTracks {
+        at_index: usize,
+        amount: usize,
+    },
+    PlaylistShuffle,
+    PlaylistSwapTracks {
+        index_a: usize,
+        index_b: usize,
+    },
     ProcessID,
     Quit,
     ReloadConfig,
     ReloadPlaylist,
     SeekBackward,
     SeekForward,
+    /// Seek by the given amount of seconds, negative to seek backwards
+    SeekRelative(i64),
+    SeekTo(Duration),
+    SetChannelMix(ChannelMixSettings),
+    SetEqualizer(EqualizerSettings),
+    /// Output device name, empty for the default device
+    SetOutputDevice(String),
+    SetGapless(bool),
+    SetLoopMode(Loop),
+    SetSpeed(i32),
+    SetVolume(u16),
     SkipNext,
     SpeedDown,
     SpeedUp,
@@ -208,6 +302,39 @@ pub enum PlayerCmd {
     VolumeUp,
 }
 
+/// Where [`PlayerCmd::PlayUri`] inserts the new track into the playlist
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnqueueMode {
+    /// Insert after the current track
+    #[default]
+    Next,
+    /// Append at the end of the playlist
+    End,
+    /// Replace the whole playlist
+    Replace,
+}
+
+impl EnqueueMode {
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::Next => 0,
+            Self::End => 1,
+            Self::Replace => 2,
+        }
+    }
+
+    #[must_use]
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Next),
+            1 => Some(Self::End),
+            2 => Some(Self::Replace),
+            _ => None,
+        }
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct GeneralPlayer {
     pub backend: Backend,
@@ -219,6 +346,18 @@ pub struct GeneralPlayer {
     pub db: DataBase,
     pub db_podcast: DBPod,
     pub cmd_tx: PlayerCmdSender,
+    history_state: HistoryState,
+}
+
+/// State of the current track in the play history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HistoryState {
+    /// The reported position may still be the one of the previous track
+    Starting,
+    /// The position was seen below the play count percent
+    Playing,
+    /// The play or skip was added to the history
+    Recorded,
 }
 
 impl GeneralPlayer {
@@ -250,6 +389,7 @@ impl GeneralPlayer {
             db_podcast,
             cmd_tx,
             current_track_updated: false,
+            history_state: HistoryState::Starting,
         })
     }
 
@@ -272,10 +412,140 @@ impl GeneralPlayer {
         self.backend.as_player_mut()
     }
 
+    /// Apply `equalizer`, which is only supported by the rusty backend
+    pub fn set_equalizer(&mut self, equalizer: EqualizerSettings) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_equalizer(equalizer.active_gains());
+        }
+        self.config.player_equalizer = equalizer;
+    }
+
+    /// Apply `preserve_pitch`, which is only supported by the rusty backend
+    pub fn set_preserve_pitch(&mut self, preserve_pitch: PreservePitch) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_preserve_pitch(preserve_pitch);
+        }
+        self.config.player_preserve_pitch = preserve_pitch;
+    }
+
+    /// Apply `silence`, which is only supported by the rusty backend
+    pub fn set_silence(&mut self, silence: SilenceSettings) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_silence(silence);
+        }
+        self.config.player_silence = silence;
+    }
+
+    /// Get the total length of the silence left out since the start of the player
+    ///
+    /// Always zero for backends other than rusty.
+    #[must_use]
+    pub fn time_saved(&self) -> Duration {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref backend) = self.backend {
+            return *backend.time_saved.lock();
+        }
+        Duration::ZERO
+    }
+
+    /// Get the format the sound is currently sent to the output device in
+    ///
+    /// Only reported by the rusty backend.
+    #[must_use]
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref backend) = self.backend {
+            return backend.output_format.lock().clone();
+        }
+        None
+    }
+
+    /// Play local tracks at their own sample rate and channel count if the device supports it
+    ///
+    /// Only supported by the rusty backend.
+    pub fn set_native_output(&mut self, native_output: bool) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_native_output(native_output);
+        }
+        self.config.player_native_output = native_output;
+    }
+
+    /// Move playback to the output device called `name`, empty for the default device
+    ///
+    /// Only supported by the rusty backend.
+    pub fn set_output_device(&mut self, name: String) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_output_device(&name);
+        }
+        self.config.player_output_device = name;
+    }
+
+    /// Apply `crossfade`, which is only supported by the rusty and `GStreamer` backends
+    pub fn set_crossfade(&mut self, crossfade: CrossfadeSettings) {
+        #[cfg(feature = "rusty")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::Rusty(ref mut backend) = self.backend {
+            backend.set_crossfade(&crossfade);
+        }
+        self.config.player_crossfade = crossfade;
+    }
+
+    /// Enqueue the next track once the current one is within the crossfade duration of its end
+    ///
+    /// Only needed for the `GStreamer` backend, whose "about-to-finish" signal is too late to crossfade.
+    /// The rusty backend sends its about-to-finish signal early enough itself.
+    pub fn enqueue_for_crossfade(&mut self) {
+        #[cfg(feature = "gst")]
+        #[allow(irrefutable_let_patterns)]
+        if let Backend::GStreamer(_) = self.backend {
+            let Some(duration) = self.config.player_crossfade.duration() else {
+                return;
+            };
+            if self.playlist.status() != Status::Running
+                || self.playlist.has_next_track()
+                || self
+                    .playlist
+                    .current_track()
+                    .map_or(true, |track| track.media_type == Some(MediaType::LiveRadio))
+            {
+                return;
+            }
+            let progress = self.get_player().get_progress();
+            let Some(total_duration) = progress.total_duration else {
+                return;
+            };
+            if progress.position >= total_duration / 2
+                && total_duration.saturating_sub(progress.position) <= duration
+            {
+                self.enqueue_next_from_playlist();
+            }
+        }
+    }
+
+    /// Apply the [`ReplayGainSettings`] of the config, resolved for the current loop mode
+    pub fn apply_replay_gain(&mut self) {
+        let settings = self
+            .config
+            .player_replaygain
+            .resolve(self.playlist.loop_mode());
+        self.set_replay_gain(settings);
+    }
+
     pub fn toggle_gapless(&mut self) -> bool {
         let new_gapless = !self.backend.as_player().gapless();
-        self.backend.as_player_mut().set_gapless(new_gapless);
-        self.config.player_gapless = new_gapless;
+        self.set_gapless(new_gapless);
         new_gapless
     }
 
@@ -288,9 +558,11 @@ impl GeneralPlayer {
         }
 
         self.playlist.proceed();
+        self.history_state = HistoryState::Starting;
 
         if let Some(track) = self.playlist.current_track() {
-            let track = track.clone();
+            let mut track = track.clone();
+            self.with_analyzed_replay_gain(&mut track);
             if self.playlist.has_next_track() {
                 self.playlist.set_next_track(None);
                 self.current_track_updated = true;
@@ -324,10 +596,91 @@ impl GeneralPlayer {
         }
     }
 
+    /// Count the current track as played once `progress` reached `library_play_count_percent`
+    pub fn count_play(&mut self, progress: PlayerProgress) {
+        let Some(total_duration) = progress.total_duration else {
+            return;
+        };
+        let percent = u32::from(self.config.library_play_count_percent.clamp(1, 100));
+        let played = progress.position >= total_duration * percent / 100;
+        match self.history_state {
+            HistoryState::Starting if !played => self.history_state = HistoryState::Playing,
+            HistoryState::Playing if played => self.add_history(HistoryEvent::Play),
+            _ => {}
+        }
+    }
+
+    /// Count the current track as skipped, unless it already counted as played
+    pub fn count_skip(&mut self) {
+        self.add_history(HistoryEvent::Skip);
+    }
+
+    /// Count the current track as played as it reached its end
+    pub fn count_end(&mut self) {
+        self.add_history(HistoryEvent::Play);
+    }
+
+    /// Add `event` of the current music track to the play history, once per track
+    fn add_history(&mut self, event: HistoryEvent) {
+        if self.history_state == HistoryState::Recorded {
+            return;
+        }
+        self.history_state = HistoryState::Recorded;
+        let Some(track) = self.playlist.current_track() else {
+            return;
+        };
+        if track.media_type != Some(MediaType::Music) {
+            return;
+        }
+        if let Some(file) = track.file() {
+            if let Err(e) = self.db.add_history(file, event) {
+                error!("error adding {event:?} of {file} to the history: {e}");
+            }
+        }
+    }
+
+    /// Set the star rating of the current music track, also in its tags if `library_rating_write_tags`
+    ///
+    /// # Errors
+    ///
+    /// - if there is no current music track
+    /// - if the database or the tags cannot be written
+    pub fn rate_current_track(&mut self, rating: u8) -> Result<()> {
+        let file = self
+            .playlist
+            .current_track()
+            .filter(|track| track.media_type == Some(MediaType::Music))
+            .and_then(Track::file)
+            .context("no current music track to rate")?
+            .to_string();
+        self.db.set_rating(&file, rating)?;
+        if self.config.player_use_mpris {
+            self.mpris.set_rating(rating);
+        }
+        if self.config.library_rating_write_tags {
+            write_rating_tags(std::path::Path::new(&file), rating)?;
+        }
+        Ok(())
+    }
+
+    /// Use the stored loudness analysis of `track`, if it has no `ReplayGain` tags
+    fn with_analyzed_replay_gain(&mut self, track: &mut Track) {
+        if !track.replay_gain().is_empty() {
+            return;
+        }
+        if let Some(loudness) = track.file().and_then(|file| self.db.get_loudness(file)) {
+            track.set_replay_gain(loudness.replay_gain());
+        }
+    }
+
     fn add_and_play_mpris_discord(&mut self) {
         if let Some(track) = self.playlist.current_track() {
             if self.config.player_use_mpris {
-                self.mpris.add_and_play(track);
+                let rating = track
+                    .file()
+                    .filter(|_| track.media_type == Some(MediaType::Music))
+                    .map_or(0, |file| self.db.get_rating(file).0);
+                self.mpris.add_and_play(track, rating);
             }
 
             if self.config.player_use_discord {
@@ -340,20 +693,32 @@ impl GeneralPlayer {
             return;
         }
 
-        let track = match self.playlist.fetch_next_track() {
+        let mut track = match self.playlist.fetch_next_track() {
             Some(t) => t.clone(),
             None => return,
         };
+        self.with_analyzed_replay_gain(&mut track);
+
+        let crossfade = self
+            .playlist
+            .current_track()
+            .and_then(|current| self.config.player_crossfade.between(current, &track));
+        // crossfading needs the next track early, even if not played gapless
+        if crossfade.is_none() && !self.config.player_gapless {
+            return;
+        }
 
         self.playlist.set_next_track(Some(&track));
         if let Some(file) = track.file() {
-            self.get_player_mut().enqueue_next(file);
+            self.get_player_mut().enqueue_next(&track, crossfade);
 
             info!("Next track enqueued: {:#?}", file);
         }
     }
 
     pub fn next(&mut self) {
+        // the rest of the current track is not played, so its end does not count
+        self.history_state = HistoryState::Recorded;
         if self.playlist.current_track().is_some() {
             info!("skip route 1 which is in most cases.");
             self.playlist.set_next_track(None);
@@ -371,6 +736,42 @@ impl GeneralPlayer {
         self.playlist.proceed_false();
         self.next();
     }
+    /// Stop the current track and play the track at `index` in the playlist instead
+    pub fn play_index(&mut self, index: usize) {
+        self.player_save_last_position();
+        self.jump_to_index(index);
+    }
+    fn jump_to_index(&mut self, index: usize) {
+        self.playlist.set_current_track_index(index);
+        self.playlist.proceed_false();
+        self.next();
+    }
+    /// Insert the track at `uri` (local file, podcast episode or radio url) and play it
+    ///
+    /// # Errors
+    /// if `uri` could not be read as a track
+    pub fn play_uri(&mut self, uri: &str, mode: EnqueueMode) -> Result<()> {
+        let location = utils::uri_to_location(uri)?;
+        let track = Playlist::tracks_from_uris(&[location])?
+            .pop()
+            .with_context(|| format!("could not read track {uri}"))?;
+
+        // save before the playlist changes, otherwise the position may be saved for the wrong track
+        self.player_save_last_position();
+        let index = match mode {
+            EnqueueMode::Next if self.playlist.is_empty() => 0,
+            EnqueueMode::Next => self.playlist.get_current_track_index() + 1,
+            EnqueueMode::End => self.playlist.len(),
+            EnqueueMode::Replace => {
+                self.playlist.clear();
+                0
+            }
+        };
+        self.playlist.add_tracks(vec![track], Some(index));
+        self.jump_to_index(index);
+
+        Ok(())
+    }
     pub fn toggle_pause(&mut self) {
         match self.playlist.status() {
             Status::Running => {
@@ -568,6 +969,12 @@ impl PlayerTrait for GeneralPlayer {
     fn set_volume(&mut self, volume: u16) {
         self.get_player_mut().set_volume(volume);
     }
+    fn set_volume_curve(&mut self, curve: VolumeCurve, max: u16) {
+        self.get_player_mut().set_volume_curve(curve, max);
+    }
+    fn set_channel_mix(&mut self, settings: ChannelMixSettings) {
+        self.get_player_mut().set_channel_mix(settings);
+    }
     fn pause(&mut self) {
         self.playlist.set_status(Status::Paused);
         self.get_player_mut().pause();
@@ -587,15 +994,16 @@ impl PlayerTrait for GeneralPlayer {
     }
 
     fn set_speed(&mut self, speed: i32) {
-        self.get_player_mut().set_speed(speed);
+        self.get_player_mut()
+            .set_speed(speed.clamp(SPEED_MIN, SPEED_MAX));
     }
 
     fn speed_up(&mut self) {
-        self.get_player_mut().speed_up();
+        self.set_speed(self.speed() + self.config.player_speed_step);
     }
 
     fn speed_down(&mut self) {
-        self.get_player_mut().speed_down();
+        self.set_speed(self.speed() - self.config.player_speed_step);
     }
 
     fn speed(&self) -> i32 {
@@ -619,6 +1027,7 @@ impl PlayerTrait for GeneralPlayer {
 
     fn set_gapless(&mut self, to: bool) {
         self.get_player_mut().set_gapless(to);
+        self.config.player_gapless = to;
     }
 
     fn skip_one(&mut self) {
@@ -629,8 +1038,36 @@ impl PlayerTrait for GeneralPlayer {
         self.get_player().position()
     }
 
-    fn enqueue_next(&mut self, file: &str) {
-        self.get_player_mut().enqueue_next(file);
+    fn enqueue_next(&mut self, track: &Track, crossfade: Option<Crossfade>) {
+        self.get_player_mut().enqueue_next(track, crossfade);
+    }
+
+    fn set_replay_gain(&mut self, settings: ReplayGainSettings) {
+        self.get_player_mut().set_replay_gain(settings);
+    }
+}
+
+/// Lowest speed a backend can be set to, in percent (`10` is `0.1x`)
+pub const SPEED_MIN: i32 = 10;
+/// Highest speed a backend can be set to, in percent (`300` is `3.0x`)
+pub const SPEED_MAX: i32 = 300;
+/// Default step of [`PlayerTrait::speed_up`] and [`PlayerTrait::speed_down`], in percent
+pub const SPEED_STEP: i32 = 10;
+
+/// Convert a speed in percent to the tenths of the older `speed` fields of the protocol
+#[must_use]
+pub const fn speed_to_tenths(percent: i32) -> i32 {
+    (percent + 5) / 10
+}
+
+/// Get the speed in percent from a message with `speed_percent` and the older `speed` in tenths,
+/// which is all that clients from before `speed_percent` set
+#[must_use]
+pub const fn speed_from_fields(percent: i32, tenths: i32) -> i32 {
+    if percent == 0 {
+        tenths * 10
+    } else {
+        percent
     }
 }
 
@@ -663,6 +1100,45 @@ impl From<PlayerProgress> for crate::player::PlayerTime {
     }
 }
 
+/// Format of the sound as it is sent to the output device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Type of the samples, like `f32` or `i16`
+    pub sample_format: String,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} Hz, {} channels, {}",
+            self.sample_rate, self.channels, self.sample_format
+        )
+    }
+}
+
+impl From<crate::player::OutputFormat> for OutputFormat {
+    fn from(value: crate::player::OutputFormat) -> Self {
+        Self {
+            sample_rate: value.sample_rate,
+            channels: u16::try_from(value.channels).unwrap_or(u16::MAX),
+            sample_format: value.sample_format,
+        }
+    }
+}
+
+impl From<OutputFormat> for crate::player::OutputFormat {
+    fn from(value: OutputFormat) -> Self {
+        Self {
+            sample_rate: value.sample_rate,
+            channels: u32::from(value.channels),
+            sample_format: value.sample_format,
+        }
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[async_trait]
 pub trait PlayerTrait {
@@ -671,6 +1147,10 @@ pub trait PlayerTrait {
     fn volume_up(&mut self);
     fn volume_down(&mut self);
     fn set_volume(&mut self, volume: u16);
+    /// Map the volume onto `curve` and allow it up to `max`, amplifying and limiting above `100`
+    fn set_volume_curve(&mut self, curve: VolumeCurve, max: u16);
+    /// Apply the mono downmix and balance of `settings` to the current and all later tracks
+    fn set_channel_mix(&mut self, settings: ChannelMixSettings);
     fn pause(&mut self);
     fn resume(&mut self);
     fn is_paused(&self) -> bool;
@@ -699,5 +1179,37 @@ pub trait PlayerTrait {
     fn position(&self) -> PlayerTimeUnit {
         self.get_progress().position
     }
-    fn enqueue_next(&mut self, file: &str);
+    /// Play `track` after the current one, fading between them if `crossfade` is given
+    ///
+    /// Backends that cannot crossfade play it after the current one instead.
+    fn enqueue_next(&mut self, track: &Track, crossfade: Option<Crossfade>);
+    /// Apply the loudness normalization `settings`, [`ReplayGainMode::Auto`] is already resolved
+    ///
+    /// [`ReplayGainMode::Auto`]: termusiclib::config::ReplayGainMode::Auto
+    fn set_replay_gain(&mut self, settings: ReplayGainSettings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_speed_to_tenths() {
+        assert_eq!(speed_to_tenths(100), 10);
+        assert_eq!(speed_to_tenths(SPEED_MIN), 1);
+        assert_eq!(speed_to_tenths(SPEED_MAX), 30);
+        // rounded to the nearest tenth
+        assert_eq!(speed_to_tenths(104), 10);
+        assert_eq!(speed_to_tenths(105), 11);
+        assert_eq!(speed_to_tenths(125), 13);
+    }
+
+    #[test]
+    fn test_speed_from_fields() {
+        assert_eq!(speed_from_fields(125, 13), 125);
+        assert_eq!(speed_from_fields(125, 0), 125);
+        // older clients only set the tenths
+        assert_eq!(speed_from_fields(0, 15), 150);
+    }
 }
@@ -0,0 +1,534 @@
+//! MPRIS service on dbus, used instead of the one of souvlaki
+//!
+//! The metadata of souvlaki cannot be extended, but `xesam:userRating` should be exported too.
+//! The interface is the same as [`souvlaki::MediaControls`], so that the platforms are handled alike.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::blocking::Connection;
+use dbus::channel::{MatchingReceiver, Sender};
+use dbus::message::{MatchRule, SignalArgs};
+use dbus::Path;
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+use parking_lot::Mutex;
+use souvlaki::{
+    MediaControlEvent, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection,
+};
+
+/// Object path the MPRIS interfaces are served at
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+/// Interface of all properties that change while playing
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+/// How long to wait for dbus messages, before handling the next events of the player
+const PROCESS_TIMEOUT: Duration = Duration::from_millis(50);
+
+type EventHandler = Arc<Mutex<Box<dyn Fn(MediaControlEvent) + Send>>>;
+
+/// Metadata of the current track, as exported in the `Metadata` property
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Metadata {
+    title: Option<String>,
+    album: Option<String>,
+    artist: Option<String>,
+    cover_url: Option<String>,
+    /// Length in microseconds
+    length: Option<i64>,
+    /// Rating from `0.0` to `1.0`
+    user_rating: Option<f64>,
+}
+
+impl From<MediaMetadata<'_>> for Metadata {
+    fn from(value: MediaMetadata<'_>) -> Self {
+        Self {
+            title: value.title.map(String::from),
+            album: value.album.map(String::from),
+            artist: value.artist.map(String::from),
+            cover_url: value.cover_url.map(String::from),
+            length: value
+                .duration
+                .and_then(|v| i64::try_from(v.as_micros()).ok()),
+            user_rating: None,
+        }
+    }
+}
+
+impl Metadata {
+    fn to_dict(&self) -> PropMap {
+        let mut dict = PropMap::new();
+        let mut insert = |key: &str, value: Box<dyn RefArg>| {
+            dict.insert(key.to_string(), Variant(value));
+        };
+
+        // there is no tracklist, but clients need a track id to use "SetPosition"
+        insert("mpris:trackid", Box::new(Path::from("/")));
+        if let Some(length) = self.length {
+            insert("mpris:length", Box::new(length));
+        }
+        if let Some(cover_url) = &self.cover_url {
+            insert("mpris:artUrl", Box::new(cover_url.clone()));
+        }
+        if let Some(title) = &self.title {
+            insert("xesam:title", Box::new(title.clone()));
+        }
+        if let Some(artist) = &self.artist {
+            insert("xesam:artist", Box::new(vec![artist.clone()]));
+        }
+        if let Some(album) = &self.album {
+            insert("xesam:album", Box::new(album.clone()));
+        }
+        if let Some(user_rating) = self.user_rating {
+            insert("xesam:userRating", Box::new(user_rating));
+        }
+
+        dict
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    metadata: Metadata,
+    playback: MediaPlayback,
+    volume: f64,
+}
+
+impl State {
+    fn playback_status(&self) -> &'static str {
+        match self.playback {
+            MediaPlayback::Playing { .. } => "Playing",
+            MediaPlayback::Paused { .. } => "Paused",
+            MediaPlayback::Stopped => "Stopped",
+        }
+    }
+
+    /// Position in microseconds
+    fn position(&self) -> i64 {
+        match self.playback {
+            MediaPlayback::Playing {
+                progress: Some(progress),
+            }
+            | MediaPlayback::Paused {
+                progress: Some(progress),
+            } => i64::try_from(progress.0.as_micros()).unwrap_or(i64::MAX),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ServiceEvent {
+    Metadata(Metadata),
+    UserRating(Option<f64>),
+    Playback(MediaPlayback),
+    Volume(f64),
+    Quit,
+}
+
+struct Service {
+    tx: mpsc::Sender<ServiceEvent>,
+    handle: JoinHandle<Result<()>>,
+}
+
+/// Serves `org.mpris.MediaPlayer2` on the session bus from its own thread, once attached
+pub struct MediaControls {
+    dbus_name: String,
+    display_name: String,
+    service: Option<Service>,
+}
+
+impl MediaControls {
+    /// Create the controls, without connecting to dbus yet
+    ///
+    /// # Errors
+    ///
+    /// never, but kept to be used like [`souvlaki::MediaControls::new`]
+    #[allow(clippy::unnecessary_wraps, clippy::needless_pass_by_value)]
+    pub fn new(config: PlatformConfig<'_>) -> Result<Self> {
+        Ok(Self {
+            dbus_name: config.dbus_name.to_string(),
+            display_name: config.display_name.to_string(),
+            service: None,
+        })
+    }
+
+    /// Register the service on the session bus, calling `handler` for every request of a client
+    ///
+    /// # Errors
+    ///
+    /// if the session bus is unavailable or the name is taken
+    pub fn attach<F>(&mut self, handler: F) -> Result<()>
+    where
+        F: Fn(MediaControlEvent) + Send + 'static,
+    {
+        self.detach()?;
+
+        let conn = Connection::new_session().context("Error connecting to the dbus session bus")?;
+        let name = format!("org.mpris.MediaPlayer2.{}", self.dbus_name);
+        conn.request_name(&name, false, true, false)
+            .with_context(|| format!("Error requesting dbus name {name}"))?;
+
+        let (tx, rx) = mpsc::channel();
+        let display_name = self.display_name.clone();
+        let handler: EventHandler = Arc::new(Mutex::new(Box::new(handler)));
+        let handle = thread::Builder::new()
+            .name("mpris".to_string())
+            .spawn(move || run_service(&conn, display_name, &handler, &rx))?;
+        self.service = Some(Service { tx, handle });
+
+        Ok(())
+    }
+
+    /// Stop serving and release the name on the session bus
+    ///
+    /// # Errors
+    ///
+    /// if the service stopped with an error
+    pub fn detach(&mut self) -> Result<()> {
+        let Some(service) = self.service.take() else {
+            return Ok(());
+        };
+        // the thread might have already stopped, which is reported by joining
+        service.tx.send(ServiceEvent::Quit).ok();
+        service
+            .handle
+            .join()
+            .map_err(|_| anyhow!("mpris thread panicked"))?
+    }
+
+    /// # Errors
+    ///
+    /// if the service is not running
+    pub fn set_playback(&mut self, playback: MediaPlayback) -> Result<()> {
+        self.send(ServiceEvent::Playback(playback))
+    }
+
+    /// Set the metadata of a new track, without a rating until [`MediaControls::set_user_rating`]
+    ///
+    /// # Errors
+    ///
+    /// if the service is not running
+    pub fn set_metadata(&mut self, metadata: MediaMetadata<'_>) -> Result<()> {
+        self.send(ServiceEvent::Metadata(metadata.into()))
+    }
+
+    /// Set the rating of the current track from `0.0` to `1.0`, [`None`] if unrated
+    ///
+    /// # Errors
+    ///
+    /// if the service is not running
+    pub fn set_user_rating(&mut self, user_rating: Option<f64>) -> Result<()> {
+        self.send(ServiceEvent::UserRating(user_rating))
+    }
+
+    /// Set the volume, `1.0` being full volume
+    ///
+    /// # Errors
+    ///
+    /// if the service is not running
+    pub fn set_volume(&mut self, volume: f64) -> Result<()> {
+        self.send(ServiceEvent::Volume(volume))
+    }
+
+    fn send(&self, event: ServiceEvent) -> Result<()> {
+        self.service
+            .as_ref()
+            .context("mpris service is not attached")?
+            .tx
+            .send(event)
+            .map_err(|_| anyhow!("mpris service stopped"))
+    }
+}
+
+impl Drop for MediaControls {
+    fn drop(&mut self) {
+        if let Err(e) = self.detach() {
+            error!("error stopping mpris: {e:#}");
+        }
+    }
+}
+
+fn run_service(
+    conn: &Connection,
+    display_name: String,
+    handler: &EventHandler,
+    rx: &Receiver<ServiceEvent>,
+) -> Result<()> {
+    let state = Arc::new(Mutex::new(State {
+        metadata: Metadata::default(),
+        playback: MediaPlayback::Stopped,
+        volume: 1.0,
+    }));
+
+    let mut cr = register_interfaces(&state, handler, display_name);
+    conn.start_receive(
+        MatchRule::new_method_call(),
+        Box::new(move |msg, conn| {
+            // errors are already answered to the caller
+            cr.handle_message(msg, conn).ok();
+            true
+        }),
+    );
+
+    loop {
+        match rx.recv_timeout(PROCESS_TIMEOUT) {
+            Ok(ServiceEvent::Quit) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            Ok(event) => {
+                if let Some((property, value)) = apply_event(&mut state.lock(), event) {
+                    properties_changed(conn, property, value);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        conn.process(Duration::ZERO)?;
+    }
+}
+
+/// Apply `event` to `state`, returning the changed property if clients should be notified
+fn apply_event(state: &mut State, event: ServiceEvent) -> Option<(&'static str, Box<dyn RefArg>)> {
+    match event {
+        ServiceEvent::Metadata(metadata) => {
+            state.metadata = metadata;
+            Some(("Metadata", Box::new(state.metadata.to_dict())))
+        }
+        ServiceEvent::UserRating(user_rating) => {
+            state.metadata.user_rating = user_rating;
+            Some(("Metadata", Box::new(state.metadata.to_dict())))
+        }
+        ServiceEvent::Playback(playback) => {
+            // the progress is updated every tick, but clients only need to know about status changes
+            let old_status = state.playback_status();
+            state.playback = playback;
+            let status = state.playback_status();
+            (status != old_status).then(|| ("PlaybackStatus", Box::new(status.to_string()) as _))
+        }
+        ServiceEvent::Volume(volume) => {
+            #[allow(clippy::float_cmp)]
+            let changed = state.volume != volume;
+            state.volume = volume;
+            changed.then(|| ("Volume", Box::new(volume) as _))
+        }
+        ServiceEvent::Quit => None,
+    }
+}
+
+fn properties_changed(conn: &Connection, property: &str, value: Box<dyn RefArg>) {
+    let mut changed_properties = PropMap::new();
+    changed_properties.insert(property.to_string(), Variant(value));
+    let signal = PropertiesPropertiesChanged {
+        interface_name: PLAYER_INTERFACE.to_string(),
+        changed_properties,
+        invalidated_properties: Vec::new(),
+    };
+    // ignore error if sending failed
+    conn.send(signal.to_emit_message(&Path::from(MPRIS_PATH)))
+        .ok();
+}
+
+fn register_interfaces(
+    state: &Arc<Mutex<State>>,
+    handler: &EventHandler,
+    display_name: String,
+) -> Crossroads {
+    let mut cr = Crossroads::new();
+
+    let app_interface = cr.register("org.mpris.MediaPlayer2", {
+        let handler = handler.clone();
+        move |b: &mut IfaceBuilder<()>| {
+            register_method(b, &handler, "Raise", MediaControlEvent::Raise);
+            register_method(b, &handler, "Quit", MediaControlEvent::Quit);
+
+            b.property("Identity")
+                .get(move |_, ()| Ok(display_name.clone()));
+            b.property("CanQuit").get(|_, ()| Ok(true));
+            // there is no window to raise
+            b.property("CanRaise").get(|_, ()| Ok(false));
+            b.property("HasTracklist").get(|_, ()| Ok(false));
+            b.property("SupportedUriSchemes")
+                .get(|_, ()| Ok(Vec::<String>::new()));
+            b.property("SupportedMimeTypes")
+                .get(|_, ()| Ok(Vec::<String>::new()));
+        }
+    });
+
+    let player_interface = cr.register(PLAYER_INTERFACE, |b: &mut IfaceBuilder<()>| {
+        register_player_interface(b, state, handler);
+    });
+
+    cr.insert(MPRIS_PATH, &[app_interface, player_interface], ());
+
+    cr
+}
+
+fn register_player_interface(
+    b: &mut IfaceBuilder<()>,
+    state: &Arc<Mutex<State>>,
+    handler: &EventHandler,
+) {
+    register_method(b, handler, "Next", MediaControlEvent::Next);
+    register_method(b, handler, "Previous", MediaControlEvent::Previous);
+    register_method(b, handler, "Pause", MediaControlEvent::Pause);
+    register_method(b, handler, "PlayPause", MediaControlEvent::Toggle);
+    register_method(b, handler, "Stop", MediaControlEvent::Stop);
+    register_method(b, handler, "Play", MediaControlEvent::Play);
+
+    b.method("Seek", ("Offset",), (), {
+        let handler = handler.clone();
+        move |_, (), (offset,): (i64,)| {
+            let direction = if offset > 0 {
+                SeekDirection::Forward
+            } else {
+                SeekDirection::Backward
+            };
+            (handler.lock())(MediaControlEvent::SeekBy(
+                direction,
+                Duration::from_micros(offset.unsigned_abs()),
+            ));
+            Ok(())
+        }
+    });
+    b.method("SetPosition", ("TrackId", "Position"), (), {
+        let state = state.clone();
+        let handler = handler.clone();
+        move |_, (), (_track_id, position): (Path<'static>, i64)| {
+            // positions outside of the track are ignored, as the specification requires
+            let length = state.lock().metadata.length;
+            if length.is_some_and(|length| position > length) {
+                return Ok(());
+            }
+            if let Ok(position) = u64::try_from(position) {
+                (handler.lock())(MediaControlEvent::SetPosition(MediaPosition(
+                    Duration::from_micros(position),
+                )));
+            }
+            Ok(())
+        }
+    });
+    b.method("OpenUri", ("Uri",), (), {
+        let handler = handler.clone();
+        move |_, (), (uri,): (String,)| {
+            (handler.lock())(MediaControlEvent::OpenUri(uri));
+            Ok(())
+        }
+    });
+
+    b.property("PlaybackStatus").get({
+        let state = state.clone();
+        move |_, ()| Ok(state.lock().playback_status().to_string())
+    });
+    b.property("Metadata").get({
+        let state = state.clone();
+        move |_, ()| Ok(state.lock().metadata.to_dict())
+    });
+    b.property("Volume")
+        .get({
+            let state = state.clone();
+            move |_, ()| Ok(state.lock().volume)
+        })
+        .set({
+            let handler = handler.clone();
+            move |_, (), volume: f64| {
+                (handler.lock())(MediaControlEvent::SetVolume(volume));
+                Ok(Some(volume))
+            }
+        });
+    b.property("Position")
+        .get({
+            let state = state.clone();
+            move |_, ()| Ok(state.lock().position())
+        })
+        .emits_changed_false();
+    b.property("Rate").get(|_, ()| Ok(1.0));
+    b.property("MinimumRate").get(|_, ()| Ok(1.0));
+    b.property("MaximumRate").get(|_, ()| Ok(1.0));
+    for name in [
+        "CanGoNext",
+        "CanGoPrevious",
+        "CanPlay",
+        "CanPause",
+        "CanSeek",
+        "CanControl",
+    ] {
+        b.property(name).get(|_, ()| Ok(true));
+    }
+}
+
+fn register_method(
+    b: &mut IfaceBuilder<()>,
+    handler: &EventHandler,
+    name: &'static str,
+    event: MediaControlEvent,
+) {
+    let handler = handler.clone();
+    b.method(name, (), (), move |_, (), (): ()| {
+        (handler.lock())(event.clone());
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_metadata_user_rating() {
+        let metadata = Metadata::from(MediaMetadata {
+            title: Some("title"),
+            duration: Some(Duration::from_secs(2)),
+            ..Default::default()
+        });
+        let dict = metadata.to_dict();
+        assert_eq!(
+            dict.get("mpris:length").and_then(RefArg::as_i64),
+            Some(2_000_000)
+        );
+        assert!(!dict.contains_key("xesam:userRating"));
+
+        let mut state = State {
+            metadata,
+            playback: MediaPlayback::Stopped,
+            volume: 1.0,
+        };
+        let (property, value) =
+            apply_event(&mut state, ServiceEvent::UserRating(Some(0.6))).unwrap();
+        assert_eq!(property, "Metadata");
+        let rating = value.as_iter().and_then(|mut iter| {
+            // dicts iterate as key, value
+            while let Some(key) = iter.next() {
+                let value = iter.next()?;
+                if key.as_str() == Some("xesam:userRating") {
+                    return value.as_f64();
+                }
+            }
+            None
+        });
+        assert_eq!(rating, Some(0.6));
+
+        // a new track starts unrated
+        apply_event(&mut state, ServiceEvent::Metadata(Metadata::default()));
+        assert_eq!(state.metadata.user_rating, None);
+    }
+
+    #[test]
+    fn test_only_status_changes_are_signaled() {
+        let mut state = State {
+            metadata: Metadata::default(),
+            playback: MediaPlayback::Stopped,
+            volume: 1.0,
+        };
+        let playing = |secs| MediaPlayback::Playing {
+            progress: Some(MediaPosition(Duration::from_secs(secs))),
+        };
+
+        assert!(apply_event(&mut state, ServiceEvent::Playback(playing(1))).is_some());
+        assert!(apply_event(&mut state, ServiceEvent::Playback(playing(2))).is_none());
+        assert_eq!(state.position(), 2_000_000);
+        assert!(apply_event(&mut state, ServiceEvent::Volume(1.0)).is_none());
+        assert!(apply_event(&mut state, ServiceEvent::Volume(0.5)).is_some());
+    }
+}
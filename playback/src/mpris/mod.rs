@@ -1,9 +1,16 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+mod controls;
+
 use base64::Engine;
-use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, PlatformConfig};
+#[cfg(all(unix, not(target_os = "macos")))]
+use controls::MediaControls;
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+use souvlaki::MediaControls;
+use souvlaki::{MediaControlEvent, MediaMetadata, MediaPlayback, PlatformConfig};
 use std::sync::mpsc::{self, Receiver};
 use termusiclib::track::Track;
 
-use crate::{GeneralPlayer, PlayerCmd, PlayerTimeUnit, PlayerTrait, Status};
+use crate::{EnqueueMode, GeneralPlayer, PlayerCmd, PlayerTimeUnit, PlayerTrait, Status};
 
 pub struct Mpris {
     controls: MediaControls,
@@ -57,7 +64,8 @@ impl Mpris {
 }
 
 impl Mpris {
-    pub fn add_and_play(&mut self, track: &Track) {
+    /// Export `track` with its star `rating` as the one now playing, `0` if unrated
+    pub fn add_and_play(&mut self, track: &Track, rating: u8) {
         // This is to fix a bug that the first track is not updated
         std::thread::sleep(std::time::Duration::from_millis(100));
         self.controls
@@ -90,6 +98,20 @@ impl Mpris {
                 duration: Some(track.duration()),
             })
             .ok();
+        self.set_rating(rating);
+    }
+
+    /// Export the star `rating` of the current track as `xesam:userRating`, `0` if unrated
+    pub fn set_rating(&mut self, rating: u8) {
+        // souvlaki has no metadata field for it, only our dbus implementation exports it
+        #[cfg(all(unix, not(target_os = "macos")))]
+        self.controls
+            .set_user_rating(
+                (rating > 0).then(|| f64::from(rating) / f64::from(termusiclib::track::MAX_RATING)),
+            )
+            .ok();
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let _ = rating;
     }
 
     pub fn pause(&mut self) {
@@ -153,14 +175,15 @@ impl GeneralPlayer {
             MediaControlEvent::SetPosition(position) => {
                 self.seek_to(position.0);
             }
-            MediaControlEvent::OpenUri(_uri) => {
-                // let wait = async {
-                //     self.player.add_and_play(&uri).await;
-                // };
-                // let rt = tokio::runtime::Runtime::new().expect("failed to create runtime");
-                // rt.block_on(wait);
-                // TODO: handle "OpenUri"
-                info!("Unimplemented Event: OpenUri");
+            MediaControlEvent::OpenUri(uri) => {
+                // handled in the server loop, so that clients get notified about the playlist change
+                // ignore error if sending failed
+                self.cmd_tx
+                    .send(PlayerCmd::PlayUri {
+                        uri,
+                        mode: EnqueueMode::Next,
+                    })
+                    .ok();
             }
             MediaControlEvent::SeekBy(direction, duration) => {
                 #[allow(clippy::cast_possible_wrap)]
@@ -183,15 +206,16 @@ impl GeneralPlayer {
             }
             MediaControlEvent::SetVolume(volume) => {
                 debug!("got souvlaki SetVolume: {:#}", volume);
-                // volume can be anything above 0; 1.0 means a sensible max; termusic currently does not support more than 100 volume
+                // volume can be anything above 0; 1.0 means a sensible max; termusic supports up to "player_volume_max"
+                let max = f64::from(self.config.volume_max()) / 100.0;
                 // warn users trying to set higher than max via logging
-                if volume > 1.0 {
-                    error!("SetVolume above 1.0 will be clamped to 1.0!");
+                if volume > max {
+                    error!("SetVolume above {max} will be clamped to {max}!");
                 }
                 // convert a 0.0 to 1.0 range to 0 to 100, because that is what termusic uses for volume
                 // default float to int casting will truncate values to the decimal point
                 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-                let uvol = (volume.clamp(0.0, 1.0) * 100.0) as u16;
+                let uvol = (volume.clamp(0.0, max) * 100.0) as u16;
                 self.set_volume(uvol);
             }
             MediaControlEvent::Quit => {
@@ -23,7 +23,7 @@
  */
 mod libmpv;
 
-use super::{PlayerCmd, PlayerProgress, PlayerTrait};
+use super::{PlayerCmd, PlayerProgress, PlayerTrait, SPEED_MAX, SPEED_MIN, SPEED_STEP};
 use anyhow::Result;
 use async_trait::async_trait;
 use libmpv::Mpv;
@@ -36,12 +36,16 @@ use std::cmp;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use std::time::Duration;
-use termusiclib::config::Settings;
+use termusiclib::config::{
+    ChannelMixSettings, Crossfade, ReplayGainMode, ReplayGainSettings, Settings, VolumeCurve,
+};
 use termusiclib::track::Track;
 
 pub struct MpvBackend {
     // player: Mpv,
     volume: u16,
+    /// highest volume in percent
+    volume_max: u16,
     speed: i32,
     pub gapless: bool,
     command_tx: Sender<PlayerInternalCmd>,
@@ -53,17 +57,20 @@ pub struct MpvBackend {
 }
 
 enum PlayerInternalCmd {
+    ChannelMix(ChannelMixSettings),
     Eos,
     Pause,
     // GetProgress,
     Play(String),
     QueueNext(String),
+    ReplayGain(ReplayGainSettings),
     Resume,
     Seek(i64),
     SeekAbsolute(Duration),
     Speed(i32),
     Stop,
     Volume(u16),
+    VolumeCurve(VolumeCurve),
 }
 
 impl MpvBackend {
@@ -72,7 +79,8 @@ impl MpvBackend {
         let (command_tx, command_rx): (Sender<PlayerInternalCmd>, Receiver<PlayerInternalCmd>) =
             mpsc::channel();
         let volume = config.player_volume;
-        let speed = config.player_speed;
+        let mut volume_curve = config.player_volume_curve;
+        let speed = config.player_speed_percent;
         let gapless = config.player_gapless;
         let position = Arc::new(Mutex::new(Duration::default()));
         let duration = Arc::new(Mutex::new(Duration::default()));
@@ -89,12 +97,20 @@ impl MpvBackend {
         mpv.set_property("ao", "pulse")
             .expect("Couldn't set ao=pulse in libmpv");
 
-        mpv.set_property("volume", i64::from(volume))
-            .expect("Error setting volume");
-        mpv.set_property("speed", f64::from(speed) / 10.0).ok();
+        let mut filters = AudioFilters {
+            limited: false,
+            channel_mix: config.player_channel_mix,
+        };
+        filters.apply(&mpv);
+        set_volume_properties(&mpv, volume, volume_curve, &mut filters);
+        mpv.set_property("speed", f64::from(speed) / 100.0).ok();
         let gapless_setting = if gapless { "yes" } else { "no" };
         mpv.set_property("gapless-audio", gapless_setting)
             .expect("gapless setting failed");
+        set_replay_gain_properties(
+            &mpv,
+            config.player_replaygain.resolve(config.player_loop_mode),
+        );
 
         let cmd_tx_inside = command_tx.clone();
         // let mut time_pos: i64 = 0;
@@ -194,9 +210,21 @@ impl MpvBackend {
                                     .ok();
                                 // .expect("Error loading file");
                             }
+                            PlayerInternalCmd::ReplayGain(settings) => {
+                                set_replay_gain_properties(&mpv, settings);
+                            }
                             PlayerInternalCmd::Volume(volume) => {
-                                mpv.set_property("volume", i64::from(volume)).ok();
-                                // .expect("Error increase volume");
+                                set_volume_properties(&mpv, volume, volume_curve, &mut filters);
+                            }
+                            PlayerInternalCmd::ChannelMix(channel_mix) => {
+                                if channel_mix != filters.channel_mix {
+                                    filters.channel_mix = channel_mix;
+                                    filters.apply(&mpv);
+                                }
+                            }
+                            PlayerInternalCmd::VolumeCurve(curve) => {
+                                // applied by the volume sent afterwards
+                                volume_curve = curve;
                             }
                             PlayerInternalCmd::Pause => {
                                 mpv.set_property("pause", true).ok();
@@ -205,7 +233,7 @@ impl MpvBackend {
                                 mpv.set_property("pause", false).ok();
                             }
                             PlayerInternalCmd::Speed(speed) => {
-                                mpv.set_property("speed", f64::from(speed) / 10.0).ok();
+                                mpv.set_property("speed", f64::from(speed) / 100.0).ok();
                             }
                             PlayerInternalCmd::Stop => {
                                 mpv.command("stop", &[""]).ok();
@@ -254,6 +282,7 @@ impl MpvBackend {
 
         Self {
             volume,
+            volume_max: config.volume_max(),
             speed,
             gapless,
             command_tx,
@@ -282,6 +311,71 @@ impl MpvBackend {
     }
 }
 
+/// Audio filters of mpv, set together as they all are in the same property
+struct AudioFilters {
+    /// whether the limiter for amplified sound is active
+    limited: bool,
+    channel_mix: ChannelMixSettings,
+}
+
+impl AudioFilters {
+    /// Set the filters on `mpv`, which reinitializes the audio output, so only call it on changes
+    fn apply(&self, mpv: &Mpv) {
+        let mut filters = Vec::new();
+        if !self.channel_mix.is_neutral() {
+            // the sound is converted to stereo first, so both channels always exist
+            let (left, right) = self.channel_mix.gains();
+            let pan = if self.channel_mix.mono {
+                let (left, right) = (left / 2.0, right / 2.0);
+                format!("c0={left:.3}*c0+{left:.3}*c1|c1={right:.3}*c0+{right:.3}*c1")
+            } else {
+                format!("c0={left:.3}*c0|c1={right:.3}*c1")
+            };
+            filters.push(format!(
+                "@channels:lavfi=[aformat=channel_layouts=stereo,pan=stereo|{pan}]"
+            ));
+        }
+        if self.limited {
+            filters.push("@limiter:lavfi=[alimiter=level=disabled]".to_string());
+        }
+        mpv.set_property("af", filters.join(",")).ok();
+    }
+}
+
+/// Let mpv play at `volume` in percent as mapped by `curve`
+///
+/// mpv cubes its own volume, so the factor is converted back. Above a factor of `1.0` a limiter
+/// keeps the amplified sound from clipping.
+fn set_volume_properties(mpv: &Mpv, volume: u16, curve: VolumeCurve, filters: &mut AudioFilters) {
+    let factor = f64::from(curve.factor(volume));
+    let mpv_volume = 100.0 * factor.cbrt();
+    mpv.set_property("volume-max", mpv_volume.max(100.0)).ok();
+    mpv.set_property("volume", mpv_volume).ok();
+
+    if (factor > 1.0) != filters.limited {
+        filters.limited = factor > 1.0;
+        filters.apply(mpv);
+    }
+}
+
+/// Let mpv apply the `ReplayGain` tags according to `settings`
+fn set_replay_gain_properties(mpv: &Mpv, settings: ReplayGainSettings) {
+    let mode = match settings.mode {
+        ReplayGainMode::Off => "no",
+        ReplayGainMode::Track => "track",
+        ReplayGainMode::Album | ReplayGainMode::Auto => "album",
+    };
+    mpv.set_property("replaygain", mode).ok();
+    mpv.set_property("replaygain-preamp", f64::from(settings.preamp))
+        .ok();
+    let clip = if settings.prevent_clipping {
+        "yes"
+    } else {
+        "no"
+    };
+    mpv.set_property("replaygain-clip", clip).ok();
+}
+
 /// Format a duration in "SS.mm" format
 ///
 /// Note that mpv supports "HH:MM:SS.mmmm" format, but only the second and millisecond part is used
@@ -311,12 +405,27 @@ impl PlayerTrait for MpvBackend {
     }
 
     fn set_volume(&mut self, volume: u16) {
-        self.volume = volume.min(100);
+        self.volume = volume.min(self.volume_max);
         self.command_tx
             .send(PlayerInternalCmd::Volume(self.volume))
             .ok();
     }
 
+    fn set_volume_curve(&mut self, curve: VolumeCurve, max: u16) {
+        self.volume_max = max;
+        self.command_tx
+            .send(PlayerInternalCmd::VolumeCurve(curve))
+            .ok();
+        // apply the curve and lower the volume if it is above the new maximum
+        self.set_volume(self.volume);
+    }
+
+    fn set_channel_mix(&mut self, settings: ChannelMixSettings) {
+        self.command_tx
+            .send(PlayerInternalCmd::ChannelMix(settings))
+            .ok();
+    }
+
     fn pause(&mut self) {
         self.command_tx.send(PlayerInternalCmd::Pause).ok();
     }
@@ -352,19 +461,11 @@ impl PlayerTrait for MpvBackend {
     }
 
     fn speed_up(&mut self) {
-        let mut speed = self.speed + 1;
-        if speed > 30 {
-            speed = 30;
-        }
-        self.set_speed(speed);
+        self.set_speed((self.speed + SPEED_STEP).min(SPEED_MAX));
     }
 
     fn speed_down(&mut self) {
-        let mut speed = self.speed - 1;
-        if speed < 1 {
-            speed = 1;
-        }
-        self.set_speed(speed);
+        self.set_speed((self.speed - SPEED_STEP).max(SPEED_MIN));
     }
     fn stop(&mut self) {
         self.command_tx.send(PlayerInternalCmd::Stop).ok();
@@ -389,7 +490,16 @@ impl PlayerTrait for MpvBackend {
         self.skip_one();
     }
 
-    fn enqueue_next(&mut self, file: &str) {
-        self.enqueue_next(file);
+    /// mpv cannot crossfade, the track is always played after the current one
+    fn enqueue_next(&mut self, track: &Track, _crossfade: Option<Crossfade>) {
+        if let Some(file) = track.file() {
+            self.enqueue_next(file);
+        }
+    }
+
+    fn set_replay_gain(&mut self, settings: ReplayGainSettings) {
+        self.command_tx
+            .send(PlayerInternalCmd::ReplayGain(settings))
+            .ok();
     }
 }
@@ -4,15 +4,16 @@ use rand::seq::SliceRandom;
 use rand::thread_rng;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use termusiclib::podcast::{db::Database as DBPod, Episode};
+use termusiclib::podcast::{db::Database as DBPod, Episode, Podcast};
 use termusiclib::track::MediaType;
 use termusiclib::{
     config::{Loop, Settings},
     track::Track,
-    utils::{filetype_supported, get_app_config_path, get_parent_folder},
+    utils::{filetype_supported, get_app_config_path, get_parent_folder, write_atomic},
 };
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
@@ -132,37 +133,77 @@ impl Playlist {
             }
         }
 
-        let mut playlist_items = Vec::new();
-        let db_path = get_app_config_path()?;
-        let db_podcast = DBPod::connect(&db_path)?;
-        let podcasts = db_podcast
-            .get_podcasts()
-            .with_context(|| "failed to get podcasts from db.")?;
-        for line in lines {
-            if let Ok(track) = Track::read_from_path(&line, false) {
-                playlist_items.push(track);
+        let podcasts = load_podcasts()?;
+        let playlist_items = lines
+            .filter_map(|line| track_from_uri(&line, &podcasts))
+            .collect();
+
+        Ok((current_track_index, playlist_items))
+    }
+
+    /// Resolve local file paths and urls to [`Track`]s, like [`load`](Self::load) does for the playlist file
+    ///
+    /// Local files with a unsupported filetype are skipped.
+    ///
+    /// # Errors
+    /// errors could happen when reading the podcast database
+    pub fn tracks_from_uris(uris: &[String]) -> Result<Vec<Track>> {
+        let podcasts = if uris.iter().any(|v| v.starts_with("http")) {
+            load_podcasts()?
+        } else {
+            Vec::new()
+        };
+
+        let mut tracks = Vec::with_capacity(uris.len());
+        for uri in uris {
+            if !uri.starts_with("http") && !filetype_supported(uri) {
+                error!("unsupported filetype: {:#?}", uri);
                 continue;
-            };
-            if line.starts_with("http") {
-                let mut is_podcast = false;
-                'outer: for pod in &podcasts {
-                    for ep in &pod.episodes {
-                        if ep.url == line.as_str() {
-                            is_podcast = true;
-                            let track = Track::from_episode(ep);
-                            playlist_items.push(track);
-                            break 'outer;
-                        }
-                    }
-                }
-                if !is_podcast {
-                    let track = Track::new_radio(&line);
-                    playlist_items.push(track);
-                }
+            }
+            match track_from_uri(uri, &podcasts) {
+                Some(track) => tracks.push(track),
+                None => error!("could not read track: {:#?}", uri),
             }
         }
 
-        Ok((current_track_index, playlist_items))
+        Ok(tracks)
+    }
+
+    /// Replace all tracks with the given `uris`, re-using already loaded [`Track`]s where possible
+    ///
+    /// Used by clients to mirror the playlist owned by the server.
+    ///
+    /// # Errors
+    /// errors could happen when reading the podcast database
+    pub fn replace_tracks_from_uris(
+        &mut self,
+        current_track_index: usize,
+        uris: &[String],
+    ) -> Result<()> {
+        let mut known: HashMap<String, Track> = self
+            .tracks
+            .drain(..)
+            .filter_map(|track| {
+                let file = track.file()?.to_string();
+                Some((file, track))
+            })
+            .collect();
+
+        let unknown: Vec<String> = uris
+            .iter()
+            .filter(|v| !known.contains_key(*v))
+            .cloned()
+            .collect();
+        for track in Self::tracks_from_uris(&unknown)? {
+            if let Some(file) = track.file() {
+                known.insert(file.to_string(), track);
+            }
+        }
+
+        self.tracks = uris.iter().filter_map(|v| known.get(v).cloned()).collect();
+        self.current_track_index = current_track_index.min(self.len().saturating_sub(1));
+
+        Ok(())
     }
 
     /// # Errors
@@ -183,8 +224,6 @@ impl Playlist {
     pub fn save(&mut self) -> Result<()> {
         let path = get_playlist_path()?;
 
-        let file = File::create(path.as_path())?;
-        let mut writer = BufWriter::new(file);
         let mut bytes = Vec::new();
         bytes.extend(format!("{}", self.current_track_index).as_bytes());
         bytes.extend("\n".as_bytes());
@@ -195,8 +234,7 @@ impl Playlist {
             }
         }
 
-        writer.write_all(&bytes)?;
-        writer.flush()?;
+        write_atomic(&path, &bytes)?;
 
         Ok(())
     }
@@ -260,6 +298,61 @@ impl Playlist {
         self.tracks.is_empty()
     }
 
+    /// Insert `tracks` at `at_index`, or append them if `at_index` is `None` or out of bounds
+    pub fn add_tracks(&mut self, tracks: Vec<Track>, at_index: Option<usize>) {
+        let amount = tracks.len();
+        match at_index {
+            Some(index) if index < self.len() => {
+                self.tracks.splice(index..index, tracks);
+                if index <= self.current_track_index {
+                    self.current_track_index += amount;
+                }
+            }
+            _ => self.tracks.extend(tracks),
+        }
+    }
+
+    /// Remove `amount` tracks, starting at (and including) `at_index`
+    ///
+    /// Out of bounds tracks are ignored.
+    pub fn remove_range(&mut self, at_index: usize, amount: usize) {
+        let end = at_index.saturating_add(amount).min(self.len());
+        for index in (at_index..end).rev() {
+            self.remove(index);
+        }
+    }
+
+    /// Move the track at `from` to be at `to`, shifting all tracks in-between
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.len() || to >= self.len() || from == to {
+            return;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        // handle index
+        if from == self.current_track_index {
+            self.current_track_index = to;
+        } else if from < self.current_track_index && to >= self.current_track_index {
+            self.current_track_index -= 1;
+        } else if from > self.current_track_index && to <= self.current_track_index {
+            self.current_track_index += 1;
+        }
+    }
+
+    /// Swap the tracks at `index_a` and `index_b`
+    pub fn swap(&mut self, index_a: usize, index_b: usize) {
+        if index_a >= self.len() || index_b >= self.len() {
+            return;
+        }
+        self.tracks.swap(index_a, index_b);
+        // handle index
+        if index_a == self.current_track_index {
+            self.current_track_index = index_b;
+        } else if index_b == self.current_track_index {
+            self.current_track_index = index_a;
+        }
+    }
+
     pub fn swap_down(&mut self, index: usize) {
         if index < self.len() - 1 {
             let track = self.tracks.remove(index);
@@ -336,6 +429,15 @@ impl Playlist {
         self.status
     }
 
+    #[must_use]
+    pub fn loop_mode(&self) -> Loop {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: Loop) {
+        self.loop_mode = loop_mode;
+    }
+
     /// Cycle through the loop modes and return the new mode
     ///
     /// order:
@@ -523,9 +625,131 @@ impl Playlist {
 
 const PLAYLIST_SAVE_FILENAME: &str = "playlist.log";
 
+fn load_podcasts() -> Result<Vec<Podcast>> {
+    let db_path = get_app_config_path()?;
+    let db_podcast = DBPod::connect(&db_path)?;
+    db_podcast
+        .get_podcasts()
+        .with_context(|| "failed to get podcasts from db.")
+}
+
+/// Resolve a single playlist entry to a [`Track`]
+///
+/// urls are matched against known podcast episodes first, otherwise they are treated as live radio
+fn track_from_uri(uri: &str, podcasts: &[Podcast]) -> Option<Track> {
+    if let Ok(track) = Track::read_from_path(uri, false) {
+        return Some(track);
+    }
+    if !uri.starts_with("http") {
+        return None;
+    }
+    for pod in podcasts {
+        for ep in &pod.episodes {
+            if ep.url == uri {
+                return Some(Track::from_episode(ep));
+            }
+        }
+    }
+    Some(Track::new_radio(uri))
+}
+
 fn get_playlist_path() -> Result<PathBuf> {
     let mut path = get_app_config_path()?;
     path.push(PLAYLIST_SAVE_FILENAME);
 
     Ok(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Playlist of the radio stations `0` to `len - 1`, playing `current`
+    fn playlist(len: usize, current: usize) -> Playlist {
+        let mut playlist = Playlist::default();
+        let tracks = (0..len).map(|i| Track::new_radio(&i.to_string())).collect();
+        playlist.add_tracks(tracks, None);
+        playlist.set_current_track_index(current);
+        playlist
+    }
+
+    fn files(playlist: &Playlist) -> Vec<&str> {
+        playlist.tracks().iter().filter_map(Track::file).collect()
+    }
+
+    #[test]
+    fn should_keep_current_track_when_adding() {
+        let new = || vec![Track::new_radio("new")];
+
+        let mut before = playlist(3, 1);
+        before.add_tracks(new(), Some(0));
+        assert_eq!(files(&before), vec!["new", "0", "1", "2"]);
+        assert_eq!(before.get_current_track_index(), 2);
+
+        let mut at = playlist(3, 1);
+        at.add_tracks(new(), Some(1));
+        assert_eq!(files(&at), vec!["0", "new", "1", "2"]);
+        assert_eq!(at.get_current_track_index(), 2);
+
+        let mut after = playlist(3, 1);
+        after.add_tracks(new(), Some(2));
+        assert_eq!(files(&after), vec!["0", "1", "new", "2"]);
+        assert_eq!(after.get_current_track_index(), 1);
+
+        let mut out_of_range = playlist(3, 1);
+        out_of_range.add_tracks(new(), Some(9));
+        assert_eq!(files(&out_of_range), vec!["0", "1", "2", "new"]);
+        assert_eq!(out_of_range.get_current_track_index(), 1);
+    }
+
+    #[test]
+    fn should_move_to_previous_track_when_removing_current() {
+        let mut playlist = playlist(5, 2);
+        playlist.remove_range(1, 2);
+        assert_eq!(files(&playlist), vec!["0", "3", "4"]);
+        assert_eq!(playlist.get_current_track_index(), 0);
+
+        playlist.remove_range(2, 9);
+        assert_eq!(files(&playlist), vec!["0", "3"]);
+        playlist.remove_range(5, 1);
+        assert_eq!(files(&playlist), vec!["0", "3"]);
+        assert_eq!(playlist.get_current_track_index(), 0);
+    }
+
+    #[test]
+    fn should_follow_current_track_when_moving() {
+        let mut playlist = playlist(4, 1);
+        playlist.move_track(1, 3);
+        assert_eq!(files(&playlist), vec!["0", "2", "3", "1"]);
+        assert_eq!(playlist.get_current_track_index(), 3);
+
+        playlist.move_track(0, 3);
+        assert_eq!(files(&playlist), vec!["2", "3", "1", "0"]);
+        assert_eq!(playlist.get_current_track_index(), 2);
+
+        playlist.move_track(3, 0);
+        assert_eq!(files(&playlist), vec!["0", "2", "3", "1"]);
+        assert_eq!(playlist.get_current_track_index(), 3);
+
+        playlist.move_track(0, 4);
+        assert_eq!(files(&playlist), vec!["0", "2", "3", "1"]);
+        assert_eq!(playlist.get_current_track_index(), 3);
+    }
+
+    #[test]
+    fn should_follow_current_track_when_swapping() {
+        let mut playlist = playlist(3, 0);
+        playlist.swap(0, 2);
+        assert_eq!(files(&playlist), vec!["2", "1", "0"]);
+        assert_eq!(playlist.get_current_track_index(), 2);
+
+        playlist.swap(1, 2);
+        assert_eq!(files(&playlist), vec!["2", "0", "1"]);
+        assert_eq!(playlist.get_current_track_index(), 1);
+
+        playlist.swap(1, 3);
+        assert_eq!(files(&playlist), vec!["2", "0", "1"]);
+        assert_eq!(playlist.get_current_track_index(), 1);
+    }
+}
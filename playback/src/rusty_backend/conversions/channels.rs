@@ -35,6 +35,20 @@ where
         }
     }
 
+    /// Returns a reference to the underlying iterator.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the underlying iterator.
+    ///
+    /// Only advance it between whole output frames, the converter keeps its position within one.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
     /// Destroys this iterator and returns the underlying iterator.
     #[inline]
     pub fn into_inner(self) -> I {
@@ -0,0 +1,405 @@
+//! EBU R128 loudness analysis, to compute the `ReplayGain` of untagged tracks
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
+use termusiclib::config::Settings;
+use termusiclib::sqlite::DataBase;
+use termusiclib::track::{write_replay_gain_tags, Loudness, Track};
+
+use super::decoder::buffered_source::BufferedSource;
+use super::{Source, Symphonia};
+
+/// Gating blocks quieter than this are ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Gating blocks this far below the loudness of the remaining ones are ignored, in LU
+const RELATIVE_GATE: f64 = -10.0;
+/// Number of 100ms segments in a gating block of 400ms
+const SEGMENTS_PER_BLOCK: usize = 4;
+/// Oversampling factor used to find the true peak
+const OVERSAMPLING: usize = 4;
+/// Input samples the interpolation filter of the true peak uses
+const TAPS: usize = 12;
+
+/// Decode the whole file at `path` and measure its integrated loudness and true peak
+///
+/// # Errors
+///
+/// - if the file cannot be opened or decoded
+/// - if the file is too short or silent to have a loudness
+pub fn analyze_file(path: &Path) -> Result<Loudness> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mss = MediaSourceStream::new(
+        Box::new(BufferedSource::new_default_size(file)),
+        MediaSourceStreamOptions::default(),
+    );
+    let mut decoder = Symphonia::new(mss, false)?;
+
+    let mut meter = LoudnessMeter::new(decoder.sample_rate(), decoder.channels());
+    for sample in decoder.by_ref() {
+        meter.push(f64::from(sample) / 32768.0);
+    }
+
+    meter
+        .finish()
+        .with_context(|| format!("{} is too short or silent", path.display()))
+}
+
+/// Add `paths` to the library database and analyze the loudness of their tracks.
+///
+/// Empty `paths` means all music directories of the `config`.
+/// Tracks with `ReplayGain` tags or an earlier result are skipped, unless `force`.
+///
+/// Returns the number of analyzed tracks.
+///
+/// # Errors
+///
+/// if any database operation fails
+pub fn analyze_library(
+    config: &Settings,
+    paths: &[PathBuf],
+    write_tags: bool,
+    force: bool,
+) -> Result<usize> {
+    let paths = if paths.is_empty() {
+        config
+            .music_dir
+            .iter()
+            .map(|dir| PathBuf::from(shellexpand::tilde(dir).as_ref()))
+            .collect()
+    } else {
+        paths.to_vec()
+    };
+    let mut db = DataBase::new(config);
+    let mut analyzed = 0;
+
+    for path in &paths {
+        db.sync_database_blocking(path)?;
+        for file in db.get_files_to_analyze(path, force)? {
+            let file_path = Path::new(&file);
+            if !force
+                && Track::read_from_path(file_path, true)
+                    .is_ok_and(|track| !track.replay_gain().is_empty())
+            {
+                continue;
+            }
+
+            let loudness = match analyze_file(file_path) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Error analyzing loudness: {e:#}");
+                    continue;
+                }
+            };
+            info!(
+                "{file}: {:.1} LUFS, true peak {:.3}",
+                loudness.integrated, loudness.true_peak
+            );
+            db.set_loudness(&file, loudness)?;
+            analyzed += 1;
+
+            if write_tags {
+                if let Err(e) = write_replay_gain_tags(file_path, &loudness.replay_gain()) {
+                    warn!("Error writing ReplayGain tags to {file}: {e:#}");
+                }
+            }
+        }
+    }
+
+    Ok(analyzed)
+}
+
+/// Coefficients of a biquad filter, already normalized by `a0`
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    /// First stage of the K-weighting, a high shelf modelling the acoustic effect of the head
+    ///
+    /// Coefficients for any sample rate as derived in libebur128.
+    fn k_shelf(sample_rate: f64) -> Self {
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10_f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+        }
+    }
+
+    /// Second stage of the K-weighting, a high pass
+    fn k_highpass(sample_rate: f64) -> Self {
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        Self {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+        }
+    }
+}
+
+/// Previous in- and outputs of a [`Biquad`] for one channel
+#[derive(Clone, Copy, Debug, Default)]
+struct BiquadState {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, filter: &Biquad, x: f64) -> f64 {
+        let y = filter.b0 * x + filter.b1 * self.x1 + filter.b2 * self.x2
+            - filter.a1 * self.y1
+            - filter.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Integrated loudness and true peak meter as in ITU-R BS.1770-4 / EBU R128
+struct LoudnessMeter {
+    shelf: Biquad,
+    highpass: Biquad,
+    /// shelf and high pass state per channel
+    states: Vec<[BiquadState; 2]>,
+    weights: Vec<f64>,
+    /// channel of the next sample
+    channel: usize,
+    /// frames in a 100ms segment
+    segment_len: usize,
+    /// frames already in the current segment
+    segment_frames: usize,
+    /// weighted sum of squares of the current segment
+    segment_energy: f64,
+    /// weighted sums of squares of the previous segments of the current block
+    previous_segments: Vec<f64>,
+    /// mean square of all 400ms gating blocks, overlapping by 75%
+    blocks: Vec<f64>,
+    /// windowed sinc interpolation filter for each oversampled phase between two samples
+    interpolation: [[f64; TAPS]; OVERSAMPLING],
+    /// last [`TAPS`] samples per channel
+    history: Vec<[f64; TAPS]>,
+    /// index into the history of the last sample
+    history_pos: usize,
+    true_peak: f64,
+}
+
+#[allow(clippy::cast_precision_loss)]
+impl LoudnessMeter {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        let channels = usize::from(channels.max(1));
+        let sample_rate_f = f64::from(sample_rate);
+        // surround channels are weighted higher and the LFE channel is ignored
+        let weights = match channels {
+            5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+            6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+            _ => vec![1.0; channels],
+        };
+
+        Self {
+            shelf: Biquad::k_shelf(sample_rate_f),
+            highpass: Biquad::k_highpass(sample_rate_f),
+            states: vec![[BiquadState::default(); 2]; channels],
+            weights,
+            channel: 0,
+            segment_len: (sample_rate as usize / 10).max(1),
+            segment_frames: 0,
+            segment_energy: 0.0,
+            previous_segments: Vec::with_capacity(SEGMENTS_PER_BLOCK),
+            blocks: Vec::new(),
+            interpolation: interpolation_filter(),
+            history: vec![[0.0; TAPS]; channels],
+            history_pos: 0,
+            true_peak: 0.0,
+        }
+    }
+
+    /// Add the next interleaved sample
+    fn push(&mut self, sample: f64) {
+        let channel = self.channel;
+
+        let state = &mut self.states[channel];
+        let filtered = state[0].process(&self.shelf, sample);
+        let filtered = state[1].process(&self.highpass, filtered);
+        self.segment_energy += self.weights[channel] * filtered * filtered;
+
+        self.history[channel][self.history_pos] = sample;
+        self.update_true_peak(channel);
+
+        self.channel += 1;
+        if self.channel == self.states.len() {
+            self.channel = 0;
+            self.history_pos = (self.history_pos + 1) % TAPS;
+            self.end_frame();
+        }
+    }
+
+    /// Check the sample just added to `channel` and the oversampled values before it
+    fn update_true_peak(&mut self, channel: usize) {
+        let history = &self.history[channel];
+        let mut peak = history[self.history_pos].abs();
+        // phase 0 is the sample in the middle of the history itself
+        for phase in &self.interpolation[1..] {
+            let value: f64 = phase
+                .iter()
+                .enumerate()
+                .map(|(k, coefficient)| coefficient * history[(self.history_pos + TAPS - k) % TAPS])
+                .sum();
+            peak = peak.max(value.abs());
+        }
+        self.true_peak = self.true_peak.max(peak);
+    }
+
+    fn end_frame(&mut self) {
+        self.segment_frames += 1;
+        if self.segment_frames < self.segment_len {
+            return;
+        }
+
+        if self.previous_segments.len() == SEGMENTS_PER_BLOCK - 1 {
+            let block_energy: f64 =
+                self.previous_segments.iter().sum::<f64>() + self.segment_energy;
+            self.blocks
+                .push(block_energy / (SEGMENTS_PER_BLOCK * self.segment_len) as f64);
+            self.previous_segments.remove(0);
+        }
+        self.previous_segments.push(self.segment_energy);
+        self.segment_energy = 0.0;
+        self.segment_frames = 0;
+    }
+
+    /// Get the gated integrated loudness, [`None`] if no block is above the absolute gate
+    fn finish(self) -> Option<Loudness> {
+        let above_absolute: Vec<f64> = self
+            .blocks
+            .into_iter()
+            .filter(|energy| energy_to_lufs(*energy) > ABSOLUTE_GATE)
+            .collect();
+        if above_absolute.is_empty() {
+            return None;
+        }
+
+        let relative_gate = energy_to_lufs(mean(&above_absolute)) + RELATIVE_GATE;
+        let gated: Vec<f64> = above_absolute
+            .into_iter()
+            .filter(|energy| energy_to_lufs(*energy) > relative_gate)
+            .collect();
+
+        #[allow(clippy::cast_possible_truncation)]
+        Some(Loudness {
+            integrated: energy_to_lufs(mean(&gated)) as f32,
+            true_peak: self.true_peak as f32,
+        })
+    }
+}
+
+/// Hann windowed sinc filters to interpolate [`OVERSAMPLING`] values between two samples
+///
+/// The values are interpolated around the middle of the [`TAPS`] last samples.
+#[allow(clippy::cast_precision_loss)]
+fn interpolation_filter() -> [[f64; TAPS]; OVERSAMPLING] {
+    let half = TAPS as f64 / 2.0;
+    let mut filter = [[0.0; TAPS]; OVERSAMPLING];
+    for (phase, coefficients) in filter.iter_mut().enumerate() {
+        let offset = phase as f64 / OVERSAMPLING as f64;
+        for (k, coefficient) in coefficients.iter_mut().enumerate() {
+            let x = k as f64 - half + offset;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 * (1.0 + (PI * x / half).cos());
+            *coefficient = sinc * window;
+        }
+        // keep the gain of every phase at exactly 1
+        let sum: f64 = coefficients.iter().sum();
+        for coefficient in coefficients.iter_mut() {
+            *coefficient /= sum;
+        }
+    }
+
+    filter
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Measure `secs` of a 1 kHz sine with `amplitude` on all `channels`
+    #[allow(clippy::cast_precision_loss)]
+    fn measure_sine(sample_rate: u32, channels: u16, amplitude: f64, secs: u32) -> Loudness {
+        let mut meter = LoudnessMeter::new(sample_rate, channels);
+        for n in 0..sample_rate * secs {
+            let sample =
+                amplitude * (2.0 * PI * 1000.0 * f64::from(n) / f64::from(sample_rate)).sin();
+            for _ in 0..channels {
+                meter.push(sample);
+            }
+        }
+        meter.finish().unwrap()
+    }
+
+    #[test]
+    fn should_measure_reference_sine() {
+        // EBU Tech 3341 case 1: a stereo 1 kHz sine at -23 dBFS is -23 LUFS
+        let amplitude = 10_f64.powf(-23.0 / 20.0);
+        for sample_rate in [44100, 48000] {
+            let loudness = measure_sine(sample_rate, 2, amplitude, 10);
+            assert!(
+                (loudness.integrated + 23.0).abs() < 0.1,
+                "{} LUFS at {sample_rate} Hz",
+                loudness.integrated
+            );
+            assert!((f64::from(loudness.true_peak) / amplitude - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn should_not_measure_silence() {
+        let mut meter = LoudnessMeter::new(48000, 2);
+        for _ in 0..48000 * 2 * 2 {
+            meter.push(0.0);
+        }
+        assert!(meter.finish().is_none());
+    }
+}
@@ -8,6 +8,7 @@ mod stream;
 pub mod buffer;
 pub mod decoder;
 pub mod dynamic_mixer;
+pub mod loudness;
 pub mod queue;
 pub mod source;
 
@@ -16,12 +17,15 @@ pub use conversions::Sample;
 pub use cpal::{traits::StreamTrait, ChannelCount, SampleRate};
 pub use decoder::Symphonia;
 pub use sink::Sink;
-pub use source::Source;
-pub use stream::OutputStream;
+pub use source::{ChannelMixControl, EqualizerControl, Source, TimeSaved};
+pub use stream::{output_device_names, OutputStream};
 
 use self::decoder::buffered_source::BufferedSource;
+use self::stream::{OutputStreamHandle, StreamError};
 
-use super::{PlayerCmd, PlayerProgress, PlayerTrait};
+use super::{
+    OutputFormat, PlayerCmd, PlayerProgress, PlayerTrait, SPEED_MAX, SPEED_MIN, SPEED_STEP,
+};
 use anyhow::Result;
 use parking_lot::Mutex;
 use std::io::Read;
@@ -31,11 +35,14 @@ use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fs::File, io::Cursor};
 use symphonia::core::io::{MediaSource, MediaSourceStream, MediaSourceStreamOptions};
 use termusic_stream::StreamDownload;
-use termusiclib::config::Settings;
+use termusiclib::config::{
+    ChannelMixSettings, Crossfade, CrossfadeSettings, EqualizerGains, PreservePitch,
+    ReplayGainSettings, Settings, SilenceSettings, SilenceTrim, VolumeCurve,
+};
 use termusiclib::track::{MediaType, Track};
 
 static VOLUME_STEP: u16 = 5;
@@ -47,9 +54,29 @@ pub type ArcTotalDuration = Arc<Mutex<TotalDuration>>;
 #[derive(Clone, Debug)]
 pub enum PlayerInternalCmd {
     MessageOnEnd,
-    Play(Box<Track>, bool),
+    /// Play the track
+    Play {
+        track: Box<Track>,
+        gapless: bool,
+        /// linear `ReplayGain` factor
+        gain: f32,
+        /// time stretch instead of resampling for speed changes
+        preserve_pitch: bool,
+        silence: Option<SilenceTrim>,
+    },
     Progress(Duration),
-    QueueNext(String, bool),
+    /// Queue the track after the current one
+    QueueNext {
+        track: Box<Track>,
+        gapless: bool,
+        /// linear `ReplayGain` factor
+        gain: f32,
+        /// fade from the current track into the file instead of playing it afterwards
+        crossfade: Option<Crossfade>,
+        /// time stretch instead of resampling for speed changes
+        preserve_pitch: bool,
+        silence: Option<SilenceTrim>,
+    },
     Resume,
     SeekAbsolute(Duration),
     SeekRelative(i64),
@@ -58,12 +85,37 @@ pub enum PlayerInternalCmd {
     Stop,
     TogglePause,
     Volume(u16),
+    /// Set how the volume maps to the amplitude
+    VolumeCurve(VolumeCurve),
     Eos,
+    /// Set the equalizer gains, [`None`] to disable it
+    Equalizer(Option<EqualizerGains>),
+    /// Set the mono downmix and balance
+    ChannelMix(ChannelMixSettings),
+    /// Set the crossfade duration, the next track is requested that long before the end
+    Crossfade(Option<Duration>),
+    /// Move playback to the output device with the name, [`None`] for the default device
+    OutputDevice(Option<String>),
+    /// Play local tracks at their own sample rate and channel count if the device supports it
+    NativeOutput(bool),
 }
 pub struct RustyBackend {
     volume: Arc<AtomicU16>,
+    /// highest volume in percent
+    volume_max: u16,
     speed: i32,
     pub gapless: bool,
+    replay_gain: ReplayGainSettings,
+    preserve_pitch: PreservePitch,
+    silence: SilenceSettings,
+    /// total length of the silence left out since the start
+    pub time_saved: TimeSaved,
+    /// name of the configured output device, empty for the default device
+    output_device: String,
+    /// whether local tracks are played at their own sample rate and channel count
+    native_output: bool,
+    /// format the sound is currently sent to the output device in
+    pub output_format: Arc<Mutex<Option<OutputFormat>>>,
     command_tx: Sender<PlayerInternalCmd>,
     pub position: Arc<Mutex<Duration>>,
     pub total_duration: ArcTotalDuration,
@@ -86,8 +138,10 @@ impl RustyBackend {
         let picmd_tx_local = picmd_tx.clone();
         let volume = Arc::new(AtomicU16::from(config.player_volume));
         let volume_local = volume.clone();
-        let speed = config.player_speed;
+        let volume_curve = config.player_volume_curve;
+        let speed = config.player_speed_percent;
         let gapless = config.player_gapless;
+        let replay_gain = config.player_replaygain.resolve(config.player_loop_mode);
         let position = Arc::new(Mutex::new(Duration::default()));
         let total_duration = Arc::new(Mutex::new(None));
         let total_duration_local = total_duration.clone();
@@ -97,6 +151,16 @@ impl RustyBackend {
         let radio_title_local = radio_title.clone();
         let radio_downloaded = Arc::new(Mutex::new(100_u64));
         let radio_downloaded_local = radio_downloaded.clone();
+        let equalizer = Arc::new(Mutex::new(config.player_equalizer.active_gains()));
+        let channel_mix = Arc::new(Mutex::new(config.player_channel_mix));
+        let crossfade = config.player_crossfade.duration();
+        let output_device = config.player_output_device.clone();
+        let output_device_local = output_device_name(&output_device);
+        let time_saved = TimeSaved::default();
+        let time_saved_local = time_saved.clone();
+        let native_output = config.player_native_output;
+        let output_format = Arc::new(Mutex::new(None));
+        let output_format_local = output_format.clone();
 
         std::thread::Builder::new()
             .name("playback player loop".into())
@@ -110,7 +174,15 @@ impl RustyBackend {
                     radio_downloaded_local,
                     position_local,
                     volume_local,
+                    volume_curve,
                     speed,
+                    equalizer,
+                    channel_mix,
+                    crossfade,
+                    output_device_local,
+                    native_output,
+                    output_format_local,
+                    time_saved_local,
                 );
             })
             .expect("failed to spawn thread");
@@ -118,8 +190,16 @@ impl RustyBackend {
         Self {
             total_duration,
             volume,
+            volume_max: config.volume_max(),
             speed,
             gapless,
+            replay_gain,
+            preserve_pitch: config.player_preserve_pitch,
+            silence: config.player_silence,
+            time_saved,
+            output_device,
+            native_output,
+            output_format,
             command_tx: picmd_tx,
             position,
             radio_title,
@@ -159,14 +239,27 @@ impl RustyBackend {
 
     #[allow(clippy::unused_async)]
     pub async fn enqueue(&mut self, item: &Track) {
-        self.command(PlayerInternalCmd::Play(
-            Box::new(item.clone()),
-            self.gapless,
-        ));
+        self.command(PlayerInternalCmd::Play {
+            track: Box::new(item.clone()),
+            gapless: self.gapless,
+            gain: item.replay_gain().factor(&self.replay_gain),
+            preserve_pitch: self.preserve_pitch.for_media_type(item.media_type.as_ref()),
+            silence: self.silence.for_media_type(item.media_type.as_ref()),
+        });
     }
 
-    pub fn enqueue_next(&mut self, item: &str) {
-        self.command(PlayerInternalCmd::QueueNext(item.to_string(), self.gapless));
+    pub fn enqueue_next(&mut self, item: &Track, crossfade: Option<Crossfade>) {
+        if item.file().is_none() {
+            return;
+        }
+        self.command(PlayerInternalCmd::QueueNext {
+            track: Box::new(item.clone()),
+            gapless: self.gapless,
+            gain: item.replay_gain().factor(&self.replay_gain),
+            crossfade,
+            preserve_pitch: self.preserve_pitch.for_media_type(item.media_type.as_ref()),
+            silence: self.silence.for_media_type(item.media_type.as_ref()),
+        });
     }
 
     async fn play(&mut self, current_item: &Track) {
@@ -185,6 +278,54 @@ impl RustyBackend {
     pub fn message_on_end(&self) {
         self.command(PlayerInternalCmd::MessageOnEnd);
     }
+
+    /// Set the equalizer gains, applied to the current track immediately
+    pub fn set_equalizer(&self, gains: Option<EqualizerGains>) {
+        self.command(PlayerInternalCmd::Equalizer(gains));
+    }
+
+    /// Set for which media types the pitch is kept, applies to tracks started afterwards
+    pub fn set_preserve_pitch(&mut self, preserve_pitch: PreservePitch) {
+        self.preserve_pitch = preserve_pitch;
+    }
+
+    /// Set how silence is removed, applies to tracks started afterwards
+    pub fn set_silence(&mut self, silence: SilenceSettings) {
+        self.silence = silence;
+    }
+
+    /// Set how long before the end of a track the next one is requested to crossfade into it
+    pub fn set_crossfade(&self, crossfade: &CrossfadeSettings) {
+        self.command(PlayerInternalCmd::Crossfade(crossfade.duration()));
+    }
+
+    /// Move playback to the output device called `name`, empty for the default device
+    ///
+    /// The current track continues at its position on the new device.
+    pub fn set_output_device(&mut self, name: &str) {
+        if self.output_device == name {
+            return;
+        }
+        self.output_device = name.to_string();
+        self.command(PlayerInternalCmd::OutputDevice(output_device_name(name)));
+    }
+
+    /// Set whether local tracks are played at their own sample rate and channel count
+    ///
+    /// Applies to tracks started afterwards, disabling it moves the current track back to the
+    /// default format of the device.
+    pub fn set_native_output(&mut self, native_output: bool) {
+        if self.native_output == native_output {
+            return;
+        }
+        self.native_output = native_output;
+        self.command(PlayerInternalCmd::NativeOutput(native_output));
+    }
+}
+
+/// Convert the configured output device `name` to [`None`] for the default device
+fn output_device_name(name: &str) -> Option<String> {
+    (!name.is_empty()).then(|| name.to_string())
 }
 
 #[async_trait]
@@ -214,11 +355,22 @@ impl PlayerTrait for RustyBackend {
     }
 
     fn set_volume(&mut self, volume: u16) {
-        let volume = volume.min(100);
+        let volume = volume.min(self.volume_max);
         self.volume.store(volume, Ordering::SeqCst);
         self.command(PlayerInternalCmd::Volume(volume));
     }
 
+    fn set_volume_curve(&mut self, curve: VolumeCurve, max: u16) {
+        self.volume_max = max;
+        self.command(PlayerInternalCmd::VolumeCurve(curve));
+        // apply the curve and lower the volume if it is above the new maximum
+        self.set_volume(self.volume());
+    }
+
+    fn set_channel_mix(&mut self, settings: ChannelMixSettings) {
+        self.command(PlayerInternalCmd::ChannelMix(settings));
+    }
+
     fn pause(&mut self) {
         self.command(PlayerInternalCmd::TogglePause);
     }
@@ -243,19 +395,11 @@ impl PlayerTrait for RustyBackend {
     }
 
     fn speed_up(&mut self) {
-        let mut speed = self.speed + 1;
-        if speed > 30 {
-            speed = 30;
-        }
-        self.set_speed(speed);
+        self.set_speed((self.speed + SPEED_STEP).min(SPEED_MAX));
     }
 
     fn speed_down(&mut self) {
-        let mut speed = self.speed - 1;
-        if speed < 1 {
-            speed = 1;
-        }
-        self.set_speed(speed);
+        self.set_speed((self.speed - SPEED_STEP).max(SPEED_MIN));
     }
 
     fn set_speed(&mut self, speed: i32) {
@@ -291,75 +435,263 @@ impl PlayerTrait for RustyBackend {
         self.skip_one();
     }
 
-    fn enqueue_next(&mut self, file: &str) {
-        self.enqueue_next(file);
+    fn enqueue_next(&mut self, track: &Track, crossfade: Option<Crossfade>) {
+        self.enqueue_next(track, crossfade);
+    }
+
+    /// Only applies to tracks started afterwards
+    fn set_replay_gain(&mut self, settings: ReplayGainSettings) {
+        self.replay_gain = settings;
     }
 }
 
 /// Append the `media_source` to the `sink`, while allowing different functions to run with `func`
+#[allow(clippy::too_many_arguments)]
 fn append_to_sink_inner<F: FnOnce(&Symphonia)>(
     media_source: Box<dyn MediaSource>,
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    gain: f32,
+    silence: Option<SilenceTrim>,
+    equalizer: &EqualizerControl,
+    channel_mix: &ChannelMixControl,
+    time_saved: &TimeSaved,
     func: F,
 ) {
     let mss = MediaSourceStream::new(media_source, MediaSourceStreamOptions::default());
     match Symphonia::new(mss, gapless) {
         Ok(decoder) => {
             func(&decoder);
-            sink.append(decoder);
+            let source = decoder.convert_samples::<f32>();
+            match silence {
+                Some(trim) => sink.append(
+                    source
+                        .skip_silence(trim, time_saved.clone())
+                        .amplify(gain)
+                        .equalizer(equalizer.clone())
+                        .channel_mix(channel_mix.clone()),
+                ),
+                None => sink.append(
+                    source
+                        .amplify(gain)
+                        .equalizer(equalizer.clone())
+                        .channel_mix(channel_mix.clone()),
+                ),
+            }
         }
         Err(e) => error!("error decoding '{trace}' is: {e:?}"),
     }
 }
 
 /// Append the `media_source` to the `sink`, while also setting `total_duration*`
+#[allow(clippy::too_many_arguments)]
 fn append_to_sink(
     media_source: Box<dyn MediaSource>,
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    gain: f32,
+    silence: Option<SilenceTrim>,
+    equalizer: &EqualizerControl,
+    channel_mix: &ChannelMixControl,
+    time_saved: &TimeSaved,
     total_duration_local: &ArcTotalDuration,
 ) {
-    append_to_sink_inner(media_source, trace, sink, gapless, |decoder| {
-        std::mem::swap(
-            &mut *total_duration_local.lock(),
-            &mut decoder.total_duration(),
-        );
-    });
+    append_to_sink_inner(
+        media_source,
+        trace,
+        sink,
+        gapless,
+        gain,
+        silence,
+        equalizer,
+        channel_mix,
+        time_saved,
+        |decoder| {
+            std::mem::swap(
+                &mut *total_duration_local.lock(),
+                &mut decoder.total_duration(),
+            );
+        },
+    );
 }
 
 /// Append the `media_source` to the `sink`, while setting duration to be unknown (to [`None`])
+#[allow(clippy::too_many_arguments)]
 fn append_to_sink_no_duration(
     media_source: Box<dyn MediaSource>,
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    gain: f32,
+    silence: Option<SilenceTrim>,
+    equalizer: &EqualizerControl,
+    channel_mix: &ChannelMixControl,
+    time_saved: &TimeSaved,
     total_duration_local: &ArcTotalDuration,
 ) {
-    append_to_sink_inner(media_source, trace, sink, gapless, |_| {
-        // remove old stale duration
-        total_duration_local.lock().take();
-    });
+    append_to_sink_inner(
+        media_source,
+        trace,
+        sink,
+        gapless,
+        gain,
+        silence,
+        equalizer,
+        channel_mix,
+        time_saved,
+        |_| {
+            // remove old stale duration
+            total_duration_local.lock().take();
+        },
+    );
 }
 
 /// Append the `media_source` to the `sink`, while also setting `total_duration_opt`
 ///
 /// This is used for enqueued entries which do not start immediately
+#[allow(clippy::too_many_arguments)]
 fn append_to_sink_queue(
     media_source: Box<dyn MediaSource>,
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    gain: f32,
+    silence: Option<SilenceTrim>,
+    equalizer: &EqualizerControl,
+    channel_mix: &ChannelMixControl,
+    time_saved: &TimeSaved,
     // total_duration_local: &ArcTotalDuration,
     next_duration_opt: &mut Option<Duration>,
 ) {
-    append_to_sink_inner(media_source, trace, sink, gapless, |decoder| {
-        std::mem::swap(next_duration_opt, &mut decoder.total_duration());
-        // rely on EOS message to set next duration
-        sink.message_on_end();
-    });
+    append_to_sink_inner(
+        media_source,
+        trace,
+        sink,
+        gapless,
+        gain,
+        silence,
+        equalizer,
+        channel_mix,
+        time_saved,
+        |decoder| {
+            std::mem::swap(next_duration_opt, &mut decoder.total_duration());
+            // rely on EOS message to set next duration
+            sink.message_on_end();
+        },
+    );
+}
+
+/// Create a sink on the output stream of `handle` with the current `speed` and `volume` factor
+#[allow(clippy::cast_precision_loss)]
+fn new_sink(
+    handle: &OutputStreamHandle,
+    picmd_tx: &Sender<PlayerInternalCmd>,
+    pcmd_tx: &crate::PlayerCmdSender,
+    speed: i32,
+    volume: f32,
+) -> Sink {
+    let sink = Sink::try_new(handle, picmd_tx.clone(), pcmd_tx.clone()).unwrap();
+    sink.set_speed(speed as f32 / 100.0);
+    sink.set_volume(volume);
+    sink
+}
+
+/// Replace `sink` with a new one on the output stream of `handle`, keeping it paused
+///
+/// The tracks on the old sink end with it, without being reported.
+fn replace_sink(
+    sink: &mut Sink,
+    handle: &OutputStreamHandle,
+    picmd_tx: &Sender<PlayerInternalCmd>,
+    pcmd_tx: &crate::PlayerCmdSender,
+    speed: i32,
+    volume: f32,
+) {
+    let paused = sink.is_paused();
+    sink.set_current(false);
+    *sink = new_sink(handle, picmd_tx, pcmd_tx, speed, volume);
+    if paused {
+        sink.pause();
+    }
+}
+
+/// Open a local file, or download the whole file if it is not local
+fn open_queued(file: &str) -> Option<Box<dyn MediaSource>> {
+    match File::open(Path::new(file)) {
+        Ok(v) => Some(Box::new(BufferedSource::new_default_size(v))),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            match RustyBackend::cache_complete(file) {
+                Ok(cursor) => Some(Box::new(cursor)),
+                Err(e) => {
+                    error!("error downloading '{file}': {e:?}");
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            error!("error is now: {e:?}");
+            None
+        }
+    }
+}
+
+/// Open the output device called `name`, or the default device for [`None`]
+fn open_output(name: Option<&str>) -> Result<(OutputStream, OutputStreamHandle), StreamError> {
+    match name {
+        Some(name) => OutputStream::try_from_name(name),
+        None => OutputStream::try_default(),
+    }
+}
+
+/// Get the sample rate and channel count the local `file` is played with
+fn track_format(file: &str) -> Option<(u32, u16)> {
+    let file = File::open(Path::new(file)).ok()?;
+    let mss = MediaSourceStream::new(
+        Box::new(BufferedSource::new_default_size(file)),
+        MediaSourceStreamOptions::default(),
+    );
+    let decoder = Symphonia::new(mss, false).ok()?;
+    // mono is played on two channels by the channel mix
+    Some((decoder.sample_rate(), decoder.channels().max(2)))
+}
+
+/// Whether the local `file` has a different format than `stream`, so it is not played natively
+fn needs_native_output(stream: &OutputStream, file: &str) -> bool {
+    let format = stream.format();
+    track_format(file).is_some_and(|(sample_rate, channels)| {
+        sample_rate != format.sample_rate || channels != format.channels
+    })
+}
+
+/// Open the output device called `name` at the format of the local `file`, if it differs from
+/// the one of `stream` and the device supports it
+fn open_native_output(
+    stream: &OutputStream,
+    name: Option<&str>,
+    file: &str,
+) -> Option<(OutputStream, OutputStreamHandle)> {
+    let (sample_rate, channels) = track_format(file)?;
+    let format = stream.format();
+    if sample_rate == format.sample_rate && channels == format.channels {
+        return None;
+    }
+    let native = OutputStream::try_native(name, sample_rate, channels);
+    if native.is_none() {
+        info!("resampling '{file}' to {format}");
+    }
+    native
+}
+
+/// The current or queued track of the sink, kept to append it again on a different output device
+#[derive(Clone, Debug)]
+struct SinkTrack {
+    track: Box<Track>,
+    gapless: bool,
+    gain: f32,
+    preserve_pitch: bool,
+    silence: Option<SilenceTrim>,
 }
 
 /// Player thread loop
@@ -380,18 +712,47 @@ fn player_thread(
     radio_downloaded: Arc<Mutex<u64>>,
     position: Arc<Mutex<Duration>>,
     volume_inside: Arc<AtomicU16>,
+    mut volume_curve: VolumeCurve,
     mut speed_inside: i32,
+    equalizer: EqualizerControl,
+    channel_mix: ChannelMixControl,
+    mut crossfade_duration: Option<Duration>,
+    mut output_device: Option<String>,
+    mut native_output: bool,
+    output_format: Arc<Mutex<Option<OutputFormat>>>,
+    time_saved: TimeSaved,
 ) {
     let mut is_radio = false;
 
     // option to store enqueued's duration
     // note that the current implementation is only meant to have 1 enqueued next after the current playing song
     let mut next_duration_opt = None;
-    let (_stream, handle) = OutputStream::try_default().unwrap();
-    let mut sink = Sink::try_new(&handle, picmd_tx.clone(), pcmd_tx.clone()).unwrap();
-    sink.set_speed(speed_inside as f32 / 10.0);
-    sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+    let (mut stream, mut handle) = open_output(output_device.as_deref()).unwrap();
+    *output_format.lock() = Some(stream.format().clone());
+    // the tracks given to the sink, to move them to another output device
+    let mut current_track: Option<SinkTrack> = None;
+    let mut queued_track: Option<SinkTrack> = None;
+    // the next track, waiting for the end of the current one to be played at its own format
+    let mut native_queued: Option<SinkTrack> = None;
+    // when to check again for a working device, after the device of the stream was lost
+    let mut next_device_check = Instant::now();
+    let mut sink = new_sink(
+        &handle,
+        &picmd_tx,
+        &pcmd_tx,
+        speed_inside,
+        volume_curve.factor(volume_inside.load(Ordering::SeqCst)),
+    );
+    // the previous track while crossfading, on its own sink so both are mixed
+    let mut fading_sink: Option<Sink> = None;
     loop {
+        if stream.is_device_lost() && Instant::now() >= next_device_check {
+            // the device was unplugged or disappeared otherwise
+            warn!("output device lost, moving playback to the default device");
+            picmd_tx.send(PlayerInternalCmd::OutputDevice(None)).ok();
+            next_device_check = Instant::now() + Duration::from_secs(1);
+        }
+
         let cmd = match picmd_rx.recv_timeout(Duration::from_micros(100)) {
             Ok(v) => v,
             Err(RecvTimeoutError::Disconnected) => break,
@@ -399,141 +760,265 @@ fn player_thread(
         };
 
         match cmd {
-            PlayerInternalCmd::Play(track, gapless) => match track.media_type {
-                Some(MediaType::Music) => {
-                    is_radio = false;
-                    if let Some(file_path) = track.file() {
-                        match File::open(Path::new(file_path)) {
-                            Ok(file) => append_to_sink(
-                                Box::new(BufferedSource::new_default_size(file)),
-                                file_path,
-                                &sink,
-                                gapless,
-                                &total_duration,
-                            ),
-                            Err(e) => error!("error open file: {e}"),
-                        }
-                    }
-                }
-                Some(MediaType::Podcast) => {
-                    is_radio = false;
-                    if let Some(url_str) = track.file() {
-                        let url = match url_str.parse::<reqwest::Url>() {
-                            Ok(v) => v,
-                            Err(err) => {
-                                error!("error parse url: {:#?}", err);
-                                continue;
+            PlayerInternalCmd::Play {
+                track,
+                gapless,
+                gain,
+                preserve_pitch,
+                silence,
+            } => {
+                current_track = Some(SinkTrack {
+                    track: track.clone(),
+                    gapless,
+                    gain,
+                    preserve_pitch,
+                    silence,
+                });
+                queued_track = None;
+                native_queued = None;
+                sink.set_preserve_pitch(preserve_pitch);
+                match track.media_type {
+                    Some(MediaType::Music) => {
+                        is_radio = false;
+                        if let Some(file_path) = track.file() {
+                            let native = native_output
+                                .then(|| {
+                                    open_native_output(&stream, output_device.as_deref(), file_path)
+                                })
+                                .flatten();
+                            if let Some((new_stream, new_handle)) = native {
+                                fading_sink = None;
+                                replace_sink(
+                                    &mut sink,
+                                    &new_handle,
+                                    &picmd_tx,
+                                    &pcmd_tx,
+                                    speed_inside,
+                                    volume_curve.factor(volume_inside.load(Ordering::SeqCst)),
+                                );
+                                sink.set_preserve_pitch(preserve_pitch);
+                                stream = new_stream;
+                                handle = new_handle;
+                                *output_format.lock() = Some(stream.format().clone());
                             }
-                        };
-
-                        match StreamDownload::new_http(
-                            url,
-                            false,
-                            radio_title.clone(),
-                            radio_downloaded.clone(),
-                        ) {
-                            Ok(reader) => {
-                                append_to_sink(
-                                    Box::new(reader),
-                                    url_str,
+                            match File::open(Path::new(file_path)) {
+                                Ok(file) => append_to_sink(
+                                    Box::new(BufferedSource::new_default_size(file)),
+                                    file_path,
                                     &sink,
                                     gapless,
+                                    gain,
+                                    silence,
+                                    &equalizer,
+                                    &channel_mix,
+                                    &time_saved,
                                     &total_duration,
-                                );
-                            }
-                            Err(e) => {
-                                error!("download error: {e}");
-                                continue;
+                                ),
+                                Err(e) => error!("error open file: {e}"),
                             }
                         }
                     }
-                }
+                    Some(MediaType::Podcast) => {
+                        is_radio = false;
+                        if let Some(url_str) = track.file() {
+                            let url = match url_str.parse::<reqwest::Url>() {
+                                Ok(v) => v,
+                                Err(err) => {
+                                    error!("error parse url: {:#?}", err);
+                                    continue;
+                                }
+                            };
 
-                Some(MediaType::LiveRadio) => {
-                    is_radio = true;
-                    if let Some(url_str) = track.file() {
-                        let url = match url_str.parse::<reqwest::Url>() {
-                            Ok(v) => v,
-                            Err(err) => {
-                                error!("error parse url: {:#?}", err);
-                                continue;
-                            }
-                        };
-
-                        match StreamDownload::new_http(
-                            url,
-                            true,
-                            radio_title.clone(),
-                            radio_downloaded.clone(),
-                        ) {
-                            Ok(reader) => {
-                                append_to_sink_no_duration(
-                                    Box::new(reader),
-                                    url_str,
-                                    &sink,
-                                    gapless,
-                                    &total_duration,
-                                );
+                            match StreamDownload::new_http(
+                                url,
+                                false,
+                                radio_title.clone(),
+                                radio_downloaded.clone(),
+                            ) {
+                                Ok(reader) => {
+                                    append_to_sink(
+                                        Box::new(reader),
+                                        url_str,
+                                        &sink,
+                                        gapless,
+                                        gain,
+                                        silence,
+                                        &equalizer,
+                                        &channel_mix,
+                                        &time_saved,
+                                        &total_duration,
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("download error: {e}");
+                                    continue;
+                                }
                             }
-                            Err(e) => {
-                                error!("download error: {e}");
-                                continue;
+                        }
+                    }
+
+                    Some(MediaType::LiveRadio) => {
+                        is_radio = true;
+                        if let Some(url_str) = track.file() {
+                            let url = match url_str.parse::<reqwest::Url>() {
+                                Ok(v) => v,
+                                Err(err) => {
+                                    error!("error parse url: {:#?}", err);
+                                    continue;
+                                }
+                            };
+
+                            match StreamDownload::new_http(
+                                url,
+                                true,
+                                radio_title.clone(),
+                                radio_downloaded.clone(),
+                            ) {
+                                Ok(reader) => {
+                                    append_to_sink_no_duration(
+                                        Box::new(reader),
+                                        url_str,
+                                        &sink,
+                                        gapless,
+                                        gain,
+                                        silence,
+                                        &equalizer,
+                                        &channel_mix,
+                                        &time_saved,
+                                        &total_duration,
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("download error: {e}");
+                                    continue;
+                                }
                             }
                         }
                     }
+                    None => {}
                 }
-                None => {}
-            },
+            }
             PlayerInternalCmd::TogglePause => {
                 sink.toggle_playback();
+                if let Some(fading) = &fading_sink {
+                    fading.toggle_playback();
+                }
             }
-            PlayerInternalCmd::QueueNext(url, gapless) => {
-                match File::open(Path::new(&url)) {
-                    Ok(file) => {
-                        append_to_sink_queue(
-                            Box::new(BufferedSource::new_default_size(file)),
-                            &url,
-                            &sink,
-                            gapless,
-                            &mut next_duration_opt,
-                        );
-                    }
+            PlayerInternalCmd::QueueNext {
+                track,
+                gapless,
+                gain,
+                crossfade,
+                preserve_pitch,
+                silence,
+            } => {
+                let Some(file) = track.file() else {
+                    continue;
+                };
+                let sink_track = SinkTrack {
+                    track: track.clone(),
+                    gapless,
+                    gain,
+                    preserve_pitch,
+                    silence,
+                };
+                if crossfade.is_none() && native_output && needs_native_output(&stream, file) {
+                    // the output is reopened for it once the current track ended, see `Eos`
+                    native_queued = Some(sink_track);
+                    continue;
+                }
+                let Some(media_source) = open_queued(file) else {
+                    continue;
+                };
+                let Some(crossfade) = crossfade else {
+                    queued_track = Some(sink_track);
+                    sink.set_preserve_pitch(preserve_pitch);
+                    append_to_sink_queue(
+                        media_source,
+                        file,
+                        &sink,
+                        gapless,
+                        gain,
+                        silence,
+                        &equalizer,
+                        &channel_mix,
+                        &time_saved,
+                        &mut next_duration_opt,
+                    );
+                    continue;
+                };
 
-                    Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
-                        if let Ok(cursor) = RustyBackend::cache_complete(&url) {
-                            // TODO: replace "trace" param once knowing what to set for trace
-                            append_to_sink_queue(
-                                Box::new(cursor),
-                                // maybe there is a better trace point?
-                                "QueueNext Error cache_complete",
-                                &sink,
-                                gapless,
-                                &mut next_duration_opt,
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        error!("error is now: {e:?}");
-                    }
+                let next_sink = new_sink(
+                    &handle,
+                    &picmd_tx,
+                    &pcmd_tx,
+                    speed_inside,
+                    volume_curve.factor(volume_inside.load(Ordering::SeqCst)),
+                );
+                next_sink.fade_in_next(crossfade);
+                next_sink.set_preserve_pitch(preserve_pitch);
+                current_track = Some(sink_track);
+                queued_track = None;
+                append_to_sink(
+                    media_source,
+                    file,
+                    &next_sink,
+                    gapless,
+                    gain,
+                    silence,
+                    &equalizer,
+                    &channel_mix,
+                    &time_saved,
+                    &total_duration,
+                );
+                sink.fade_out(crossfade);
+                fading_sink = Some(std::mem::replace(&mut sink, next_sink));
+                next_duration_opt = None;
+                // the next track is the current one from now on, the faded out end is not reported
+                if let Err(e) = pcmd_tx.send(PlayerCmd::Eos) {
+                    error!("command Eos sent failed: {e}");
                 }
             }
             PlayerInternalCmd::Resume => {
                 sink.play();
+                if let Some(fading) = &fading_sink {
+                    fading.play();
+                }
             }
             PlayerInternalCmd::Speed(speed) => {
                 speed_inside = speed;
-                sink.set_speed(speed_inside as f32 / 10.0);
+                sink.set_speed(speed_inside as f32 / 100.0);
+                if let Some(fading) = &fading_sink {
+                    fading.set_speed(speed_inside as f32 / 100.0);
+                }
             }
             PlayerInternalCmd::Stop => {
-                sink = Sink::try_new(&handle, picmd_tx.clone(), pcmd_tx.clone()).unwrap();
-                sink.set_speed(speed_inside as f32 / 10.0);
-                sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+                fading_sink = None;
+                current_track = None;
+                queued_track = None;
+                native_queued = None;
+                sink = new_sink(
+                    &handle,
+                    &picmd_tx,
+                    &pcmd_tx,
+                    speed_inside,
+                    volume_curve.factor(volume_inside.load(Ordering::SeqCst)),
+                );
             }
             PlayerInternalCmd::Volume(volume) => {
-                sink.set_volume(f32::from(volume) / 100.0);
+                sink.set_volume(volume_curve.factor(volume));
+                if let Some(fading) = &fading_sink {
+                    fading.set_volume(volume_curve.factor(volume));
+                }
                 volume_inside.store(volume, Ordering::SeqCst);
             }
+            PlayerInternalCmd::VolumeCurve(curve) => {
+                // applied by the volume sent afterwards
+                volume_curve = curve;
+            }
             PlayerInternalCmd::Skip => {
+                // the player starts the track after the skipped one itself
+                native_queued = None;
                 sink.skip_one();
                 if sink.is_paused() {
                     sink.play();
@@ -544,13 +1029,19 @@ fn player_thread(
                 // error!("position in rusty backend is: {}", position);
                 *position.lock() = new_position;
 
+                // drop the previous track once it faded out, its sink would only add silence
+                if fading_sink.as_ref().is_some_and(Sink::empty) {
+                    fading_sink = None;
+                }
+
                 // About to finish signal is a simulation of gstreamer, and used for gapless
+                // and crossfade, which needs the next track earlier
                 if !is_radio {
                     if let Some(d) = *total_duration.lock() {
                         let progress = new_position.as_secs_f64() / d.as_secs_f64();
-                        if progress >= 0.5
-                            && d.saturating_sub(new_position) < Duration::from_secs(2)
-                        {
+                        let window =
+                            Duration::from_secs(2).max(crossfade_duration.unwrap_or_default());
+                        if progress >= 0.5 && d.saturating_sub(new_position) < window {
                             if let Err(e) = pcmd_tx.send(PlayerCmd::AboutToFinish) {
                                 error!("command AboutToFinish sent failed: {e}");
                             }
@@ -587,16 +1078,113 @@ fn player_thread(
                 if paused {
                     std::thread::sleep(std::time::Duration::from_millis(50));
                     sink.pause();
-                    sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+                    sink.set_volume(volume_curve.factor(volume_inside.load(Ordering::SeqCst)));
                 }
             }
 
+            PlayerInternalCmd::Equalizer(gains) => {
+                // shared with all appended sources, which pick it up while playing
+                *equalizer.lock() = gains;
+            }
+            PlayerInternalCmd::ChannelMix(settings) => {
+                // shared with all appended sources, which pick it up while playing
+                *channel_mix.lock() = settings;
+            }
+
+            PlayerInternalCmd::Crossfade(duration) => {
+                crossfade_duration = duration;
+            }
+
             PlayerInternalCmd::Eos => {
+                if let Some(queued) = native_queued.take() {
+                    // the queued track was not appended, so it is played on its own now
+                    picmd_tx
+                        .send(PlayerInternalCmd::Play {
+                            track: queued.track,
+                            gapless: queued.gapless,
+                            gain: queued.gain,
+                            preserve_pitch: queued.preserve_pitch,
+                            silence: queued.silence,
+                        })
+                        .ok();
+                    picmd_tx.send(PlayerInternalCmd::MessageOnEnd).ok();
+                    continue;
+                }
                 // replace the current total_duration with the next one
                 // this is only present when QueueNext was used; which is only used if gapless is enabled
                 if next_duration_opt.is_some() {
                     *total_duration.lock() = next_duration_opt;
                 }
+                if queued_track.is_some() {
+                    current_track = queued_track.take();
+                }
+            }
+
+            PlayerInternalCmd::OutputDevice(name) => {
+                let (new_stream, new_handle) = match open_output(name.as_deref()) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        // keep the current stream, a lost device is checked again later
+                        error!("error opening output device: {e}");
+                        continue;
+                    }
+                };
+
+                let position = sink.elapsed();
+                fading_sink = None;
+                replace_sink(
+                    &mut sink,
+                    &new_handle,
+                    &picmd_tx,
+                    &pcmd_tx,
+                    speed_inside,
+                    volume_curve.factor(volume_inside.load(Ordering::SeqCst)),
+                );
+                stream = new_stream;
+                handle = new_handle;
+                output_device = name;
+                *output_format.lock() = Some(stream.format().clone());
+
+                // append the tracks again, these commands are handled after this one in order
+                if let Some(current) = current_track.clone() {
+                    picmd_tx
+                        .send(PlayerInternalCmd::Play {
+                            track: current.track,
+                            gapless: current.gapless,
+                            gain: current.gain,
+                            preserve_pitch: current.preserve_pitch,
+                            silence: current.silence,
+                        })
+                        .ok();
+                    picmd_tx.send(PlayerInternalCmd::MessageOnEnd).ok();
+                    if let Some(queued) = queued_track.clone().or_else(|| native_queued.clone()) {
+                        picmd_tx
+                            .send(PlayerInternalCmd::QueueNext {
+                                track: queued.track,
+                                gapless: queued.gapless,
+                                gain: queued.gain,
+                                crossfade: None,
+                                preserve_pitch: queued.preserve_pitch,
+                                silence: queued.silence,
+                            })
+                            .ok();
+                    }
+                    if !is_radio {
+                        picmd_tx
+                            .send(PlayerInternalCmd::SeekAbsolute(position))
+                            .ok();
+                    }
+                }
+            }
+
+            PlayerInternalCmd::NativeOutput(enabled) => {
+                native_output = enabled;
+                if !enabled && stream.is_native() {
+                    // move the current track back to the default format of the device
+                    picmd_tx
+                        .send(PlayerInternalCmd::OutputDevice(output_device.clone()))
+                        .ok();
+                }
             }
         }
     }
@@ -9,6 +9,7 @@ use super::stream::{OutputStreamHandle, PlayError};
 use super::{queue, source::Done, PlayerInternalCmd, Sample, Source};
 use crate::PlayerCmd;
 use cpal::FromSample;
+use termusiclib::config::{Crossfade, CrossfadeCurve};
 
 /// Handle to an device that outputs sounds.
 ///
@@ -32,6 +33,14 @@ struct Controls {
     stopped: AtomicBool,
     speed: Mutex<f32>,
     to_clear: Mutex<u32>,
+    /// fade in applied to the next appended sound
+    fade_in: Mutex<Option<Crossfade>>,
+    /// fade out to start on the current sound
+    fade_out: Mutex<Option<Crossfade>>,
+    /// whether the sounds are the current track, which is not the case while fading out
+    is_current: AtomicBool,
+    /// keep the pitch of the next appended sounds when changing the speed
+    preserve_pitch: AtomicBool,
 }
 
 impl Sink {
@@ -66,6 +75,10 @@ impl Sink {
                 seek: Mutex::new(None),
                 speed: Mutex::new(1.0),
                 to_clear: Mutex::new(0),
+                fade_in: Mutex::new(None),
+                fade_out: Mutex::new(None),
+                is_current: AtomicBool::new(true),
+                preserve_pitch: AtomicBool::new(false),
             }),
             sound_count: Arc::new(AtomicUsize::new(0)),
             detached: false,
@@ -94,19 +107,32 @@ impl Sink {
         }
 
         let controls = self.controls.clone();
+        let progress_controls = self.controls.clone();
 
         let start_played = AtomicBool::new(false);
 
+        let fade_in = self.controls.fade_in.lock().take().unwrap_or(Crossfade {
+            duration: Duration::ZERO,
+            curve: CrossfadeCurve::Linear,
+        });
+        let preserve_pitch = self.controls.preserve_pitch.load(Ordering::SeqCst);
         let tx = self.message_tx.clone();
         let elapsed = self.elapsed.clone();
         let source = source
+            .convert_samples::<f32>()
+            .fade_in(fade_in.duration, fade_in.curve)
+            .time_stretch(1.0)
             .speed(1.0)
             .pausable(false)
             .amplify(1.0)
+            .soft_limit()
+            .fade_out()
             .skippable()
             .stoppable()
             .periodic_access(Duration::from_millis(500), move |src| {
-                tx.send(PlayerInternalCmd::Progress(src.elapsed())).ok();
+                if progress_controls.is_current.load(Ordering::SeqCst) {
+                    tx.send(PlayerInternalCmd::Progress(src.elapsed())).ok();
+                }
             })
             .periodic_access(Duration::from_millis(5), move |src| {
                 let src = src.inner_mut();
@@ -124,13 +150,23 @@ impl Sink {
                             *to_clear -= 1;
                         }
                     }
-                    let amp = src.inner_mut().inner_mut();
-                    amp.set_factor(*controls.volume.lock());
+                    let fade_out = src.inner_mut().inner_mut();
+                    if let Some(crossfade) = controls.fade_out.lock().take() {
+                        fade_out.start(crossfade);
+                    }
+                    let volume = *controls.volume.lock();
+                    let limit = fade_out.inner_mut();
+                    limit.set_enabled(volume > 1.0);
+                    let amp = limit.inner_mut();
+                    amp.set_factor(volume);
                     amp.inner_mut()
                         .set_paused(controls.pause.load(Ordering::SeqCst));
-                    amp.inner_mut()
-                        .inner_mut()
-                        .set_factor(*controls.speed.lock());
+                    let speed = amp.inner_mut().inner_mut();
+                    if preserve_pitch {
+                        speed.inner_mut().set_factor(*controls.speed.lock());
+                    } else {
+                        speed.set_factor(*controls.speed.lock());
+                    }
                     start_played.store(true, Ordering::SeqCst);
                 }
             })
@@ -152,7 +188,7 @@ impl Sink {
     /// Changes the volume of the sound.
     ///
     /// The value `1.0` is the "normal" volume (unfiltered input). Any value other than `1.0` will
-    /// multiply each sample by this value.
+    /// multiply each sample by this value, above `1.0` the samples are also softly limited.
     #[inline]
     pub fn set_volume(&self, value: f32) {
         *self.controls.volume.lock() = value;
@@ -240,6 +276,32 @@ impl Sink {
         }
     }
 
+    /// Keeps the pitch of sounds appended afterwards when changing the speed, by time stretching.
+    pub fn set_preserve_pitch(&self, preserve_pitch: bool) {
+        self.controls
+            .preserve_pitch
+            .store(preserve_pitch, Ordering::SeqCst);
+    }
+
+    /// Fades in the next appended sound over the duration of `crossfade`.
+    pub fn fade_in_next(&self, crossfade: Crossfade) {
+        *self.controls.fade_in.lock() = Some(crossfade);
+    }
+
+    /// Fades out the current sound, which ends afterwards.
+    ///
+    /// As the sound is no longer the current track, neither its progress nor its end are reported.
+    pub fn fade_out(&self, crossfade: Crossfade) {
+        self.set_current(false);
+        *self.controls.fade_out.lock() = Some(crossfade);
+    }
+
+    /// Sets whether the sounds are the current track, only then their progress and end are reported.
+    #[inline]
+    pub fn set_current(&self, is_current: bool) {
+        self.controls.is_current.store(is_current, Ordering::SeqCst);
+    }
+
     /// Stops the sink by emptying the queue.
     #[inline]
     pub fn stop(&self) {
@@ -283,10 +345,14 @@ impl Sink {
         if let Some(sleep_until_end) = self.sleep_until_end.lock().take() {
             let cmd_tx = self.cmd_tx.clone();
             let message_tx = self.message_tx.clone();
+            let controls = self.controls.clone();
             std::thread::Builder::new()
                 .name("rusty message_on_end".into())
                 .spawn(move || {
                     let _drop = sleep_until_end.recv();
+                    if !controls.is_current.load(Ordering::SeqCst) {
+                        return;
+                    }
                     if let Err(e) = cmd_tx.send(PlayerCmd::Eos) {
                         error!("Error in message_on_end: {e}");
                     }
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use termusiclib::config::ChannelMixSettings;
+
+use super::super::conversions::ChannelCountConverter;
+use super::Source;
+
+/// Handle to change the mono downmix and balance of a playing [`ChannelMix`]
+pub type ChannelMixControl = Arc<Mutex<ChannelMixSettings>>;
+
+/// Number of frames after which the [`ChannelMixControl`] is checked for changes
+const CONTROL_CHECK_INTERVAL: usize = 512;
+
+/// Internal function that builds a `ChannelMix` object.
+pub fn channel_mix<I>(input: I, control: ChannelMixControl) -> ChannelMix<I>
+where
+    I: Source<Item = f32>,
+{
+    let from = input.channels().max(1);
+    // mono sound is played on two channels, so the balance can move it
+    let to = from.max(2);
+    let settings = *control.lock();
+
+    ChannelMix {
+        input: ChannelCountConverter::new(input, from, to),
+        control,
+        settings,
+        gains: settings.gains(),
+        from,
+        to,
+        frame: Vec::with_capacity(usize::from(to)),
+        position: 0,
+        until_check: CONTROL_CHECK_INTERVAL,
+    }
+}
+
+/// Filter that mixes all channels into one, played on every channel, and turns down the left or
+/// right channel for the balance.
+///
+/// Mono input is converted to stereo by repeating its channel. The sound is processed a frame at a
+/// time, so a change of the [`ChannelMixControl`] never applies to only some channels of a frame.
+#[derive(Clone, Debug)]
+pub struct ChannelMix<I>
+where
+    I: Iterator<Item = f32>,
+{
+    input: ChannelCountConverter<I>,
+    control: ChannelMixControl,
+    settings: ChannelMixSettings,
+    /// factors of the left and right channel
+    gains: (f32, f32),
+    /// channels of the input
+    from: u16,
+    /// channels of the output
+    to: u16,
+    /// the current output frame
+    frame: Vec<f32>,
+    /// next sample of `frame` to return
+    position: usize,
+    until_check: usize,
+}
+
+#[allow(unused)]
+impl<I> ChannelMix<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        self.input.inner()
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        self.input.inner_mut()
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input.into_inner()
+    }
+
+    /// Read and mix the next frame, returns `false` once the input ended
+    #[allow(clippy::cast_precision_loss)]
+    fn next_frame(&mut self) -> bool {
+        self.until_check -= 1;
+        if self.until_check == 0 {
+            self.until_check = CONTROL_CHECK_INTERVAL;
+            self.settings = *self.control.lock();
+            self.gains = self.settings.gains();
+        }
+
+        self.frame.clear();
+        self.frame
+            .extend(self.input.by_ref().take(usize::from(self.to)));
+        self.position = 0;
+        if self.frame.is_empty() {
+            return false;
+        }
+
+        if self.settings.mono {
+            let mix = self.frame.iter().sum::<f32>() / self.frame.len() as f32;
+            self.frame.fill(mix);
+        }
+        let (left, right) = self.gains;
+        if let [first, second, ..] = self.frame.as_mut_slice() {
+            *first *= left;
+            *second *= right;
+        }
+        true
+    }
+}
+
+impl<I> Iterator for ChannelMix<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.frame.len() - self.position;
+        let (min, max) = self.input.size_hint();
+        (min + buffered, max.map(|max| max + buffered))
+    }
+}
+
+impl<I> Source for ChannelMix<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        let buffered = self.frame.len() - self.position;
+        self.inner()
+            .current_frame_len()
+            .map(|len| len / usize::from(self.from) * usize::from(self.to) + buffered)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.to
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.inner().sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner().total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.inner_mut().elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        // the rest of the current frame is unrelated to the sound after the seek
+        self.frame.clear();
+        self.position = 0;
+        self.inner_mut().seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+    use pretty_assertions::assert_eq;
+
+    fn mix(channels: u16, samples: &[f32], mono: bool, balance: i16) -> Vec<f32> {
+        let source = SamplesBuffer::new(channels, 44100, samples.to_vec());
+        let control = Arc::new(Mutex::new(ChannelMixSettings { mono, balance }));
+        let mix = channel_mix(source, control);
+        assert_eq!(mix.channels(), channels.max(2));
+        mix.collect()
+    }
+
+    fn assert_near(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} instead of {expected:?}");
+        }
+    }
+
+    #[test]
+    fn should_apply_mono_and_balance() {
+        let stereo = [0.8, 0.2, -0.4, 0.6];
+        assert_near(&mix(2, &stereo, false, 0), &stereo);
+        assert_near(&mix(2, &stereo, true, 0), &[0.5, 0.5, 0.1, 0.1]);
+        // the side the balance is moved to keeps its level
+        assert_near(&mix(2, &stereo, false, 50), &[0.4, 0.2, -0.2, 0.6]);
+        assert_near(&mix(2, &stereo, false, -100), &[0.8, 0.0, -0.4, 0.0]);
+        assert_near(&mix(2, &stereo, true, 100), &[0.0, 0.5, 0.0, 0.1]);
+        // mono input is played on both channels
+        assert_near(&mix(1, &[0.3, -0.6], false, 0), &[0.3, 0.3, -0.6, -0.6]);
+        assert_near(&mix(1, &[0.3, -0.6], false, 50), &[0.15, 0.3, -0.3, -0.6]);
+    }
+}
@@ -0,0 +1,272 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use termusiclib::config::{EqualizerGains, EQ_BAND_FREQUENCIES};
+
+use super::Source;
+
+/// Handle to change the gains of a playing [`Equalizer`], [`None`] disables it
+pub type EqualizerControl = Arc<Mutex<Option<EqualizerGains>>>;
+
+/// Quality factor of each band, about one octave wide
+const BAND_Q: f32 = std::f32::consts::SQRT_2;
+/// Number of samples after which the [`EqualizerControl`] is checked for changes
+const CONTROL_CHECK_INTERVAL: usize = 1024;
+
+/// Internal function that builds a `Equalizer` object.
+pub fn equalizer<I>(input: I, control: EqualizerControl) -> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    let mut equalizer = Equalizer {
+        input,
+        control,
+        gains: None,
+        sample_rate: 0,
+        channels: 0,
+        filters: Vec::new(),
+        states: Vec::new(),
+        preamp: 1.0,
+        channel: 0,
+        until_check: CONTROL_CHECK_INTERVAL,
+    };
+    equalizer.update_filters();
+
+    equalizer
+}
+
+/// Coefficients of a peaking biquad filter, already normalized by `a0`
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    /// Peaking filter as in the "Audio EQ Cookbook" by Robert Bristow-Johnson
+    fn peaking(sample_rate: f32, frequency: f32, gain_db: f32) -> Self {
+        let a = 10_f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * BAND_Q);
+        let a0 = 1.0 + alpha / a;
+
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+/// Previous in- and outputs of a [`Biquad`] for one channel
+#[derive(Clone, Copy, Debug, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, filter: &Biquad, x: f32) -> f32 {
+        let y = filter.b0 * x + filter.b1 * self.x1 + filter.b2 * self.x2
+            - filter.a1 * self.y1
+            - filter.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Filter that boosts or cuts the bands in [`EQ_BAND_FREQUENCIES`].
+///
+/// Positive gains are compensated by lowering the overall volume, to not clip.
+#[derive(Debug)]
+pub struct Equalizer<I> {
+    input: I,
+    control: EqualizerControl,
+    gains: Option<EqualizerGains>,
+    sample_rate: u32,
+    channels: u16,
+    /// one filter per band that is not flat
+    filters: Vec<Biquad>,
+    /// one state per channel and filter, grouped by channel
+    states: Vec<BiquadState>,
+    preamp: f32,
+    /// channel of the next sample
+    channel: usize,
+    until_check: usize,
+}
+
+#[allow(unused)]
+impl<I> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+
+    /// Apply changed gains or a changed input format
+    #[allow(clippy::cast_precision_loss)]
+    fn update_filters(&mut self) {
+        let gains = *self.control.lock();
+        let sample_rate = self.input.sample_rate();
+        let channels = self.input.channels().max(1);
+        if gains == self.gains && sample_rate == self.sample_rate && channels == self.channels {
+            return;
+        }
+        if channels != self.channels {
+            self.channel = 0;
+        }
+        self.gains = gains;
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+
+        let nyquist = sample_rate as f32 / 2.0;
+        self.filters = gains
+            .map(|gains| {
+                EQ_BAND_FREQUENCIES
+                    .iter()
+                    .zip(gains)
+                    // bands above the nyquist frequency cannot be represented
+                    .filter(|(frequency, gain)| **frequency < nyquist * 0.9 && *gain != 0.0)
+                    .map(|(frequency, gain)| Biquad::peaking(sample_rate as f32, *frequency, gain))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let max_gain = gains.map_or(0.0, |gains| gains.into_iter().fold(0.0, f32::max));
+        self.preamp = 10_f32.powf(-max_gain / 20.0);
+
+        // keep the state on gain changes, resetting it would be audible as a click
+        let states_len = self.filters.len() * usize::from(channels);
+        if self.states.len() != states_len {
+            self.states = vec![BiquadState::default(); states_len];
+        }
+    }
+}
+
+impl<I> Iterator for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+
+        self.until_check -= 1;
+        if self.until_check == 0 {
+            self.until_check = CONTROL_CHECK_INTERVAL;
+            self.update_filters();
+        }
+
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % usize::from(self.channels);
+        if self.filters.is_empty() {
+            return Some(sample);
+        }
+
+        let filters_len = self.filters.len();
+        let states = &mut self.states[channel * filters_len..(channel + 1) * filters_len];
+        let value = self
+            .filters
+            .iter()
+            .zip(states)
+            .fold(sample * self.preamp, |value, (filter, state)| {
+                state.process(filter, value)
+            });
+
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Equalizer<I> where I: Source<Item = f32> + ExactSizeIterator {}
+
+impl<I> Source for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        // the previous samples are unrelated to the ones after the seek
+        self.states.fill(BiquadState::default());
+        self.channel = 0;
+        self.input.seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+    use pretty_assertions::assert_eq;
+    use termusiclib::config::EQ_BAND_COUNT;
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn should_not_change_sound_when_flat() {
+        // a sawtooth, which has energy in all bands
+        let samples: Vec<f32> = (0..4096).map(|n| (n % 100) as f32 / 50.0 - 1.0).collect();
+        for gains in [None, Some([0.0; EQ_BAND_COUNT])] {
+            let source = SamplesBuffer::new(2, 44100, samples.clone());
+            let output: Vec<f32> = equalizer(source, Arc::new(Mutex::new(gains))).collect();
+            assert_eq!(output, samples);
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use std::time::Duration;
 
+use termusiclib::config::CrossfadeCurve;
+
 use super::{Sample, Source};
 
 /// Internal function that builds a `FadeIn` object.
 #[allow(unused, clippy::cast_precision_loss)]
-pub fn fadein<I>(input: I, duration: Duration) -> FadeIn<I>
+pub fn fadein<I>(input: I, duration: Duration, curve: CrossfadeCurve) -> FadeIn<I>
 where
     I: Source,
     I::Item: Sample,
@@ -15,6 +17,7 @@ where
         input,
         remaining_ns: duration as f32,
         total_ns: duration as f32,
+        curve,
     }
 }
 
@@ -24,6 +27,7 @@ pub struct FadeIn<I> {
     input: I,
     remaining_ns: f32,
     total_ns: f32,
+    curve: CrossfadeCurve,
 }
 
 #[allow(unused)]
@@ -65,7 +69,7 @@ where
             return self.input.next();
         }
 
-        let factor = 1.0 - self.remaining_ns / self.total_ns;
+        let factor = self.curve.gain(1.0 - self.remaining_ns / self.total_ns);
         self.remaining_ns -=
             1_000_000_000.0 / (self.input.sample_rate() as f32 * f32::from(self.channels()));
         self.input.next().map(|value| value.amplify(factor))
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use termusiclib::config::{Crossfade, CrossfadeCurve};
+
+use super::{Sample, Source};
+
+/// Internal function that builds a `FadeOut` object.
+pub fn fadeout<I>(input: I) -> FadeOut<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    FadeOut {
+        input,
+        remaining_ns: 0.0,
+        total_ns: 0.0,
+        curve: CrossfadeCurve::default(),
+        fading: false,
+    }
+}
+
+/// Filter that lowers the volume to silence over a time period once started, then ends the sound.
+#[derive(Clone, Debug)]
+pub struct FadeOut<I> {
+    input: I,
+    remaining_ns: f32,
+    total_ns: f32,
+    curve: CrossfadeCurve,
+    fading: bool,
+}
+
+#[allow(unused)]
+impl<I> FadeOut<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    /// Start fading out, the sound ends after `crossfade.duration`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn start(&mut self, crossfade: Crossfade) {
+        self.total_ns = crossfade.duration.as_nanos() as f32;
+        self.remaining_ns = self.total_ns;
+        self.curve = crossfade.curve;
+        self.fading = true;
+    }
+
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+}
+
+impl<I> Iterator for FadeOut<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    #[inline]
+    #[allow(clippy::cast_precision_loss)]
+    fn next(&mut self) -> Option<I::Item> {
+        if !self.fading {
+            return self.input.next();
+        }
+        if self.remaining_ns <= 0.0 {
+            return None;
+        }
+
+        let factor = self.curve.gain(self.remaining_ns / self.total_ns);
+        self.remaining_ns -=
+            1_000_000_000.0 / (self.input.sample_rate() as f32 * f32::from(self.channels()));
+        self.input.next().map(|value| value.amplify(factor))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.fading {
+            (0, self.input.size_hint().1)
+        } else {
+            self.input.size_hint()
+        }
+    }
+}
+
+impl<I> Source for FadeOut<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        self.input.seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+
+    #[test]
+    fn should_fade_to_silence_and_end() {
+        for curve in [
+            CrossfadeCurve::Linear,
+            CrossfadeCurve::EqualPower,
+            CrossfadeCurve::SCurve,
+        ] {
+            let mut fade = fadeout(SamplesBuffer::new(1, 1000, vec![1.0_f32; 1000]));
+            fade.start(Crossfade {
+                duration: Duration::from_millis(100),
+                curve,
+            });
+            let output: Vec<f32> = fade.collect();
+
+            assert_eq!(output.len(), 100, "{curve:?}");
+            assert!((output[0] - 1.0).abs() < f32::EPSILON, "{curve:?}");
+            assert!(
+                output.windows(2).all(|pair| pair[1] <= pair[0]),
+                "{curve:?}"
+            );
+            assert!(output[99] < 0.02, "{curve:?} ends at {}", output[99]);
+        }
+    }
+}
@@ -2,34 +2,48 @@
 
 use std::time::Duration;
 
+use termusiclib::config::{CrossfadeCurve, SilenceTrim};
+
 use super::Sample;
 
 pub use self::amplify::Amplify;
+pub use self::channel_mix::{ChannelMix, ChannelMixControl};
 pub use self::done::Done;
 pub use self::empty::Empty;
+pub use self::equalizer::{Equalizer, EqualizerControl};
 pub use self::fadein::FadeIn;
+pub use self::fadeout::FadeOut;
 pub use self::pausable::Pausable;
 pub use self::periodic::PeriodicAccess;
 pub use self::samples_converter::SamplesConverter;
+pub use self::skip_silence::{SkipSilence, TimeSaved};
 pub use self::skippable::Skippable;
+pub use self::soft_limit::SoftLimit;
 pub use self::speed::Speed;
 pub use self::stoppable::Stoppable;
 pub use self::take::TakeDuration;
+pub use self::time_stretch::TimeStretch;
 pub use self::uniform::UniformSourceIterator;
 pub use self::zero::Zero;
 
 mod amplify;
+mod channel_mix;
 mod done;
 mod empty;
+mod equalizer;
 mod fadein;
+mod fadeout;
 // mod http;
 mod pausable;
 mod periodic;
 mod samples_converter;
+mod skip_silence;
 mod skippable;
+mod soft_limit;
 mod speed;
 mod stoppable;
 mod take;
+mod time_stretch;
 mod uniform;
 mod zero;
 
@@ -149,13 +163,40 @@ where
         amplify::amplify(self, value)
     }
 
-    /// Fades in the sound.
+    /// Applies the equalizer gains set in `control`, which can be changed while playing.
+    #[inline]
+    fn equalizer(self, control: EqualizerControl) -> Equalizer<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        equalizer::equalizer(self, control)
+    }
+
+    /// Applies the mono downmix and balance set in `control`, which can be changed while playing.
+    #[inline]
+    fn channel_mix(self, control: ChannelMixControl) -> ChannelMix<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        channel_mix::channel_mix(self, control)
+    }
+
+    /// Fades in the sound, with the volume following `curve`.
+    #[inline]
+    fn fade_in(self, duration: Duration, curve: CrossfadeCurve) -> FadeIn<Self>
+    where
+        Self: Sized,
+    {
+        fadein::fadein(self, duration, curve)
+    }
+
+    /// Makes the sound fade out and end once [`FadeOut::start`] is called.
     #[inline]
-    fn fade_in(self, duration: Duration) -> FadeIn<Self>
+    fn fade_out(self) -> FadeOut<Self>
     where
         Self: Sized,
     {
-        fadein::fadein(self, duration)
+        fadeout::fadeout(self)
     }
 
     /// Calls the `access` closure on `Self` the first time the source is iterated and every
@@ -213,6 +254,34 @@ where
     {
         skippable::skippable(self)
     }
+
+    /// Keeps amplified samples from clipping once enabled with [`SoftLimit::set_enabled`].
+    #[inline]
+    fn soft_limit(self) -> SoftLimit<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        soft_limit::soft_limit(self)
+    }
+
+    /// Leaves out the silence as set in `trim`, adding its length to `time_saved`.
+    #[inline]
+    fn skip_silence(self, trim: SilenceTrim, time_saved: TimeSaved) -> SkipSilence<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        skip_silence::skip_silence(self, trim, time_saved)
+    }
+
+    /// Changes the play speed of the sound without changing its pitch.
+    #[inline]
+    fn time_stretch(self, factor: f32) -> TimeStretch<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        time_stretch::time_stretch(self, factor)
+    }
+
     /// Changes the play speed of the sound. Does not adjust the samples, only the play speed.
     #[inline]
     fn speed(self, ratio: f32) -> Speed<Self>
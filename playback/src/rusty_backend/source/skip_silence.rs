@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use termusiclib::config::SilenceTrim;
+
+use super::Source;
+
+/// Total time of the silence left out by all [`SkipSilence`] sources sharing it
+pub type TimeSaved = Arc<Mutex<Duration>>;
+
+/// Length of the blocks that are checked for silence as a whole, in milliseconds
+const BLOCK_MS: u32 = 10;
+/// Silence held back to find out if it is at the end of the track is played once it gets longer
+/// than this, in seconds, as it is most likely a pause within the track
+const MAX_HELD_SECS: u32 = 30;
+
+/// Internal function that builds a `SkipSilence` object.
+pub fn skip_silence<I>(input: I, trim: SilenceTrim, time_saved: TimeSaved) -> SkipSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    SkipSilence {
+        input,
+        trim,
+        time_saved,
+        sound_started: false,
+        pause_frames: 0,
+        block: Vec::new(),
+        held: Vec::new(),
+        output: VecDeque::new(),
+    }
+}
+
+/// Filter that leaves out the silence at the start and end of the sound and optionally shortens
+/// the pauses within it.
+///
+/// The sound is checked in blocks of 10ms, a block is silent if none of its samples reaches the
+/// threshold. Silence at the end is held back until the sound continues or the input ends.
+#[derive(Clone, Debug)]
+pub struct SkipSilence<I> {
+    input: I,
+    trim: SilenceTrim,
+    time_saved: TimeSaved,
+    /// whether a block that is not silent was seen yet
+    sound_started: bool,
+    /// frames in the current pause so far
+    pause_frames: usize,
+    /// the block currently checked
+    block: Vec<f32>,
+    /// silent blocks not played yet, as they may be the end of the sound
+    held: Vec<f32>,
+    /// samples to play
+    output: VecDeque<f32>,
+}
+
+#[allow(unused)]
+impl<I> SkipSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+
+    #[inline]
+    fn channel_count(&self) -> usize {
+        usize::from(self.input.channels().max(1))
+    }
+
+    /// Add `samples` of silence to the time saved
+    fn skipped(&self, samples: usize) {
+        let frames = samples / self.channel_count();
+        let sample_rate = u64::from(self.input.sample_rate().max(1));
+        *self.time_saved.lock() += Duration::from_micros(frames as u64 * 1_000_000 / sample_rate);
+    }
+
+    /// Check the next block of the input, returns `false` once the input ended
+    fn process_block(&mut self) -> bool {
+        let channels = self.channel_count();
+        let block_len = (self.input.sample_rate() * BLOCK_MS / 1000).max(1) as usize * channels;
+        self.block.clear();
+        self.block.extend(self.input.by_ref().take(block_len));
+        if self.block.is_empty() {
+            // the held back silence is the end of the sound
+            self.skipped(self.held.len());
+            self.held.clear();
+            return false;
+        }
+
+        let silent = self
+            .block
+            .iter()
+            .all(|sample| sample.abs() < self.trim.threshold);
+        if !silent {
+            // the pause ended within the sound, so it is played after all
+            self.sound_started = true;
+            self.pause_frames = 0;
+            self.output.extend(self.held.drain(..));
+            self.output.extend(&self.block);
+            return true;
+        }
+
+        self.pause_frames += self.block.len() / channels;
+        let pause = Duration::from_micros(
+            self.pause_frames as u64 * 1_000_000 / u64::from(self.input.sample_rate().max(1)),
+        );
+        if !self.sound_started || self.trim.max_pause.is_some_and(|max| pause > max) {
+            self.skipped(self.block.len());
+            return true;
+        }
+
+        self.held.extend(&self.block);
+        if self.held.len() / channels > (self.input.sample_rate() * MAX_HELD_SECS) as usize {
+            self.output.extend(self.held.drain(..));
+        }
+        true
+    }
+}
+
+impl<I> Iterator for SkipSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            if let Some(sample) = self.output.pop_front() {
+                return Some(sample);
+            }
+            if !self.process_block() {
+                return None;
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.output.len(), None)
+    }
+}
+
+impl<I> Source for SkipSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        // the buffered samples are unrelated to the ones after the seek
+        self.block.clear();
+        self.held.clear();
+        self.output.clear();
+        self.pause_frames = 0;
+        // only the start of the sound is trimmed
+        self.sound_started = !time.is_zero();
+        self.input.seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+    use pretty_assertions::assert_eq;
+
+    const SAMPLE_RATE: u32 = 8000;
+
+    /// `ms` of a square wave, or silence if `level` is `0.0`
+    fn sound(ms: u32, level: f32) -> impl Iterator<Item = f32> {
+        let len = (SAMPLE_RATE * ms / 1000) as usize;
+        (0..len).map(move |n| if n % 2 == 0 { level } else { -level })
+    }
+
+    #[test]
+    fn should_skip_silence_and_count_it() {
+        let samples: Vec<f32> = sound(500, 0.0)
+            .chain(sound(500, 0.5))
+            .chain(sound(2000, 0.0))
+            .chain(sound(500, 0.5))
+            .chain(sound(500, 0.0))
+            .collect();
+        let trim = SilenceTrim {
+            threshold: 0.01,
+            max_pause: Some(Duration::from_millis(500)),
+        };
+        let time_saved = TimeSaved::default();
+
+        let source = SamplesBuffer::new(1, SAMPLE_RATE, samples);
+        let output: Vec<f32> = skip_silence(source, trim, time_saved.clone()).collect();
+
+        // the start and end are left out, the pause is shortened to the longest allowed
+        let expected: Vec<f32> = sound(500, 0.5)
+            .chain(sound(500, 0.0))
+            .chain(sound(500, 0.5))
+            .collect();
+        assert_eq!(output, expected);
+        assert_eq!(*time_saved.lock(), Duration::from_millis(2500));
+    }
+}
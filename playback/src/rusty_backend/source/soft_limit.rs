@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use super::Source;
+
+/// Samples above this level are compressed towards `1.0`
+const KNEE: f32 = 0.8;
+
+/// Internal function that builds a `SoftLimit` object.
+pub fn soft_limit<I>(input: I) -> SoftLimit<I>
+where
+    I: Source<Item = f32>,
+{
+    SoftLimit {
+        input,
+        enabled: false,
+    }
+}
+
+/// Filter that keeps amplified samples from clipping, by smoothly compressing the ones above
+/// the knee instead of cutting them off at `1.0`.
+#[derive(Clone, Debug)]
+pub struct SoftLimit<I> {
+    input: I,
+    enabled: bool,
+}
+
+#[allow(clippy::missing_const_for_fn, unused)]
+impl<I> SoftLimit<I> {
+    /// Enables or disables the limiter, disabled samples pass unchanged.
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+}
+
+/// Compress `sample` above the knee, with the same slope at the knee and approaching `1.0`
+#[inline]
+fn limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= KNEE {
+        return sample;
+    }
+    let headroom = 1.0 - KNEE;
+    (KNEE + headroom * ((level - KNEE) / headroom).tanh()).copysign(sample)
+}
+
+impl<I> Iterator for SoftLimit<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        Some(if self.enabled { limit(sample) } else { sample })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for SoftLimit<I> where I: Source<Item = f32> + ExactSizeIterator {}
+
+impl<I> Source for SoftLimit<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    #[inline]
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        self.input.seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn should_keep_samples_within_full_scale() {
+        // from -4.0 to 4.0, as with a volume of 400%
+        let samples: Vec<f32> = (-400..=400).map(|n| n as f32 / 100.0).collect();
+        let mut limit = soft_limit(SamplesBuffer::new(1, 44100, samples.clone()));
+        limit.set_enabled(true);
+
+        for (input, output) in samples.into_iter().zip(limit) {
+            assert!(output.abs() <= 1.0, "{input} was limited to {output}");
+            assert!(output.abs() <= input.abs());
+            if input.abs() <= KNEE {
+                assert!((output - input).abs() < f32::EPSILON);
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use super::Source;
+
+/// Length of the overlapping windows in milliseconds
+const WINDOW_MS: u32 = 40;
+/// How far a window may be moved to continue the previous one, in milliseconds
+const TOLERANCE_MS: u32 = 10;
+/// Only every n-th position and frame is compared in the coarse part of the search
+const COARSE_STRIDE: usize = 4;
+
+/// Internal function that builds a `TimeStretch` object.
+pub fn time_stretch<I>(input: I, factor: f32) -> TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    TimeStretch {
+        input,
+        factor,
+        active: false,
+        input_done: false,
+        channels: 0,
+        window_len: 0,
+        tolerance: 0,
+        window: Vec::new(),
+        buffer: Vec::new(),
+        position: 0.0,
+        continuation: None,
+        overlap: Vec::new(),
+        output: VecDeque::new(),
+    }
+}
+
+/// Filter that changes the speed of the sound without changing its pitch.
+///
+/// Uses WSOLA (waveform similarity overlap-add): windows of the input are taken at the speed
+/// `factor` and added with half overlap, each one moved slightly to best continue the previous one.
+/// The input is passed through unchanged until the factor is first set to something other than 1.
+#[derive(Clone, Debug)]
+pub struct TimeStretch<I> {
+    input: I,
+    factor: f32,
+    active: bool,
+    input_done: bool,
+    channels: usize,
+    /// frames per window, even
+    window_len: usize,
+    /// frames a window may be moved
+    tolerance: usize,
+    /// hann window of `window_len`
+    window: Vec<f32>,
+    /// interleaved input not used by a window yet
+    buffer: Vec<f32>,
+    /// ideal start frame of the next window in `buffer`, advancing by the factor
+    position: f64,
+    /// start frame in `buffer` of the input that continues the previous window
+    continuation: Option<usize>,
+    /// overlap-added output of the windows so far, `window_len` frames, empty once flushed
+    overlap: Vec<f32>,
+    /// finished samples
+    output: VecDeque<f32>,
+}
+
+impl<I> TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Modifies the speed factor.
+    #[inline]
+    pub fn set_factor(&mut self, factor: f32) {
+        self.factor = factor;
+    }
+
+    /// Start processing the input at its current format
+    #[allow(clippy::cast_precision_loss)]
+    fn activate(&mut self) {
+        let sample_rate = self.input.sample_rate().max(1);
+        self.channels = usize::from(self.input.channels().max(1));
+        self.window_len = (sample_rate * WINDOW_MS / 1000) as usize & !1;
+        self.tolerance = (sample_rate * TOLERANCE_MS / 1000) as usize;
+        self.window = (0..self.window_len)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / self.window_len as f32).cos())
+            .collect();
+        self.reset();
+        self.active = true;
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.position = 0.0;
+        self.continuation = None;
+        self.overlap = vec![0.0; self.window_len * self.channels];
+        self.output.clear();
+        self.input_done = false;
+    }
+
+    /// Read the input until `buffer` has `frames` frames or the input ended
+    fn fill(&mut self, frames: usize) {
+        let len = frames * self.channels;
+        while !self.input_done && self.buffer.len() < len {
+            match self.input.next() {
+                Some(sample) => self.buffer.push(sample),
+                None => self.input_done = true,
+            }
+        }
+    }
+
+    /// Get the sample at `index` in `buffer`, silence after the end of the input
+    #[inline]
+    fn sample(&self, index: usize) -> f32 {
+        self.buffer.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Get all channels of `frame` in `buffer` mixed together
+    #[inline]
+    fn mono(&self, frame: usize) -> f32 {
+        (frame * self.channels..(frame + 1) * self.channels)
+            .map(|index| self.sample(index))
+            .sum()
+    }
+
+    /// Correlation of the window at `start` with the `template` window, normalized by its energy
+    fn similarity(&self, template: usize, start: usize, stride: usize) -> f32 {
+        let hop = self.window_len / 2;
+        let mut correlation = 0.0;
+        let mut energy = 0.0;
+        for frame in (0..hop).step_by(stride) {
+            let a = self.mono(template + frame);
+            let b = self.mono(start + frame);
+            correlation += a * b;
+            energy += b * b;
+        }
+        if energy > 0.0 {
+            correlation / energy.sqrt()
+        } else {
+            0.0
+        }
+    }
+
+    /// Find the start between `from` and `to` that best continues the `template`
+    fn best_start(&self, template: usize, from: usize, to: usize) -> usize {
+        let search = |candidates: &mut dyn Iterator<Item = usize>, stride: usize| {
+            candidates
+                .map(|start| (start, self.similarity(template, start, stride)))
+                .fold((from, f32::MIN), |best, current| {
+                    if current.1 > best.1 {
+                        current
+                    } else {
+                        best
+                    }
+                })
+                .0
+        };
+
+        let coarse = search(&mut (from..=to).step_by(COARSE_STRIDE), COARSE_STRIDE);
+        let fine_from = coarse.saturating_sub(COARSE_STRIDE).max(from);
+        let fine_to = (coarse + COARSE_STRIDE).min(to);
+        search(&mut (fine_from..=fine_to), 1)
+    }
+
+    /// Add the next window to the output, returns `false` once the input is used up
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn process_window(&mut self) -> bool {
+        let hop = self.window_len / 2;
+        let ideal = self.position.round() as usize;
+        let from = ideal.saturating_sub(self.tolerance);
+        let to = ideal + self.tolerance;
+        self.fill(to.max(self.continuation.unwrap_or(0)) + self.window_len);
+
+        // the next window would only be silence after the end of the input
+        if self.input_done && ideal * self.channels >= self.buffer.len() {
+            if self.overlap.is_empty() {
+                return false;
+            }
+            // the fading out half of the last window
+            let overlap = std::mem::take(&mut self.overlap);
+            self.output.extend(&overlap[..hop * self.channels]);
+            return true;
+        }
+
+        let start = match self.continuation {
+            None => ideal,
+            // at normal speed the natural continuation is exactly the right window
+            Some(natural) if (self.factor - 1.0).abs() < f32::EPSILON => {
+                self.position = natural as f64;
+                natural
+            }
+            Some(natural) => self.best_start(natural, from, to),
+        };
+
+        for (frame, gain) in self.window.iter().enumerate() {
+            let offset = frame * self.channels;
+            let input = (start + frame) * self.channels;
+            for channel in 0..self.channels {
+                let value = self.sample(input + channel) * gain;
+                self.overlap[offset + channel] += value;
+            }
+        }
+        self.output
+            .extend(self.overlap.drain(..hop * self.channels));
+        self.overlap.resize(self.window_len * self.channels, 0.0);
+
+        self.position += hop as f64 * f64::from(self.factor);
+        // keep the input the next window and the continuation of this one need
+        let consumed = (start + hop).min((self.position as usize).saturating_sub(self.tolerance));
+        self.buffer
+            .drain(..(consumed * self.channels).min(self.buffer.len()));
+        self.position -= consumed as f64;
+        self.continuation = Some(start + hop - consumed);
+
+        true
+    }
+}
+
+impl<I> Iterator for TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if !self.active {
+            if (self.factor - 1.0).abs() < f32::EPSILON {
+                return self.input.next();
+            }
+            self.activate();
+        }
+
+        loop {
+            if let Some(sample) = self.output.pop_front() {
+                return Some(sample);
+            }
+            if !self.process_window() {
+                return None;
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.active {
+            (self.output.len(), None)
+        } else {
+            self.input.size_hint()
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+impl<I> Source for TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.active {
+            None
+        } else {
+            self.input.current_frame_len()
+        }
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input
+            .total_duration()
+            .map(|duration| duration.div_f32(self.factor))
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        // the buffered input is unrelated to the one after the seek
+        if self.active {
+            self.reset();
+        }
+        self.input.seek(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+    use pretty_assertions::assert_eq;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sine(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|n| 0.5 * (2.0 * PI * 440.0 * n as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn should_pass_through_at_normal_speed() {
+        let samples = sine(22050);
+        let source = SamplesBuffer::new(1, SAMPLE_RATE, samples.clone());
+        let stretched: Vec<f32> = time_stretch(source, 1.0).collect();
+        assert_eq!(stretched, samples);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn should_scale_length_by_factor() {
+        let samples = sine(88200);
+        // the end of the input is rounded to whole windows
+        let window = (SAMPLE_RATE * WINDOW_MS / 1000) as f32;
+        for factor in [0.5, 0.8, 1.5, 2.0] {
+            let source = SamplesBuffer::new(1, SAMPLE_RATE, samples.clone());
+            let len = time_stretch(source, factor).count() as f32;
+            let expected = samples.len() as f32 / factor;
+            assert!(
+                (len - expected).abs() <= window,
+                "{len} samples at factor {factor}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_clear_buffers_on_seek() {
+        let source = SamplesBuffer::new(2, SAMPLE_RATE, sine(88200));
+        let mut stretch = time_stretch(source, 1.5);
+        for _ in 0..1000 {
+            stretch.next();
+        }
+        assert!(!stretch.buffer.is_empty());
+        assert!(!stretch.output.is_empty());
+
+        stretch.seek(Duration::ZERO);
+        assert!(stretch.buffer.is_empty());
+        assert!(stretch.output.is_empty());
+        assert!(stretch.overlap.iter().all(|sample| *sample == 0.0));
+        assert_eq!(stretch.continuation, None);
+    }
+}
@@ -1,9 +1,11 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
 use std::{error, fmt};
 
 use super::decoder;
 use super::dynamic_mixer::{self, DynamicMixerController};
 use super::source::Source;
+use crate::OutputFormat;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Sample, SupportedStreamConfig};
 
@@ -14,6 +16,12 @@ use cpal::{Sample, SupportedStreamConfig};
 pub struct OutputStream {
     mixer: Arc<DynamicMixerController<f32>>,
     _stream: cpal::Stream,
+    /// set by the stream once its device is no longer available
+    device_lost: Arc<AtomicBool>,
+    /// format of the sound sent to the device
+    format: OutputFormat,
+    /// whether the stream was opened for the format of a track instead of the device default
+    native: bool,
 }
 
 /// More flexible handle to a `OutputStream` that provides playback.
@@ -40,11 +48,57 @@ impl OutputStream {
         device: &cpal::Device,
         config: SupportedStreamConfig,
     ) -> Result<(Self, OutputStreamHandle), StreamError> {
-        let (mixer, stream) = device.try_new_output_stream_config(config)?;
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let (mixer, stream, config) = device.try_new_output_stream_config(config, &device_lost)?;
+        Self::start(mixer, stream, device_lost, &config, false)
+    }
+
+    /// Return a new stream & handle on the output device called `name`, or the default device
+    /// for [`None`], playing sound with `sample_rate` and `channels` without converting it.
+    ///
+    /// Returns [`None`] if the device does not support that format.
+    pub fn try_native(
+        name: Option<&str>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Option<(Self, OutputStreamHandle)> {
+        let device = match name {
+            Some(name) => find_output_device(name)?,
+            None => cpal::default_host().default_output_device()?,
+        };
+        let Some(config) = native_output_config(&device, sample_rate, channels) else {
+            info!("output device does not support {sample_rate} Hz with {channels} channels");
+            return None;
+        };
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        device
+            .new_output_stream_with_format(config.clone(), &device_lost)
+            .map_err(StreamError::from)
+            .and_then(|(mixer, stream)| Self::start(mixer, stream, device_lost, &config, true))
+            .map_err(|err| warn!("error opening native output stream: {err}"))
+            .ok()
+    }
+
+    /// Start playing `stream` and create the handle for its `mixer`
+    fn start(
+        mixer: Arc<DynamicMixerController<f32>>,
+        stream: cpal::Stream,
+        device_lost: Arc<AtomicBool>,
+        config: &SupportedStreamConfig,
+        native: bool,
+    ) -> Result<(Self, OutputStreamHandle), StreamError> {
         stream.play()?;
         let out = Self {
             mixer,
             _stream: stream,
+            device_lost,
+            format: OutputFormat {
+                sample_rate: config.sample_rate().0,
+                channels: config.channels(),
+                sample_format: config.sample_format().to_string(),
+            },
+            native,
         };
         let handle = OutputStreamHandle {
             mixer: Arc::downgrade(&out.mixer),
@@ -73,6 +127,56 @@ impl OutputStream {
                 .ok_or(original_err)
         })
     }
+
+    /// Return a new stream & handle using the output device called `name`.
+    ///
+    /// Falls back to the default output device if there is no such device or it does not work.
+    pub fn try_from_name(name: &str) -> Result<(Self, OutputStreamHandle), StreamError> {
+        let Some(device) = find_output_device(name) else {
+            warn!("output device \"{name}\" not found, using the default device");
+            return Self::try_default();
+        };
+
+        Self::try_from_device(&device).or_else(|err| {
+            warn!("output device \"{name}\" failed: {err}, using the default device");
+            Self::try_default()
+        })
+    }
+
+    /// Returns true once the device of this stream is no longer available, for example unplugged.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::SeqCst)
+    }
+
+    /// Get the format of the sound sent to the device
+    pub fn format(&self) -> &OutputFormat {
+        &self.format
+    }
+
+    /// Returns true if the stream was opened for the format of a track by [`Self::try_native`].
+    pub fn is_native(&self) -> bool {
+        self.native
+    }
+}
+
+/// Find the output device called `name` on the default host
+fn find_output_device(name: &str) -> Option<cpal::Device> {
+    cpal::default_host()
+        .output_devices()
+        .ok()?
+        .find(|d| d.name().is_ok_and(|device_name| device_name == name))
+}
+
+/// Get the names of all output devices of the default host
+#[must_use]
+pub fn output_device_names() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            error!("error listing output devices: {e}");
+            Vec::new()
+        }
+    }
 }
 
 impl OutputStreamHandle {
@@ -194,12 +298,23 @@ pub(crate) trait CpalDeviceExt {
     fn new_output_stream_with_format(
         &self,
         format: cpal::SupportedStreamConfig,
+        device_lost: &Arc<AtomicBool>,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError>;
 
+    /// Like [`Self::new_output_stream_with_format`], but tries all other supported formats if
+    /// `config` does not work, also returns the format that was used
     fn try_new_output_stream_config(
         &self,
         config: cpal::SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError>;
+        device_lost: &Arc<AtomicBool>,
+    ) -> Result<
+        (
+            Arc<DynamicMixerController<f32>>,
+            cpal::Stream,
+            cpal::SupportedStreamConfig,
+        ),
+        StreamError,
+    >;
 }
 
 impl CpalDeviceExt for cpal::Device {
@@ -207,11 +322,18 @@ impl CpalDeviceExt for cpal::Device {
     fn new_output_stream_with_format(
         &self,
         format: cpal::SupportedStreamConfig,
+        device_lost: &Arc<AtomicBool>,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError> {
         let (mixer_tx, mut mixer_rx) =
             dynamic_mixer::mixer::<f32>(format.channels(), format.sample_rate().0);
 
-        let error_callback = |err| error!("an error occurred on output stream: {err}");
+        let device_lost = device_lost.clone();
+        let error_callback = move |err| {
+            error!("an error occurred on output stream: {err}");
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                device_lost.store(true, Ordering::SeqCst);
+            }
+        };
 
         match format.sample_format() {
             cpal::SampleFormat::F32 => self.build_output_stream::<f32, _, _>(
@@ -329,14 +451,28 @@ impl CpalDeviceExt for cpal::Device {
     fn try_new_output_stream_config(
         &self,
         config: SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError> {
-        self.new_output_stream_with_format(config).or_else(|err| {
-            // look through all supported formats to see if another works
-            supported_output_formats(self)?
-                .find_map(|format| self.new_output_stream_with_format(format).ok())
-                // return original error if nothing works
-                .ok_or(StreamError::BuildStreamError(err))
-        })
+        device_lost: &Arc<AtomicBool>,
+    ) -> Result<
+        (
+            Arc<DynamicMixerController<f32>>,
+            cpal::Stream,
+            cpal::SupportedStreamConfig,
+        ),
+        StreamError,
+    > {
+        self.new_output_stream_with_format(config.clone(), device_lost)
+            .map(|(mixer, stream)| (mixer, stream, config))
+            .or_else(|err| {
+                // look through all supported formats to see if another works
+                supported_output_formats(self)?
+                    .find_map(|format| {
+                        self.new_output_stream_with_format(format.clone(), device_lost)
+                            .ok()
+                            .map(|(mixer, stream)| (mixer, stream, format))
+                    })
+                    // return original error if nothing works
+                    .ok_or(StreamError::BuildStreamError(err))
+            })
     }
 }
 
@@ -360,3 +496,22 @@ fn supported_output_formats(
         formats
     }))
 }
+
+/// The supported output format of `device` playing `sample_rate` and `channels` exactly
+fn native_output_config(
+    device: &cpal::Device,
+    sample_rate: u32,
+    channels: u16,
+) -> Option<cpal::SupportedStreamConfig> {
+    let sample_rate = cpal::SampleRate(sample_rate);
+    device
+        .supported_output_configs()
+        .ok()?
+        .filter(|sf| {
+            sf.channels() == channels
+                && sf.min_sample_rate() <= sample_rate
+                && sample_rate <= sf.max_sample_rate()
+        })
+        .max_by(|a, b| a.cmp_default_heuristics(b))
+        .map(|sf| sf.with_sample_rate(sample_rate))
+}
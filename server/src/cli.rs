@@ -49,6 +49,9 @@ pub struct Args {
     pub max_depth: Option<usize>,
     #[arg(short, long, default_value_t = Backend::Default)]
     pub backend: Backend,
+    /// Use a separate named profile, with its own config, database, playlist and server.
+    #[arg(long, env = "TERMUSIC_PROFILE")]
+    pub profile: Option<String>,
     #[clap(flatten)]
     pub log_options: LogOptions,
 }
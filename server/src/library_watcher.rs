@@ -0,0 +1,262 @@
+//! Incremental updates of the library database from file system events.
+//!
+//! The music directories are watched recursively (inotify on linux). Events are collected for a
+//! short time, as copying an album causes many of them, then applied to the changed paths only.
+//! A full scan is only done at start, for the changes while the server was not running, and when
+//! the watcher lost events.
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use termusiclib::config::Settings;
+use termusiclib::sqlite::DataBase;
+use termusicplayback::{PlayerCmd, PlayerCmdSender, StreamTX, UpdateEvents};
+
+use crate::PlayerStats;
+
+/// Time events are collected after the first one, before they are applied
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Keeps the library database up to date while it lives
+pub struct LibraryWatcher {
+    _watcher: Option<RecommendedWatcher>,
+}
+
+/// Changes collected from a batch of events
+#[derive(Debug, Default)]
+struct Changes {
+    paths: BTreeSet<PathBuf>,
+    renames: Vec<(PathBuf, PathBuf)>,
+    rescan: bool,
+}
+
+impl LibraryWatcher {
+    /// Scan the music directories of `config` once, then watch them for changes
+    ///
+    /// Without a working watcher, for example when the inotify watch limit is reached, only the
+    /// first scan is done. The watched directories are reported in `playerstats` while watching.
+    pub fn start(
+        config: &Settings,
+        stream_tx: StreamTX,
+        cmd_tx: PlayerCmdSender,
+        playerstats: Arc<Mutex<PlayerStats>>,
+    ) -> Result<Self> {
+        let mut dirs: Vec<PathBuf> = config
+            .music_dirs()
+            .into_iter()
+            .filter(|dir| dir.is_dir())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        let (tx, rx) = mpsc::channel();
+        let watcher = match Self::watch(&dirs, tx) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                warn!("Error watching the music directories, changes need a rescan: {e}");
+                None
+            }
+        };
+
+        let watched_dirs = if watcher.is_some() {
+            dirs.iter()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let db = DataBase::new(config);
+        let analyze = config
+            .library_analyze_loudness
+            .then_some(config.library_loudness_write_tags);
+        let playerstats_loop = playerstats.clone();
+        std::thread::Builder::new()
+            .name("library watcher".into())
+            .spawn(move || {
+                watch_loop(db, &dirs, &rx, &stream_tx, &cmd_tx, analyze);
+                playerstats_loop.lock().watched_dirs.clear();
+            })?;
+        playerstats.lock().watched_dirs = watched_dirs;
+
+        Ok(Self { _watcher: watcher })
+    }
+
+    fn watch(
+        dirs: &[PathBuf],
+        tx: mpsc::Sender<notify::Result<Event>>,
+    ) -> notify::Result<RecommendedWatcher> {
+        let mut watcher = notify::recommended_watcher(move |res| {
+            // the receiving thread only stops with the server
+            let _ = tx.send(res);
+        })?;
+        // nested music directories are already watched with the outer one
+        for dir in dirs.iter().filter(|dir| {
+            !dirs
+                .iter()
+                .any(|other| other != *dir && dir.starts_with(other))
+        }) {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+            info!("watching music directory {}", dir.display());
+        }
+        Ok(watcher)
+    }
+}
+
+/// Apply the events of `rx` to `db` until the watcher is dropped
+///
+/// `analyze` is whether to write tags, if new tracks are analyzed for their loudness.
+fn watch_loop(
+    mut db: DataBase,
+    dirs: &[PathBuf],
+    rx: &Receiver<notify::Result<Event>>,
+    stream_tx: &StreamTX,
+    cmd_tx: &PlayerCmdSender,
+    analyze: Option<bool>,
+) {
+    rescan(&mut db, dirs);
+    UpdateEvents::LibraryChanged.send(stream_tx);
+    if let Some(write_tags) = analyze {
+        analyze_loudness(cmd_tx, dirs.iter(), write_tags);
+    }
+
+    while let Ok(first) = rx.recv() {
+        let mut changes = Changes::default();
+        changes.add(first);
+        let deadline = Instant::now() + DEBOUNCE;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(timeout) {
+                Ok(res) => changes.add(res),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        if changes.rescan {
+            rescan(&mut db, dirs);
+            UpdateEvents::LibraryChanged.send(stream_tx);
+            continue;
+        }
+        if changes.apply(&mut db, dirs) > 0 {
+            UpdateEvents::LibraryChanged.send(stream_tx);
+            if let Some(write_tags) = analyze {
+                let updated = changes.paths.iter().filter(|path| path.exists());
+                analyze_loudness(cmd_tx, updated, write_tags);
+            }
+        }
+    }
+    debug!("library watcher stopped");
+}
+
+impl Changes {
+    fn add(&mut self, res: notify::Result<Event>) {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Error from the library watcher, rescanning: {e}");
+                self.rescan = true;
+                return;
+            }
+        };
+        if event.need_rescan() {
+            warn!("The library watcher missed events, rescanning");
+            self.rescan = true;
+            return;
+        }
+
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let mut paths = event.paths.into_iter();
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.renames.push((from, to));
+                }
+            }
+            // a rename into or out of the music directories is a create or remove
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                self.paths.extend(event.paths);
+            }
+            // reading files does not change the library
+            _ => {}
+        }
+    }
+
+    /// Apply the changes to `db`, returning the number of changed tracks
+    fn apply(&self, db: &mut DataBase, dirs: &[PathBuf]) -> usize {
+        let mut changed = 0;
+        for (from, to) in &self.renames {
+            let result = match (music_dir_of(dirs, from), music_dir_of(dirs, to)) {
+                (Some(_), Some(root)) => db.rename_path(root, from, to),
+                (Some(root), None) => db.update_paths(root, &[from.clone()]),
+                (None, Some(root)) => db.update_paths(root, &[to.clone()]),
+                (None, None) => Ok(0),
+            };
+            match result {
+                Ok(count) => changed += count,
+                Err(e) => error!("Error renaming {} in the library: {e}", from.display()),
+            }
+        }
+
+        for dir in dirs {
+            let paths: Vec<PathBuf> = self
+                .paths
+                .iter()
+                .filter(|path| music_dir_of(dirs, path) == Some(dir.as_path()))
+                .cloned()
+                .collect();
+            if paths.is_empty() {
+                continue;
+            }
+            match db.update_paths(dir, &paths) {
+                Ok(count) => changed += count,
+                Err(e) => error!("Error updating {} in the library: {e}", dir.display()),
+            }
+        }
+        if changed > 0 {
+            info!("library updated, {changed} tracks changed");
+        }
+        changed
+    }
+}
+
+/// Get the music directory `path` is in, the innermost one if they are nested
+fn music_dir_of<'a>(dirs: &'a [PathBuf], path: &Path) -> Option<&'a Path> {
+    dirs.iter()
+        .filter(|dir| path.starts_with(dir))
+        .max_by_key(|dir| dir.components().count())
+        .map(PathBuf::as_path)
+}
+
+fn rescan(db: &mut DataBase, dirs: &[PathBuf]) {
+    for dir in dirs {
+        info!("scanning music directory {}", dir.display());
+        if let Err(e) = db.sync_database_blocking(dir) {
+            error!("Error scanning {}: {e}", dir.display());
+        }
+    }
+}
+
+/// Let the player analyze the loudness of the new tracks at or below `paths`
+fn analyze_loudness<'a>(
+    cmd_tx: &PlayerCmdSender,
+    paths: impl Iterator<Item = &'a PathBuf>,
+    write_tags: bool,
+) {
+    let paths: Vec<String> = paths
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    let cmd = PlayerCmd::AnalyzeLoudness {
+        paths,
+        write_tags,
+        force: false,
+    };
+    if cmd_tx.send(cmd).is_err() {
+        warn!("Could not start the loudness analysis, the player is already shutting down");
+    }
+}
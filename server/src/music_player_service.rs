@@ -1,16 +1,34 @@
 use anyhow::Result;
 use parking_lot::Mutex;
+use std::pin::Pin;
 use std::sync::Arc;
+use termusiclib::config::{
+    ChannelMixSettings, EqPreset, EqualizerSettings, Loop, BALANCE_MAX, EQ_BAND_COUNT,
+};
+use termusiclib::track::MAX_RATING;
 use termusicplayback::player::music_player_server::MusicPlayer;
 use termusicplayback::player::{
-    CycleLoopReply, CycleLoopRequest, EmptyReply, GetProgressRequest, GetProgressResponse,
-    PlaySelectedRequest, PlayerTime, ReloadConfigRequest, ReloadPlaylistRequest,
-    SeekBackwardRequest, SeekForwardRequest, SkipNextRequest, SkipNextResponse,
-    SkipPreviousRequest, SpeedDownRequest, SpeedReply, SpeedUpRequest, ToggleGaplessReply,
-    ToggleGaplessRequest, TogglePauseRequest, TogglePauseResponse, VolumeDownRequest, VolumeReply,
-    VolumeUpRequest,
+    AnalyzeLoudnessRequest, ChannelMixReply, CurrentTrackReply, CycleLoopReply, CycleLoopRequest,
+    EmptyReply, EqualizerReply, GetChannelMixRequest, GetCurrentTrackRequest, GetEqualizerRequest,
+    GetLibraryWatchRequest, GetPlaylistRequest, GetProgressRequest, GetProgressResponse,
+    LibraryWatchReply, ListOutputDevicesRequest, LoopModeReply, OutputDevicesReply,
+    PlayIndexRequest, PlaySelectedRequest, PlayUriRequest, PlayerTime, PlaylistAddTracksRequest,
+    PlaylistClearRequest, PlaylistMoveTrackRequest, PlaylistRemoveTracksRequest,
+    PlaylistShuffleRequest, PlaylistSwapTracksRequest, PlaylistTracks, RateCurrentTrackRequest,
+    ReloadConfigRequest, ReloadPlaylistRequest, SeekBackwardRequest, SeekForwardRequest,
+    SeekRelativeRequest, SeekRequest, SetChannelMixRequest, SetEqualizerRequest, SetGaplessRequest,
+    SetLoopModeRequest, SetOutputDeviceRequest, SetSpeedRequest, SetVolumeRequest, SkipNextRequest,
+    SkipNextResponse, SkipPreviousRequest, SpeedDownRequest, SpeedReply, SpeedUpRequest,
+    StreamUpdates, SubscribeServerUpdatesRequest, ToggleGaplessReply, ToggleGaplessRequest,
+    TogglePauseRequest, TogglePauseResponse, VolumeDownRequest, VolumeReply, VolumeUpRequest,
+};
+use termusicplayback::{
+    speed_from_fields, speed_to_tenths, EnqueueMode, PlayerCmd, PlayerCmdSender, StreamTX,
+    UpdateEvents,
 };
-use termusicplayback::{PlayerCmd, PlayerCmdSender};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 use tonic::{Request, Response, Status};
 
 use crate::PlayerStats;
@@ -18,15 +36,17 @@ use crate::PlayerStats;
 #[derive(Debug)]
 pub struct MusicPlayerService {
     cmd_tx: PlayerCmdSender,
+    stream_tx: StreamTX,
     pub(crate) player_stats: Arc<Mutex<PlayerStats>>,
 }
 
 impl MusicPlayerService {
-    pub fn new(cmd_tx: PlayerCmdSender) -> Self {
+    pub fn new(cmd_tx: PlayerCmdSender, stream_tx: StreamTX) -> Self {
         let player_stats = Arc::new(Mutex::new(PlayerStats::new()));
 
         Self {
             cmd_tx,
+            stream_tx,
             player_stats,
         }
     }
@@ -38,10 +58,28 @@ impl MusicPlayerService {
             error!("error {cmd:?}: {e}");
         }
     }
+
+    /// Check that all `indexes` are within the current playlist
+    fn check_playlist_indexes(&self, indexes: &[u32]) -> Result<(), Status> {
+        let len = self.player_stats.lock().playlist_tracks.len();
+        for index in indexes {
+            if *index as usize >= len {
+                return Err(Status::invalid_argument(format!(
+                    "index {index} is out of bounds for playlist of length {len}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
+type StreamUpdatesStream = Pin<Box<dyn Stream<Item = Result<StreamUpdates, Status>> + Send>>;
+
 #[tonic::async_trait]
 impl MusicPlayer for MusicPlayerService {
+    type SubscribeServerUpdatesStream = StreamUpdatesStream;
+
     async fn cycle_loop(
         &self,
         _request: Request<CycleLoopRequest>,
@@ -64,6 +102,18 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(reply))
     }
 
+    async fn get_current_track(
+        &self,
+        _request: Request<GetCurrentTrackRequest>,
+    ) -> Result<Response<CurrentTrackReply>, Status> {
+        let r = self.player_stats.lock();
+        let reply = CurrentTrackReply {
+            track: r.current_track.clone(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
     async fn play_selected(
         &self,
         _request: Request<PlaySelectedRequest>,
@@ -74,6 +124,88 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(reply))
     }
 
+    async fn play_index(
+        &self,
+        request: Request<PlayIndexRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let index = request.into_inner().index;
+        self.check_playlist_indexes(&[index])?;
+        self.command(&PlayerCmd::PlayIndex(index as usize));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn play_uri(
+        &self,
+        request: Request<PlayUriRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let PlayUriRequest { uri, enqueue_mode } = request.into_inner();
+        if uri.is_empty() {
+            return Err(Status::invalid_argument("missing uri"));
+        }
+        let mode = EnqueueMode::from_u32(enqueue_mode).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown enqueue mode {enqueue_mode}"))
+        })?;
+        self.command(&PlayerCmd::PlayUri { uri, mode });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn analyze_loudness(
+        &self,
+        request: Request<AnalyzeLoudnessRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let AnalyzeLoudnessRequest {
+            paths,
+            write_tags,
+            force,
+        } = request.into_inner();
+        if let Some(path) = paths
+            .iter()
+            .find(|v| !std::path::Path::new(v).is_absolute())
+        {
+            return Err(Status::invalid_argument(format!(
+                "path \"{path}\" is not absolute"
+            )));
+        }
+        self.command(&PlayerCmd::AnalyzeLoudness {
+            paths,
+            write_tags,
+            force,
+        });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn get_library_watch(
+        &self,
+        _request: Request<GetLibraryWatchRequest>,
+    ) -> Result<Response<LibraryWatchReply>, Status> {
+        let reply = LibraryWatchReply {
+            dirs: self.player_stats.lock().watched_dirs.clone(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn rate_current_track(
+        &self,
+        request: Request<RateCurrentTrackRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let rating = request.into_inner().rating;
+        let rating = u8::try_from(rating)
+            .ok()
+            .filter(|rating| *rating <= MAX_RATING)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "rating {rating} is not between 0 and {MAX_RATING}"
+                ))
+            })?;
+        self.command(&PlayerCmd::RateCurrentTrack(rating));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
     async fn reload_config(
         &self,
         _request: Request<ReloadConfigRequest>,
@@ -148,7 +280,10 @@ impl MusicPlayer for MusicPlayerService {
         // This is to let the player update volume within loop
         std::thread::sleep(std::time::Duration::from_millis(20));
         let s = self.player_stats.lock();
-        let reply = SpeedReply { speed: s.speed };
+        let reply = SpeedReply {
+            speed: speed_to_tenths(s.speed),
+            speed_percent: s.speed,
+        };
 
         Ok(Response::new(reply))
     }
@@ -161,7 +296,10 @@ impl MusicPlayer for MusicPlayerService {
         // This is to let the player update volume within loop
         std::thread::sleep(std::time::Duration::from_millis(20));
         let s = self.player_stats.lock();
-        let reply = SpeedReply { speed: s.speed };
+        let reply = SpeedReply {
+            speed: speed_to_tenths(s.speed),
+            speed_percent: s.speed,
+        };
 
         Ok(Response::new(reply))
     }
@@ -220,4 +358,317 @@ impl MusicPlayer for MusicPlayerService {
 
         Ok(Response::new(reply))
     }
+
+    async fn seek(&self, request: Request<SeekRequest>) -> Result<Response<PlayerTime>, Status> {
+        let position = request
+            .into_inner()
+            .position
+            .ok_or_else(|| Status::invalid_argument("missing position"))?;
+        self.command(&PlayerCmd::SeekTo(position.into()));
+        // This is to let the player update progress within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = s.as_playertime();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn seek_relative(
+        &self,
+        request: Request<SeekRelativeRequest>,
+    ) -> Result<Response<PlayerTime>, Status> {
+        self.command(&PlayerCmd::SeekRelative(request.into_inner().offset));
+        // This is to let the player update progress within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = s.as_playertime();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_volume(
+        &self,
+        request: Request<SetVolumeRequest>,
+    ) -> Result<Response<VolumeReply>, Status> {
+        // clamped to u16::MAX, the backends clamp it further
+        #[allow(clippy::cast_possible_truncation)]
+        let volume = request.into_inner().volume.min(u32::from(u16::MAX)) as u16;
+        self.command(&PlayerCmd::SetVolume(volume));
+        // This is to let the player update volume within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let r = self.player_stats.lock();
+        let reply = VolumeReply {
+            volume: u32::from(r.volume),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_speed(
+        &self,
+        request: Request<SetSpeedRequest>,
+    ) -> Result<Response<SpeedReply>, Status> {
+        let request = request.into_inner();
+        let speed = speed_from_fields(request.speed_percent, request.speed);
+        self.command(&PlayerCmd::SetSpeed(speed));
+        // This is to let the player update speed within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = SpeedReply {
+            speed: speed_to_tenths(s.speed),
+            speed_percent: s.speed,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_loop_mode(
+        &self,
+        request: Request<SetLoopModeRequest>,
+    ) -> Result<Response<LoopModeReply>, Status> {
+        let loop_mode = request.into_inner().loop_mode;
+        let loop_mode = Loop::from_u32(loop_mode)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown loop mode {loop_mode}")))?;
+        self.command(&PlayerCmd::SetLoopMode(loop_mode));
+        // This is to let the player update loop mode within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = LoopModeReply {
+            loop_mode: s.loop_mode.as_u32(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn get_equalizer(
+        &self,
+        _request: Request<GetEqualizerRequest>,
+    ) -> Result<Response<EqualizerReply>, Status> {
+        let reply = EqualizerReply::from(&self.player_stats.lock().equalizer);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_equalizer(
+        &self,
+        request: Request<SetEqualizerRequest>,
+    ) -> Result<Response<EqualizerReply>, Status> {
+        let request = request.into_inner();
+        let preset = EqPreset::from_u32(request.preset).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown equalizer preset {}", request.preset))
+        })?;
+        let custom_gains = if request.custom_gains.is_empty() {
+            self.player_stats.lock().equalizer.custom_gains
+        } else {
+            request.custom_gains.try_into().map_err(|_| {
+                Status::invalid_argument(format!("expected {EQ_BAND_COUNT} custom gains"))
+            })?
+        };
+        self.command(&PlayerCmd::SetEqualizer(EqualizerSettings {
+            enabled: request.enabled,
+            preset,
+            custom_gains,
+        }));
+        // This is to let the player update the equalizer within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let reply = EqualizerReply::from(&self.player_stats.lock().equalizer);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn get_channel_mix(
+        &self,
+        _request: Request<GetChannelMixRequest>,
+    ) -> Result<Response<ChannelMixReply>, Status> {
+        let reply = ChannelMixReply::from(self.player_stats.lock().channel_mix);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_channel_mix(
+        &self,
+        request: Request<SetChannelMixRequest>,
+    ) -> Result<Response<ChannelMixReply>, Status> {
+        let request = request.into_inner();
+        let balance = i16::try_from(request.balance)
+            .ok()
+            .filter(|balance| balance.abs() <= BALANCE_MAX)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "balance {} is not between -{BALANCE_MAX} and {BALANCE_MAX}",
+                    request.balance
+                ))
+            })?;
+        self.command(&PlayerCmd::SetChannelMix(ChannelMixSettings {
+            mono: request.mono,
+            balance,
+        }));
+        // This is to let the player update the channel mix within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let reply = ChannelMixReply::from(self.player_stats.lock().channel_mix);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn list_output_devices(
+        &self,
+        _request: Request<ListOutputDevicesRequest>,
+    ) -> Result<Response<OutputDevicesReply>, Status> {
+        let reply = OutputDevicesReply {
+            devices: output_devices(),
+            current: self.player_stats.lock().output_device.clone(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_output_device(
+        &self,
+        request: Request<SetOutputDeviceRequest>,
+    ) -> Result<Response<OutputDevicesReply>, Status> {
+        let name = request.into_inner().name;
+        let devices = output_devices();
+        if !name.is_empty() && !devices.contains(&name) {
+            return Err(Status::not_found(format!(
+                "unknown output device \"{name}\""
+            )));
+        }
+        self.command(&PlayerCmd::SetOutputDevice(name));
+        // This is to let the player update the output device within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let reply = OutputDevicesReply {
+            devices,
+            current: self.player_stats.lock().output_device.clone(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_gapless(
+        &self,
+        request: Request<SetGaplessRequest>,
+    ) -> Result<Response<ToggleGaplessReply>, Status> {
+        self.command(&PlayerCmd::SetGapless(request.into_inner().gapless));
+        // This is to let the player update gapless within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let r = self.player_stats.lock();
+        let reply = ToggleGaplessReply { gapless: r.gapless };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn subscribe_server_updates(
+        &self,
+        _request: Request<SubscribeServerUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeServerUpdatesStream>, Status> {
+        info!("new client subscribed to server updates");
+        let rx = self.stream_tx.subscribe();
+
+        // a lagging client gets told how many events it missed, instead of being disconnected
+        let stream = BroadcastStream::new(rx).map(|res| match res {
+            Ok(ev) => Ok(ev.into()),
+            Err(BroadcastStreamRecvError::Lagged(amount)) => {
+                Ok(UpdateEvents::MissedEvents { amount }.into())
+            }
+        });
+
+        Ok(Response::new(
+            Box::pin(stream) as Self::SubscribeServerUpdatesStream
+        ))
+    }
+
+    async fn playlist_add_tracks(
+        &self,
+        request: Request<PlaylistAddTracksRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.command(&PlayerCmd::PlaylistAddTracks {
+            tracks: request.tracks,
+            at_index: request.at_index.map(|v| v.index as usize),
+        });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn playlist_remove_tracks(
+        &self,
+        request: Request<PlaylistRemoveTracksRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.check_playlist_indexes(&[request.at_index])?;
+        self.command(&PlayerCmd::PlaylistRemoveTracks {
+            at_index: request.at_index as usize,
+            amount: request.amount as usize,
+        });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn playlist_move_track(
+        &self,
+        request: Request<PlaylistMoveTrackRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.check_playlist_indexes(&[request.from_index, request.to_index])?;
+        self.command(&PlayerCmd::PlaylistMoveTrack {
+            from: request.from_index as usize,
+            to: request.to_index as usize,
+        });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn playlist_swap_tracks(
+        &self,
+        request: Request<PlaylistSwapTracksRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.check_playlist_indexes(&[request.index_a, request.index_b])?;
+        self.command(&PlayerCmd::PlaylistSwapTracks {
+            index_a: request.index_a as usize,
+            index_b: request.index_b as usize,
+        });
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn playlist_clear(
+        &self,
+        _request: Request<PlaylistClearRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistClear);
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn playlist_shuffle(
+        &self,
+        _request: Request<PlaylistShuffleRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistShuffle);
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn get_playlist(
+        &self,
+        _request: Request<GetPlaylistRequest>,
+    ) -> Result<Response<PlaylistTracks>, Status> {
+        let r = self.player_stats.lock();
+        let reply = PlaylistTracks {
+            current_track_index: r.current_track_index,
+            tracks: r.playlist_tracks.clone(),
+        };
+
+        Ok(Response::new(reply))
+    }
+}
+
+/// Get the names of all output devices, which only the rusty backend supports
+#[allow(clippy::needless_return)]
+fn output_devices() -> Vec<String> {
+    #[cfg(feature = "rusty")]
+    return termusicplayback::output_device_names();
+    #[cfg(not(feature = "rusty"))]
+    Vec::new()
 }
@@ -1,20 +1,30 @@
 mod cli;
+mod library_watcher;
 mod logger;
 mod music_player_service;
 
 use std::path::Path;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
+use library_watcher::LibraryWatcher;
 use music_player_service::MusicPlayerService;
-use termusiclib::config::Settings;
+use parking_lot::Mutex;
+use termusiclib::config::{ChannelMixSettings, EqualizerSettings, Loop, PlayerProtocol, Settings};
 use termusiclib::track::MediaType;
+use termusiclib::utils;
+use termusicplayback::auth::{self, ServerAuthInterceptor};
 use termusicplayback::player::music_player_server::MusicPlayerServer;
-use termusicplayback::player::{GetProgressResponse, PlayerTime};
+use termusicplayback::player::{GetProgressResponse, PlayerTime, TrackInfo};
 use termusicplayback::{
-    Backend, GeneralPlayer, PlayerCmd, PlayerCmdSender, PlayerProgress, PlayerTrait, Status,
+    speed_to_tenths, Backend, GeneralPlayer, OutputFormat, PlayerCmd, PlayerCmdSender,
+    PlayerProgress, PlayerTrait, Playlist, Status, StreamTX, UpdateEvents,
 };
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
 use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server;
 
@@ -32,8 +42,22 @@ struct PlayerStats {
     pub volume: u16,
     pub speed: i32,
     pub gapless: bool,
+    pub loop_mode: Loop,
+    pub equalizer: EqualizerSettings,
+    pub channel_mix: ChannelMixSettings,
+    /// Configured output device, empty for the default device
+    pub output_device: String,
     pub current_track_updated: bool,
     pub radio_title: String,
+    pub current_track: Option<TrackInfo>,
+    /// Total length of the silence left out
+    pub time_saved: Duration,
+    /// Format the sound is sent to the output device in, if the backend reports it
+    pub output_format: Option<OutputFormat>,
+    /// Files / urls of all tracks in the playlist, in order
+    pub playlist_tracks: Vec<String>,
+    /// Music directories the library watcher watches, empty if it is not running
+    pub watched_dirs: Vec<String>,
 }
 
 impl PlayerStats {
@@ -46,23 +70,48 @@ impl PlayerStats {
             current_track_index: 0,
             status: 1,
             volume: 0,
-            speed: 10,
+            speed: 100,
             gapless: true,
+            loop_mode: Loop::default(),
+            equalizer: EqualizerSettings::default(),
+            channel_mix: ChannelMixSettings::default(),
+            output_device: String::new(),
             current_track_updated: false,
             radio_title: String::new(),
+            current_track: None,
+            time_saved: Duration::ZERO,
+            output_format: None,
+            playlist_tracks: Vec::new(),
+            watched_dirs: Vec::new(),
         }
     }
 
+    /// Update the playlist snapshot from the server-owned [`Playlist`]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn update_playlist(&mut self, playlist: &Playlist) {
+        self.current_track_index = playlist.get_current_track_index() as u32;
+        self.playlist_tracks = playlist
+            .tracks()
+            .iter()
+            .filter_map(|track| track.file().map(ToString::to_string))
+            .collect();
+    }
+
     pub fn as_getprogress_response(&self) -> GetProgressResponse {
         GetProgressResponse {
             progress: Some(self.as_playertime()),
             current_track_index: self.current_track_index,
             status: self.status,
             volume: u32::from(self.volume),
-            speed: self.speed,
+            speed: speed_to_tenths(self.speed),
             gapless: self.gapless,
             current_track_updated: self.current_track_updated,
             radio_title: self.radio_title.clone(),
+            loop_mode: self.loop_mode.as_u32(),
+            current_track: self.current_track.clone(),
+            speed_percent: self.speed,
+            time_saved: Some(self.time_saved.into()),
+            output_format: self.output_format.clone().map(Into::into),
         }
     }
 
@@ -86,10 +135,16 @@ async fn actual_main() -> Result<()> {
     let args = cli::Args::parse();
     let _ = logger::setup(&args);
     info!("background thread start");
+    if let Some(profile) = &args.profile {
+        utils::set_profile(profile)?;
+        info!("using profile \"{profile}\"");
+    }
 
     let (cmd_tx, cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (stream_tx, _) = termusicplayback::events::stream_channel();
 
-    let music_player_service: MusicPlayerService = MusicPlayerService::new(cmd_tx.clone());
+    let music_player_service: MusicPlayerService =
+        MusicPlayerService::new(cmd_tx.clone(), stream_tx.clone());
     let mut config = get_config(&args)?;
     let playerstats = music_player_service.player_stats.clone();
 
@@ -97,52 +152,100 @@ async fn actual_main() -> Result<()> {
     let cmd_tx_ticker = cmd_tx.clone();
 
     ctrlc::set_handler(move || {
-        cmd_tx_ctrlc
-            .send(PlayerCmd::Quit)
-            .expect("Could not send signal on channel.");
+        // the player may already be shutting down, in which case the channel is closed
+        if cmd_tx_ctrlc.send(PlayerCmd::Quit).is_err() {
+            warn!("Could not send quit signal, the player is already shutting down");
+        }
     })
     .expect("Error setting Ctrl-C handler");
 
-    let addr = std::net::SocketAddr::new(config.player_interface, config.player_port);
+    // create the token before listening, so that clients can read it once connected
+    let token = if config.player_use_token {
+        Some(auth::get_or_create_token()?)
+    } else {
+        warn!("Token authentication is disabled, anyone able to connect can control the player");
+        None
+    };
+    let incoming = bind_incoming(&config).await?;
+    #[cfg(unix)]
+    let uds_path = (config.player_protocol == PlayerProtocol::Uds)
+        .then(|| config.get_uds_path())
+        .transpose()?;
+    // let clients of this profile find this server, only written once listening
+    let pid_path = utils::get_server_pid_path()?;
+    std::fs::write(&pid_path, std::process::id().to_string())
+        .with_context(|| format!("Error writing pid file {}", pid_path.display()))?;
+
+    // keeps the library database up to date for as long as the server runs
+    let _library_watcher = if config.library_watch {
+        LibraryWatcher::start(
+            &config,
+            stream_tx.clone(),
+            cmd_tx.clone(),
+            playerstats.clone(),
+        )
+        .map_err(|e| error!("Error starting the library watcher: {e:#}"))
+        .ok()
+    } else {
+        None
+    };
 
-    // workaround to print address once sever "actually" is started and address is known
-    // see https://github.com/hyperium/tonic/issues/351
-    let tcp_listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .with_context(|| format!("Error binding address: {}", addr))?;
-    info!("Server listening on {}", tcp_listener.local_addr().unwrap());
-    let tcp_stream =
-        TcpIncoming::from_listener(tcp_listener, true, None).map_err(|e| anyhow::anyhow!(e))?;
+    // notified once the player received a quit or stopped, to stop accepting new requests
+    let shutdown = Arc::new(tokio::sync::Notify::new());
+    let shutdown_player = shutdown.clone();
 
     let player_handle = tokio::task::spawn_blocking(move || -> Result<()> {
         let mut player = GeneralPlayer::new_backend(args.backend.into(), &config, cmd_tx.clone())?;
+        {
+            let mut p_tick = playerstats.lock();
+            p_tick.update_playlist(&player.playlist);
+            p_tick.loop_mode = player.playlist.loop_mode();
+            p_tick.equalizer = config.player_equalizer.clone();
+            p_tick.channel_mix = config.player_channel_mix;
+            p_tick.output_device = config.player_output_device.clone();
+        }
+        let autosave_interval = Duration::from_secs(config.player_autosave_interval_secs);
+        let mut last_autosave = Instant::now();
+        let mut last_saved_config = volatile_config(&config);
+        let mut quitting = false;
+        let analysis_running = Arc::new(AtomicBool::new(false));
         // move "cmd_rx" and change to be mutable
         let mut cmd_rx = cmd_rx;
         while let Some(cmd) = cmd_rx.blocking_recv() {
+            // still process commands sent before the quit, but nothing periodic
+            if quitting && matches!(cmd, PlayerCmd::Tick | PlayerCmd::Quit) {
+                continue;
+            }
             #[allow(unreachable_patterns)]
             match cmd {
                 PlayerCmd::AboutToFinish => {
                     info!("about to finish signal received");
                     if !player.playlist.is_empty()
                         && !player.playlist.has_next_track()
-                        && player.config.player_gapless
+                        && (player.config.player_gapless
+                            || player.config.player_crossfade.duration().is_some())
                     {
                         player.enqueue_next_from_playlist();
                     }
                 }
+                PlayerCmd::AnalyzeLoudness {
+                    paths,
+                    write_tags,
+                    force,
+                } => {
+                    analyze_loudness(&config, &analysis_running, paths, write_tags, force);
+                }
                 PlayerCmd::Quit => {
                     info!("PlayerCmd::Quit received");
-                    player.player_save_last_position();
-                    if let Err(e) = player.playlist.save() {
-                        error!("error when saving playlist: {e}");
-                    };
-                    if let Err(e) = config.save() {
-                        error!("error when saving config: {e}");
-                    };
-                    std::process::exit(0);
+                    quitting = true;
+                    shutdown_player.notify_one();
+                    // no new commands are accepted, the ones already buffered are drained before saving
+                    cmd_rx.close();
                 }
                 PlayerCmd::CycleLoop => {
                     config.player_loop_mode = player.playlist.cycle_loop_mode();
+                    player.apply_replay_gain();
+                    loop_mode_changed(config.player_loop_mode, &playerstats, &stream_tx);
                 }
                 PlayerCmd::Eos => {
                     info!("Eos received");
@@ -154,6 +257,7 @@ async fn actual_main() -> Result<()> {
                         "current track index: {:?}",
                         player.playlist.get_current_track_index()
                     );
+                    player.count_end();
                     player.playlist.clear_current_track();
                     player.start_play();
                     debug!(
@@ -168,6 +272,30 @@ async fn actual_main() -> Result<()> {
                     player.playlist.proceed_false();
                     player.next();
                 }
+                PlayerCmd::PlayIndex(index) => {
+                    info!("play index {index}");
+                    if index < player.playlist.len() {
+                        player.play_index(index);
+                    } else {
+                        error!(
+                            "index {index} is out of bounds for playlist of length {}",
+                            player.playlist.len()
+                        );
+                    }
+                }
+                PlayerCmd::RateCurrentTrack(rating) => {
+                    info!("rate current track with {rating}");
+                    if let Err(e) = player.rate_current_track(rating) {
+                        error!("error rating current track: {e:#}");
+                    }
+                }
+                PlayerCmd::PlayUri { uri, mode } => {
+                    info!("play uri {uri} with {mode:?}");
+                    match player.play_uri(&uri, mode) {
+                        Ok(()) => playlist_changed(&player.playlist, &playerstats, &stream_tx),
+                        Err(e) => error!("error playing uri {uri}: {e:#}"),
+                    }
+                }
                 PlayerCmd::SkipPrevious => {
                     info!("skip to previous track");
                     player.player_save_last_position();
@@ -177,47 +305,178 @@ async fn actual_main() -> Result<()> {
                     config.load()?;
                     info!("config reloaded");
                     player.config = config.clone();
+                    player.set_equalizer(config.player_equalizer.clone());
+                    player.apply_replay_gain();
+                    player.set_crossfade(config.player_crossfade);
+                    player.set_preserve_pitch(config.player_preserve_pitch);
+                    player.set_silence(config.player_silence);
+                    player.set_output_device(config.player_output_device.clone());
+                    player.set_native_output(config.player_native_output);
+                    player.set_volume_curve(config.player_volume_curve, config.volume_max());
+                    player.set_channel_mix(config.player_channel_mix);
+                    let mut p_tick = playerstats.lock();
+                    p_tick.equalizer = config.player_equalizer.clone();
+                    p_tick.channel_mix = config.player_channel_mix;
+                    p_tick.output_device = config.player_output_device.clone();
                 }
                 PlayerCmd::ReloadPlaylist => {
                     player.playlist.reload_tracks().ok();
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistAddTracks { tracks, at_index } => {
+                    match Playlist::tracks_from_uris(&tracks) {
+                        Ok(tracks) => player.playlist.add_tracks(tracks, at_index),
+                        Err(e) => error!("error when adding tracks to playlist: {e}"),
+                    }
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistRemoveTracks { at_index, amount } => {
+                    player.playlist.remove_range(at_index, amount);
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistMoveTrack { from, to } => {
+                    player.playlist.move_track(from, to);
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistSwapTracks { index_a, index_b } => {
+                    player.playlist.swap(index_a, index_b);
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistClear => {
+                    player.playlist.clear();
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
+                }
+                PlayerCmd::PlaylistShuffle => {
+                    player.playlist.shuffle();
+                    playlist_changed(&player.playlist, &playerstats, &stream_tx);
                 }
                 PlayerCmd::SeekBackward => {
                     player.seek_relative(false);
                     let mut p_tick = playerstats.lock();
                     p_tick.progress = player.get_progress();
+                    UpdateEvents::Progress(p_tick.progress).send(&stream_tx);
                 }
                 PlayerCmd::SeekForward => {
                     player.seek_relative(true);
                     let mut p_tick = playerstats.lock();
                     p_tick.progress = player.get_progress();
+                    UpdateEvents::Progress(p_tick.progress).send(&stream_tx);
+                }
+                PlayerCmd::SeekRelative(offset) => {
+                    if let Err(err) = player.seek(offset) {
+                        error!("Error seeking by {offset} seconds: {err:#}");
+                    }
+                    let mut p_tick = playerstats.lock();
+                    p_tick.progress = player.get_progress();
+                    UpdateEvents::Progress(p_tick.progress).send(&stream_tx);
+                }
+                PlayerCmd::SeekTo(position) => {
+                    player.seek_to(position);
+                    let mut p_tick = playerstats.lock();
+                    p_tick.progress = player.get_progress();
+                    UpdateEvents::Progress(p_tick.progress).send(&stream_tx);
+                }
+                PlayerCmd::SetChannelMix(channel_mix) => {
+                    player.set_channel_mix(channel_mix);
+                    playerstats.lock().channel_mix = channel_mix;
+                    config.player_channel_mix = channel_mix;
+                }
+                PlayerCmd::SetEqualizer(equalizer) => {
+                    player.set_equalizer(equalizer.clone());
+                    playerstats.lock().equalizer = equalizer.clone();
+                    config.player_equalizer = equalizer;
+                }
+                PlayerCmd::SetOutputDevice(name) => {
+                    info!("switching output device to \"{name}\"");
+                    player.set_output_device(name.clone());
+                    playerstats.lock().output_device = name.clone();
+                    config.player_output_device = name;
+                }
+                PlayerCmd::SetGapless(gapless) => {
+                    player.set_gapless(gapless);
+                    config.player_gapless = gapless;
+                    let mut p_tick = playerstats.lock();
+                    p_tick.gapless = gapless;
+                    UpdateEvents::GaplessChanged { gapless }.send(&stream_tx);
+                }
+                PlayerCmd::SetLoopMode(loop_mode) => {
+                    player.playlist.set_loop_mode(loop_mode);
+                    player.apply_replay_gain();
+                    config.player_loop_mode = loop_mode;
+                    loop_mode_changed(loop_mode, &playerstats, &stream_tx);
+                }
+                PlayerCmd::SetSpeed(speed) => {
+                    player.set_speed(speed);
+                    info!("after set speed: {}", player.speed());
+                    config.player_speed_percent = player.speed();
+                    let mut p_tick = playerstats.lock();
+                    p_tick.speed = config.player_speed_percent;
+                    UpdateEvents::SpeedChanged {
+                        speed: config.player_speed_percent,
+                    }
+                    .send(&stream_tx);
+                }
+                PlayerCmd::SetVolume(volume) => {
+                    player.set_volume(volume);
+                    let new_volume = player.volume();
+                    config.player_volume = new_volume;
+                    info!("after set volume: {}", new_volume);
+                    let mut p_tick = playerstats.lock();
+                    p_tick.volume = new_volume;
+                    UpdateEvents::VolumeChanged { volume: new_volume }.send(&stream_tx);
                 }
                 PlayerCmd::SkipNext => {
                     info!("skip to next track.");
                     player.player_save_last_position();
+                    player.count_skip();
                     player.next();
                 }
                 PlayerCmd::SpeedDown => {
                     player.speed_down();
                     info!("after speed down: {}", player.speed());
-                    config.player_speed = player.speed();
+                    config.player_speed_percent = player.speed();
                     let mut p_tick = playerstats.lock();
-                    p_tick.speed = config.player_speed;
+                    p_tick.speed = config.player_speed_percent;
+                    UpdateEvents::SpeedChanged {
+                        speed: config.player_speed_percent,
+                    }
+                    .send(&stream_tx);
                 }
 
                 PlayerCmd::SpeedUp => {
                     player.speed_up();
                     info!("after speed up: {}", player.speed());
-                    config.player_speed = player.speed();
+                    config.player_speed_percent = player.speed();
                     let mut p_tick = playerstats.lock();
-                    p_tick.speed = config.player_speed;
+                    p_tick.speed = config.player_speed_percent;
+                    UpdateEvents::SpeedChanged {
+                        speed: config.player_speed_percent,
+                    }
+                    .send(&stream_tx);
                 }
                 PlayerCmd::Tick => {
                     // info!("tick received");
+                    if !autosave_interval.is_zero() && last_autosave.elapsed() >= autosave_interval
+                    {
+                        last_autosave = Instant::now();
+                        save_playlist(&mut player);
+                        // only write the config if something changed, to not overwrite manual edits needlessly
+                        let current_config = volatile_config(&config);
+                        if current_config != last_saved_config {
+                            save_config(&config);
+                            last_saved_config = current_config;
+                        }
+                    }
                     if config.player_use_mpris {
                         player.update_mpris();
                     }
+                    player.enqueue_for_crossfade();
                     let mut p_tick = playerstats.lock();
-                    p_tick.status = player.playlist.status().as_u32();
+                    let status = player.playlist.status();
+                    if p_tick.status != status.as_u32() {
+                        p_tick.status = status.as_u32();
+                        UpdateEvents::PlayStateChanged { status }.send(&stream_tx);
+                    }
                     // branch to auto-start playing if status is "stopped"(not paused) and playlist is not empty anymore
                     if player.playlist.status() == Status::Stopped {
                         if player.playlist.is_empty() {
@@ -233,13 +492,23 @@ async fn actual_main() -> Result<()> {
                         continue;
                     }
                     let pprogress = player.get_progress();
+                    player.count_play(pprogress);
                     p_tick.progress = pprogress;
+                    p_tick.time_saved = player.time_saved();
+                    p_tick.output_format = player.output_format();
                     if player.current_track_updated {
                         p_tick.current_track_index =
                             player.playlist.get_current_track_index() as u32;
                         p_tick.current_track_updated = player.current_track_updated;
+                        p_tick.current_track = player.playlist.current_track().map(TrackInfo::from);
                         player.current_track_updated = false;
+                        UpdateEvents::TrackChanged {
+                            current_track_index: p_tick.current_track_index,
+                            track: p_tick.current_track.clone(),
+                        }
+                        .send(&stream_tx);
                     }
+                    let old_radio_title = p_tick.radio_title.clone();
                     if let Some(track) = player.playlist.current_track() {
                         if let Some(MediaType::LiveRadio) = &track.media_type {
                             // TODO: consider changing "radio_title" and "media_title" to be consistent
@@ -255,7 +524,7 @@ async fn actual_main() -> Result<()> {
                                         ((*backend.radio_downloaded.lock() as f32 * 44100.0
                                             / 1000000.0
                                             / 1024.0)
-                                            * (backend.speed() as f32 / 10.0))
+                                            * (backend.speed() as f32 / 100.0))
                                             as u64,
                                     ));
                                 }
@@ -271,17 +540,30 @@ async fn actual_main() -> Result<()> {
                             }
                         }
                     }
+                    if p_tick.radio_title != old_radio_title {
+                        UpdateEvents::RadioTitleChanged {
+                            title: p_tick.radio_title.clone(),
+                        }
+                        .send(&stream_tx);
+                    }
+                    UpdateEvents::Progress(p_tick.progress).send(&stream_tx);
                 }
                 PlayerCmd::ToggleGapless => {
                     config.player_gapless = player.toggle_gapless();
                     let mut p_tick = playerstats.lock();
                     p_tick.gapless = config.player_gapless;
+                    UpdateEvents::GaplessChanged {
+                        gapless: config.player_gapless,
+                    }
+                    .send(&stream_tx);
                 }
                 PlayerCmd::TogglePause => {
                     info!("player toggled pause");
                     player.toggle_pause();
                     let mut p_tick = playerstats.lock();
-                    p_tick.status = player.playlist.status().as_u32();
+                    let status = player.playlist.status();
+                    p_tick.status = status.as_u32();
+                    UpdateEvents::PlayStateChanged { status }.send(&stream_tx);
                 }
                 PlayerCmd::VolumeDown => {
                     info!("before volumedown: {}", player.volume());
@@ -291,6 +573,7 @@ async fn actual_main() -> Result<()> {
                     info!("after volumedown: {}", player.volume());
                     let mut p_tick = playerstats.lock();
                     p_tick.volume = new_volume;
+                    UpdateEvents::VolumeChanged { volume: new_volume }.send(&stream_tx);
                 }
                 PlayerCmd::VolumeUp => {
                     info!("before volumeup: {}", player.volume());
@@ -300,32 +583,211 @@ async fn actual_main() -> Result<()> {
                     info!("after volumeup: {}", player.volume());
                     let mut p_tick = playerstats.lock();
                     p_tick.volume = new_volume;
+                    UpdateEvents::VolumeChanged { volume: new_volume }.send(&stream_tx);
                 }
                 PlayerCmd::Pause => {
                     player.pause();
+                    let mut p_tick = playerstats.lock();
+                    let status = player.playlist.status();
+                    p_tick.status = status.as_u32();
+                    UpdateEvents::PlayStateChanged { status }.send(&stream_tx);
                 }
                 PlayerCmd::Play => {
                     player.resume();
+                    let mut p_tick = playerstats.lock();
+                    let status = player.playlist.status();
+                    p_tick.status = status.as_u32();
+                    UpdateEvents::PlayStateChanged { status }.send(&stream_tx);
                 }
             }
         }
 
+        info!("Player stopped, saving state");
+        save_playlist(&mut player);
+        save_config(&config);
+
         Ok(())
     });
 
     ticker_thread(cmd_tx_ticker)?;
 
-    tokio::spawn(
-        Server::builder()
-            .add_service(MusicPlayerServer::new(music_player_service))
-            .serve_with_incoming(tcp_stream),
-    );
+    let router = Server::builder().add_service(MusicPlayerServer::with_interceptor(
+        music_player_service,
+        ServerAuthInterceptor::new(token),
+    ));
+    let shutdown_server = shutdown.clone();
+    let signal = async move { shutdown_server.notified().await };
+    let server_handle = match incoming {
+        Incoming::Tcp(stream) => tokio::spawn(router.serve_with_incoming_shutdown(stream, signal)),
+        #[cfg(unix)]
+        Incoming::Uds(stream) => tokio::spawn(router.serve_with_incoming_shutdown(stream, signal)),
+    };
 
     // if the underlying task/thread panicked, the error will be "task X panicked" instead of the actual panic (with no workaround?)
     // see the log or stderr for actual panic
-    player_handle.await??;
+    let player_result = player_handle.await;
+    // the player only notifies on a quit, not when it failed or panicked
+    shutdown.notify_one();
 
-    Ok(())
+    if let Err(err) = server_handle.await? {
+        error!("Error while shutting down the server: {err}");
+    }
+    let _ = std::fs::remove_file(&pid_path);
+    #[cfg(unix)]
+    if let Some(path) = uds_path {
+        let _ = std::fs::remove_file(path);
+    }
+    info!("Server stopped");
+
+    player_result?
+}
+
+/// Settings the player changes at runtime, to detect when the config needs to be saved
+fn volatile_config(
+    config: &Settings,
+) -> (
+    u16,
+    i32,
+    Loop,
+    bool,
+    EqualizerSettings,
+    ChannelMixSettings,
+    String,
+) {
+    (
+        config.player_volume,
+        config.player_speed_percent,
+        config.player_loop_mode,
+        config.player_gapless,
+        config.player_equalizer.clone(),
+        config.player_channel_mix,
+        config.player_output_device.clone(),
+    )
+}
+
+/// Run the loudness analysis in the background, unless one is already running
+#[cfg(feature = "rusty")]
+fn analyze_loudness(
+    config: &Settings,
+    running: &Arc<AtomicBool>,
+    paths: Vec<String>,
+    write_tags: bool,
+    force: bool,
+) {
+    if running.swap(true, Ordering::SeqCst) {
+        warn!("Loudness analysis is already running");
+        return;
+    }
+    let config = config.clone();
+    let running_thread = running.clone();
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    let spawned = std::thread::Builder::new()
+        .name("loudness analysis".into())
+        .spawn(move || {
+            match termusicplayback::loudness::analyze_library(&config, &paths, write_tags, force) {
+                Ok(count) => info!("Loudness analysis finished, {count} tracks analyzed"),
+                Err(e) => error!("Error in loudness analysis: {e:#}"),
+            }
+            running_thread.store(false, Ordering::SeqCst);
+        });
+    if let Err(e) = spawned {
+        error!("Error starting loudness analysis: {e}");
+        running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(not(feature = "rusty"))]
+fn analyze_loudness(
+    _config: &Settings,
+    _running: &Arc<AtomicBool>,
+    _paths: Vec<String>,
+    _write_tags: bool,
+    _force: bool,
+) {
+    error!("Loudness analysis requires the server to be built with the \"rusty\" feature");
+}
+
+/// Save the playlist and the position in the current track, logging errors
+fn save_playlist(player: &mut GeneralPlayer) {
+    player.player_save_last_position();
+    if let Err(e) = player.playlist.save() {
+        error!("error when saving playlist: {e}");
+    };
+}
+
+/// Save the config, logging errors
+fn save_config(config: &Settings) {
+    if let Err(e) = config.save() {
+        error!("error when saving config: {e}");
+    };
+}
+
+/// A bound listener for the configured [`PlayerProtocol`], ready to be served
+enum Incoming {
+    Tcp(TcpIncoming),
+    #[cfg(unix)]
+    Uds(UnixListenerStream),
+}
+
+/// Bind the listener for the configured [`PlayerProtocol`]
+async fn bind_incoming(config: &Settings) -> Result<Incoming> {
+    match config.player_protocol {
+        PlayerProtocol::Http => {
+            let addr = std::net::SocketAddr::new(config.player_interface, config.player_port);
+
+            // workaround to print address once sever "actually" is started and address is known
+            // see https://github.com/hyperium/tonic/issues/351
+            let tcp_listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Error binding address: {}", addr))?;
+            info!("Server listening on {}", tcp_listener.local_addr().unwrap());
+            let tcp_stream = TcpIncoming::from_listener(tcp_listener, true, None)
+                .map_err(|e| anyhow::anyhow!(e))?;
+
+            Ok(Incoming::Tcp(tcp_stream))
+        }
+        #[cfg(unix)]
+        PlayerProtocol::Uds => {
+            use std::os::unix::fs::PermissionsExt;
+
+            let path = config.get_uds_path()?;
+            if path.exists() {
+                // a left-over socket from a crashed server can be removed, a live one cannot
+                if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                    bail!("Another server is already listening on {}", path.display());
+                }
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Error removing stale socket {}", path.display()))?;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let uds_listener = tokio::net::UnixListener::bind(&path)
+                .with_context(|| format!("Error binding socket: {}", path.display()))?;
+            // access control is done via file permissions, only the current user may connect
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+            info!("Server listening on {}", path.display());
+
+            Ok(Incoming::Uds(UnixListenerStream::new(uds_listener)))
+        }
+        #[cfg(not(unix))]
+        PlayerProtocol::Uds => {
+            bail!("Unix domain sockets are not supported on this platform, use player_protocol = \"Http\"")
+        }
+    }
+}
+
+/// Update the playlist snapshot in `playerstats` and notify all subscribed clients
+fn playlist_changed(playlist: &Playlist, playerstats: &Mutex<PlayerStats>, stream_tx: &StreamTX) {
+    playerstats.lock().update_playlist(playlist);
+    UpdateEvents::PlaylistChanged.send(stream_tx);
+}
+
+/// Update the loop mode in `playerstats` and notify all subscribed clients
+fn loop_mode_changed(loop_mode: Loop, playerstats: &Mutex<PlayerStats>, stream_tx: &StreamTX) {
+    playerstats.lock().loop_mode = loop_mode;
+    UpdateEvents::LoopModeChanged { loop_mode }.send(stream_tx);
 }
 
 /// Spawn the thread that periodically sends [`PlayerCmd::Tick`]
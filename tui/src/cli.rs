@@ -24,6 +24,8 @@ use std::path::PathBuf;
  * SOFTWARE.
  */
 use clap::{builder::ArgPredicate, Parser, Subcommand, ValueEnum};
+use termusiclib::config::{EqPreset, Loop, EQ_BAND_COUNT};
+use termusicplayback::EnqueueMode;
 
 #[derive(Parser, Debug)]
 // mostly read from `Cargo.toml`
@@ -48,6 +50,9 @@ pub struct Args {
     pub max_depth: Option<usize>,
     #[arg(short, long, default_value_t = Backend::Default)]
     pub backend: Backend,
+    /// Use a separate named profile, with its own config, database, playlist and server.
+    #[arg(long, env = "TERMUSIC_PROFILE")]
+    pub profile: Option<String>,
     #[clap(flatten)]
     pub log_options: LogOptions,
 }
@@ -95,6 +100,236 @@ pub enum Action {
         #[arg(value_name = "FILE")]
         file: String,
     },
+    /// Control a already running termusic-server, without starting the TUI.
+    Ctl(CtlArgs),
+}
+
+#[derive(Debug, Parser)]
+pub struct CtlArgs {
+    /// Print the result as JSON instead of plain text.
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: CtlCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// Print the current player status.
+    Status,
+    /// Print the currently playing track.
+    Current,
+    /// Toggle between playing and paused.
+    TogglePause,
+    /// Skip to the next track.
+    Next,
+    /// Go back to the previous track.
+    Previous,
+    /// Seek in the current track.
+    #[command(subcommand)]
+    Seek(CtlSeekCommand),
+    /// Change the volume.
+    #[command(subcommand)]
+    Volume(CtlStepCommand),
+    /// Change the playback speed, in percent (100 is normal speed).
+    #[command(subcommand)]
+    Speed(CtlStepCommand),
+    /// Cycle the loop mode, or set it to MODE.
+    Loop {
+        #[arg(value_enum)]
+        mode: Option<CtlLoopMode>,
+    },
+    /// Toggle gapless playback, or set it to ENABLED.
+    Gapless { enabled: Option<bool> },
+    /// Show or change the equalizer, only applied by the rusty backend.
+    #[command(subcommand)]
+    Eq(CtlEqCommand),
+    /// Show or change the mono downmix and the balance between the left and right channel.
+    #[command(subcommand)]
+    Channels(CtlChannelsCommand),
+    /// List the output devices, or switch to another one, only supported by the rusty backend.
+    #[command(subcommand)]
+    Output(CtlOutputCommand),
+    /// Make the server reload its config file.
+    ReloadConfig,
+    /// Show or edit the playlist.
+    #[command(subcommand)]
+    Playlist(CtlPlaylistCommand),
+    /// Play the track at INDEX in the playlist.
+    Play { index: u32 },
+    /// Add a local file, podcast episode url or radio url to the playlist and play it.
+    Open {
+        uri: String,
+        /// Where to add the track in the playlist.
+        #[arg(long, value_enum, default_value_t = CtlEnqueueMode::Next)]
+        mode: CtlEnqueueMode,
+    },
+    /// Print every event the server sends, one per line, until interrupted.
+    Events,
+    /// Rate the current track with RATING stars, 0 removes the rating.
+    Rate {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        rating: u8,
+    },
+    /// Let the server add PATHS to the library and analyze the loudness of their tracks in the background.
+    ///
+    /// Without PATHS all music directories of the config are analyzed.
+    Analyze {
+        paths: Vec<String>,
+        /// Also write the results as replay gain tags into the files.
+        #[arg(long)]
+        write_tags: bool,
+        /// Also analyze tracks with replay gain tags or an earlier result.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlSeekCommand {
+    /// Seek forward by the configured seek step.
+    Forward,
+    /// Seek backward by the configured seek step.
+    Backward,
+    /// Seek to a absolute position.
+    To {
+        /// Position in seconds.
+        seconds: f64,
+    },
+    /// Seek by a offset relative to the current position.
+    By {
+        /// Offset in seconds, negative to seek backward.
+        #[arg(allow_hyphen_values = true)]
+        seconds: i64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlStepCommand {
+    /// Increase by one step.
+    Up,
+    /// Decrease by one step.
+    Down,
+    /// Set to a absolute value.
+    Set { value: u16 },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlEqCommand {
+    /// Print the current equalizer settings.
+    Show,
+    /// Enable the equalizer.
+    On,
+    /// Disable the equalizer.
+    Off,
+    /// Select a preset and enable the equalizer.
+    Preset {
+        /// One of flat, rock, pop, jazz, classical, bass, treble, vocal or custom.
+        #[arg(value_parser = parse_eq_preset)]
+        preset: EqPreset,
+    },
+    /// Set the custom gains, select the custom preset and enable the equalizer.
+    Set {
+        /// Gains in dB for the bands 31Hz, 62Hz, 125Hz, ... 16kHz.
+        #[arg(num_args = EQ_BAND_COUNT, required = true, allow_hyphen_values = true)]
+        gains: Vec<f32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlChannelsCommand {
+    /// Print the current mono downmix and balance.
+    Show,
+    /// Toggle the mono downmix, or set it to ENABLED.
+    Mono { enabled: Option<bool> },
+    /// Set the balance, from -100 (only left) over 0 (centered) to 100 (only right).
+    Balance {
+        #[arg(allow_hyphen_values = true, value_parser = clap::value_parser!(i16).range(-100..=100))]
+        value: i16,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlOutputCommand {
+    /// Print all output devices, the current one is marked.
+    List,
+    /// Move playback to the device NAME, the current track continues at its position.
+    Set { name: String },
+    /// Move playback to the default device.
+    Default,
+}
+
+fn parse_eq_preset(value: &str) -> Result<EqPreset, String> {
+    EqPreset::ALL
+        .into_iter()
+        .find(|preset| preset.name().eq_ignore_ascii_case(value))
+        .ok_or_else(|| format!("unknown preset \"{value}\""))
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CtlEnqueueMode {
+    /// Insert after the current track.
+    Next,
+    /// Append at the end of the playlist.
+    End,
+    /// Replace the whole playlist.
+    Replace,
+}
+
+impl From<CtlEnqueueMode> for EnqueueMode {
+    fn from(value: CtlEnqueueMode) -> Self {
+        match value {
+            CtlEnqueueMode::Next => Self::Next,
+            CtlEnqueueMode::End => Self::End,
+            CtlEnqueueMode::Replace => Self::Replace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CtlLoopMode {
+    Single,
+    Playlist,
+    Random,
+}
+
+impl From<CtlLoopMode> for Loop {
+    fn from(value: CtlLoopMode) -> Self {
+        match value {
+            CtlLoopMode::Single => Self::Single,
+            CtlLoopMode::Playlist => Self::Playlist,
+            CtlLoopMode::Random => Self::Random,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlPlaylistCommand {
+    /// Print all tracks in the playlist.
+    List,
+    /// Add local files or urls to the playlist.
+    Add {
+        #[arg(value_name = "TRACK", required = true)]
+        tracks: Vec<String>,
+        /// Insert at this index instead of appending at the end.
+        #[arg(long)]
+        at: Option<usize>,
+    },
+    /// Remove tracks from the playlist.
+    Remove {
+        index: usize,
+        /// Amount of tracks to remove, starting at INDEX.
+        #[arg(default_value_t = 1)]
+        amount: usize,
+    },
+    /// Move a track to a different position.
+    Move { from: usize, to: usize },
+    /// Swap the position of two tracks.
+    Swap { index_a: usize, index_b: usize },
+    /// Remove all tracks from the playlist.
+    Clear,
+    /// Shuffle the playlist.
+    Shuffle,
 }
 
 const DEFAULT_LOGFILE_FILENAME: &str = "termusic-tui.log";
@@ -0,0 +1,123 @@
+//! Connecting to `termusic-server` over the configured transport
+
+use std::fmt::Display;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use anyhow::Result;
+use sysinfo::{Pid, System};
+use termusiclib::config::{PlayerProtocol, Settings};
+use termusicplayback::auth::{self, ClientAuthInterceptor};
+use termusicplayback::player::music_player_client::MusicPlayerClient;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+
+/// Client sending the auth token with every request
+pub type Client = MusicPlayerClient<InterceptedService<Channel, ClientAuthInterceptor>>;
+
+/// Create a [`Client`] on a connected `channel`, reading the token if enabled in `config`
+pub fn new_client(channel: Channel, config: &Settings) -> Result<Client> {
+    let token = if config.player_use_token {
+        Some(auth::read_token()?)
+    } else {
+        None
+    };
+    let interceptor = ClientAuthInterceptor::new(token.as_deref())?;
+
+    Ok(MusicPlayerClient::with_interceptor(channel, interceptor))
+}
+
+/// Add a hint on how to fix it, if `err` is the server rejecting the auth token
+pub fn explain_auth_error(err: anyhow::Error) -> anyhow::Error {
+    let rejected = err.downcast_ref::<tonic::Status>().map_or(false, |status| {
+        status.code() == tonic::Code::Unauthenticated
+    });
+    if !rejected {
+        return err;
+    }
+
+    let path = auth::get_token_path().map_or_else(
+        |_| "the token file".to_string(),
+        |v| v.display().to_string(),
+    );
+    err.context(format!(
+        "the server rejected the auth token, make sure \"player_use_token\" is the same for server and client and that both use {path}"
+    ))
+}
+
+/// Find the server of the selected profile, by the pid file it writes
+///
+/// The process is checked to still be a `termusic-server`, in case the pid file is stale.
+pub fn find_running_server() -> Option<u32> {
+    let pid_path = termusiclib::utils::get_server_pid_path().ok()?;
+    let pid: u32 = std::fs::read_to_string(pid_path)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    let mut system = System::new();
+    let sys_pid = Pid::from_u32(pid);
+    system.refresh_process(sys_pid);
+    let exe = system.process(sys_pid)?.exe()?.display().to_string();
+
+    exe.contains("termusic-server").then_some(pid)
+}
+
+/// Where the server can be reached, depending on [`PlayerProtocol`]
+#[derive(Debug, Clone)]
+pub enum ServerAddress {
+    Http(SocketAddr),
+    #[cfg(unix)]
+    Uds(PathBuf),
+}
+
+impl ServerAddress {
+    /// Get the address the server is configured to listen on
+    pub fn from_config(config: &Settings) -> Result<Self> {
+        match config.player_protocol {
+            PlayerProtocol::Http => Ok(Self::Http(SocketAddr::new(
+                config.player_interface,
+                config.player_port,
+            ))),
+            #[cfg(unix)]
+            PlayerProtocol::Uds => Ok(Self::Uds(config.get_uds_path()?)),
+            #[cfg(not(unix))]
+            PlayerProtocol::Uds => anyhow::bail!(
+                "Unix domain sockets are not supported on this platform, use player_protocol = \"Http\""
+            ),
+        }
+    }
+
+    /// Connect to the server once, without any retries
+    pub async fn connect(&self) -> Result<Channel, tonic::transport::Error> {
+        match self {
+            Self::Http(addr) => {
+                tonic::transport::Endpoint::from_shared(format!("http://{addr}"))?
+                    .connect()
+                    .await
+            }
+            #[cfg(unix)]
+            Self::Uds(path) => {
+                let path = path.clone();
+                // the uri is required by tonic, but ignored by the connector
+                tonic::transport::Endpoint::from_static("http://[::]:50051")
+                    .connect_with_connector(tower::service_fn(move |_: tonic::transport::Uri| {
+                        tokio::net::UnixStream::connect(path.clone())
+                    }))
+                    .await
+            }
+        }
+    }
+}
+
+impl Display for ServerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(addr) => write!(f, "http://{addr}"),
+            #[cfg(unix)]
+            Self::Uds(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
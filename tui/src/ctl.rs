@@ -0,0 +1,507 @@
+//! Headless remote control of a running `termusic-server`, for scripts and keybinds
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use termusiclib::config::{ChannelMixSettings, EqPreset, EqualizerSettings, Settings};
+use termusiclib::track::Track;
+use termusicplayback::player::TrackInfo;
+use termusicplayback::{OutputFormat, PlayerProgress, Status, UpdateEvents};
+
+use crate::cli::{
+    CtlArgs, CtlChannelsCommand, CtlCommand, CtlEqCommand, CtlOutputCommand, CtlPlaylistCommand,
+    CtlSeekCommand, CtlStepCommand,
+};
+use crate::connection::{self, ServerAddress};
+use crate::ui::playback::Playback;
+
+/// Execute a single [`CtlCommand`] against the running server and print the result
+pub async fn run(args: CtlArgs, config: &Settings) -> Result<()> {
+    let mut playback = connect(config).await?;
+
+    run_command(&mut playback, args)
+        .await
+        .map_err(connection::explain_auth_error)
+}
+
+async fn run_command(playback: &mut Playback, args: CtlArgs) -> Result<()> {
+    let json = args.json;
+
+    match args.command {
+        CtlCommand::Status => print_status(playback, json).await?,
+        CtlCommand::Current => print_current_track(playback, json).await?,
+        CtlCommand::TogglePause => {
+            let status = playback.toggle_pause().await?;
+            output(
+                json,
+                status.to_string(),
+                json!({ "status": status.to_string() }),
+            );
+        }
+        CtlCommand::Next => {
+            playback.skip_next().await?;
+            output_ok(json);
+        }
+        CtlCommand::Previous => {
+            playback.skip_previous().await?;
+            output_ok(json);
+        }
+        CtlCommand::Seek(direction) => {
+            let progress = match direction {
+                CtlSeekCommand::Forward => playback.seek_forward().await?,
+                CtlSeekCommand::Backward => playback.seek_backward().await?,
+                CtlSeekCommand::To { seconds } => {
+                    let position = Duration::try_from_secs_f64(seconds)
+                        .with_context(|| format!("invalid position: {seconds}"))?;
+                    playback.seek_to(position).await?
+                }
+                CtlSeekCommand::By { seconds } => playback.seek_relative(seconds).await?,
+            };
+            output(json, format_progress(&progress), progress_json(&progress));
+        }
+        CtlCommand::Volume(step) => {
+            let volume = match step {
+                CtlStepCommand::Up => playback.volume_up().await?,
+                CtlStepCommand::Down => playback.volume_down().await?,
+                CtlStepCommand::Set { value } => playback.set_volume(value).await?,
+            };
+            output(json, volume.to_string(), json!({ "volume": volume }));
+        }
+        CtlCommand::Speed(step) => {
+            let speed = match step {
+                CtlStepCommand::Up => playback.speed_up().await?,
+                CtlStepCommand::Down => playback.speed_down().await?,
+                CtlStepCommand::Set { value } => playback.set_speed(i32::from(value)).await?,
+            };
+            output(json, format_speed(speed), json!({ "speed": speed }));
+        }
+        CtlCommand::Loop { mode: None } => {
+            playback.cycle_loop().await?;
+            output_ok(json);
+        }
+        CtlCommand::Loop { mode: Some(mode) } => {
+            let mode = playback.set_loop_mode(mode.into()).await?.display(false);
+            output(json, mode.clone(), json!({ "loop_mode": mode }));
+        }
+        CtlCommand::Gapless { enabled } => {
+            let gapless = match enabled {
+                Some(enabled) => playback.set_gapless(enabled).await?,
+                None => playback.toggle_gapless().await?,
+            };
+            output(json, gapless.to_string(), json!({ "gapless": gapless }));
+        }
+        CtlCommand::Eq(cmd) => run_equalizer(playback, cmd, json).await?,
+        CtlCommand::Channels(cmd) => run_channel_mix(playback, cmd, json).await?,
+        CtlCommand::Output(cmd) => run_output(playback, cmd, json).await?,
+        CtlCommand::ReloadConfig => {
+            playback.reload_config().await?;
+            output_ok(json);
+        }
+        CtlCommand::Playlist(cmd) => run_playlist(playback, cmd, json).await?,
+        CtlCommand::Play { index } => {
+            playback.play_index(index).await?;
+            output_ok(json);
+        }
+        CtlCommand::Open { uri, mode } => {
+            playback.play_uri(to_location(&uri), mode.into()).await?;
+            output_ok(json);
+        }
+        CtlCommand::Events => print_events(playback, json).await?,
+        CtlCommand::Rate { rating } => {
+            playback.rate_current_track(rating).await?;
+            output_ok(json);
+        }
+        CtlCommand::Analyze {
+            paths,
+            write_tags,
+            force,
+        } => {
+            let paths = paths.iter().map(|v| crate::get_path_export(v)).collect();
+            playback.analyze_loudness(paths, write_tags, force).await?;
+            output_ok(json);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_playlist(playback: &mut Playback, cmd: CtlPlaylistCommand, json: bool) -> Result<()> {
+    match cmd {
+        CtlPlaylistCommand::List => {
+            let playlist = playback.get_playlist().await?;
+            let text = playlist
+                .tracks
+                .iter()
+                .enumerate()
+                .map(|(idx, track)| {
+                    let marker = if idx == playlist.current_track_index as usize {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    format!("{marker}{idx:>4} {track}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(
+                json,
+                text,
+                json!({
+                    "current_track_index": playlist.current_track_index,
+                    "tracks": playlist.tracks,
+                }),
+            );
+            return Ok(());
+        }
+        CtlPlaylistCommand::Add { tracks, at } => {
+            let tracks = tracks.iter().map(|v| to_location(v)).collect();
+            playback.playlist_add_tracks(tracks, at).await?;
+        }
+        CtlPlaylistCommand::Remove { index, amount } => {
+            playback.playlist_remove_tracks(index, amount).await?;
+        }
+        CtlPlaylistCommand::Move { from, to } => playback.playlist_move_track(from, to).await?,
+        CtlPlaylistCommand::Swap { index_a, index_b } => {
+            playback.playlist_swap_tracks(index_a, index_b).await?;
+        }
+        CtlPlaylistCommand::Clear => playback.playlist_clear().await?,
+        CtlPlaylistCommand::Shuffle => playback.playlist_shuffle().await?,
+    }
+
+    output_ok(json);
+    Ok(())
+}
+
+async fn run_equalizer(playback: &mut Playback, cmd: CtlEqCommand, json: bool) -> Result<()> {
+    let mut equalizer = playback.get_equalizer().await?;
+    let changed = !matches!(cmd, CtlEqCommand::Show);
+    match cmd {
+        CtlEqCommand::Show => {}
+        CtlEqCommand::On => equalizer.enabled = true,
+        CtlEqCommand::Off => equalizer.enabled = false,
+        CtlEqCommand::Preset { preset } => {
+            equalizer.enabled = true;
+            equalizer.preset = preset;
+        }
+        CtlEqCommand::Set { gains } => {
+            equalizer.enabled = true;
+            equalizer.preset = EqPreset::Custom;
+            // the amount is checked by clap
+            equalizer.custom_gains = gains.try_into().unwrap_or(equalizer.custom_gains);
+        }
+    }
+    if changed {
+        equalizer = playback.set_equalizer(&equalizer).await?;
+    }
+
+    output(
+        json,
+        format_equalizer(&equalizer),
+        json!({
+            "enabled": equalizer.enabled,
+            "preset": equalizer.preset.name(),
+            "gains": equalizer.gains(),
+        }),
+    );
+
+    Ok(())
+}
+
+async fn run_channel_mix(
+    playback: &mut Playback,
+    cmd: CtlChannelsCommand,
+    json: bool,
+) -> Result<()> {
+    let mut channel_mix = playback.get_channel_mix().await?;
+    let changed = !matches!(cmd, CtlChannelsCommand::Show);
+    match cmd {
+        CtlChannelsCommand::Show => {}
+        CtlChannelsCommand::Mono { enabled } => {
+            channel_mix.mono = enabled.unwrap_or(!channel_mix.mono);
+        }
+        CtlChannelsCommand::Balance { value } => channel_mix.balance = value,
+    }
+    if changed {
+        channel_mix = playback.set_channel_mix(channel_mix).await?;
+    }
+
+    output(
+        json,
+        format_channel_mix(channel_mix),
+        json!({
+            "mono": channel_mix.mono,
+            "balance": channel_mix.balance,
+        }),
+    );
+
+    Ok(())
+}
+
+async fn run_output(playback: &mut Playback, cmd: CtlOutputCommand, json: bool) -> Result<()> {
+    let devices = match cmd {
+        CtlOutputCommand::List => playback.list_output_devices().await?,
+        CtlOutputCommand::Set { name } => playback.set_output_device(name).await?,
+        CtlOutputCommand::Default => playback.set_output_device(String::new()).await?,
+    };
+
+    let text = devices
+        .devices
+        .iter()
+        .map(|device| {
+            let marker = if *device == devices.current { "*" } else { " " };
+            format!("{marker} {device}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    output(
+        json,
+        text,
+        json!({
+            "current": devices.current,
+            "devices": devices.devices,
+        }),
+    );
+
+    Ok(())
+}
+
+fn format_equalizer(equalizer: &EqualizerSettings) -> String {
+    let gains: Vec<String> = equalizer
+        .gains()
+        .iter()
+        .map(|gain| format!("{gain:+.1}"))
+        .collect();
+    format!(
+        "{} {} [{}]",
+        if equalizer.enabled { "on" } else { "off" },
+        equalizer.preset.name(),
+        gains.join(" ")
+    )
+}
+
+fn format_channel_mix(channel_mix: ChannelMixSettings) -> String {
+    let balance = match channel_mix.balance {
+        0 => "centered".to_string(),
+        balance if balance < 0 => format!("{} left", -balance),
+        balance => format!("{balance} right"),
+    };
+    format!(
+        "mono: {}\nbalance: {balance}",
+        if channel_mix.mono { "on" } else { "off" }
+    )
+}
+
+async fn print_status(playback: &mut Playback, json: bool) -> Result<()> {
+    let progress = playback.get_progress().await?;
+    let status = Status::from_u32(progress.status);
+    let pprogress: PlayerProgress = progress.progress.unwrap_or_default().into();
+    let track = progress.current_track.as_ref();
+    let time_saved: Duration = progress.time_saved.clone().unwrap_or_default().into();
+    let output_format: Option<OutputFormat> = progress.output_format.clone().map(Into::into);
+
+    let mut text = format!(
+        "status: {status}\ntrack: {}\nfile: {}\nindex: {}\nposition: {}\nvolume: {}\nspeed: {}\ngapless: {}",
+        track.map(format_track).unwrap_or_default(),
+        track.map(|v| v.file.as_str()).unwrap_or_default(),
+        progress.current_track_index,
+        format_progress(&pprogress),
+        progress.volume,
+        format_speed(progress.speed_percent),
+        progress.gapless,
+    );
+    if !progress.radio_title.is_empty() {
+        text.push_str(&format!("\nradio title: {}", progress.radio_title));
+    }
+    if !time_saved.is_zero() {
+        text.push_str(&format!(
+            "\ntime saved: {}",
+            Track::duration_formatted_short(&time_saved)
+        ));
+    }
+    if let Some(format) = &output_format {
+        text.push_str(&format!("\noutput: {format}"));
+    }
+
+    output(
+        json,
+        text,
+        json!({
+            "status": status.to_string(),
+            "track": track.map(track_json),
+            "current_track_index": progress.current_track_index,
+            "progress": progress_json(&pprogress),
+            "volume": progress.volume,
+            "speed": progress.speed_percent,
+            "gapless": progress.gapless,
+            "radio_title": progress.radio_title,
+            "time_saved": time_saved.as_secs_f64(),
+            "output_format": output_format.map(|format| json!({
+                "sample_rate": format.sample_rate,
+                "channels": format.channels,
+                "sample_format": format.sample_format,
+            })),
+        }),
+    );
+
+    Ok(())
+}
+
+async fn print_events(playback: &mut Playback, json: bool) -> Result<()> {
+    let mut stream = playback.subscribe_to_stream_updates().await?;
+
+    while let Some(ev) = stream.message().await? {
+        let ev = UpdateEvents::try_from(ev)?;
+        let (text, value) = match ev {
+            UpdateEvents::MissedEvents { amount } => (
+                format!("missed-events {amount}"),
+                json!({ "event": "missed-events", "amount": amount }),
+            ),
+            UpdateEvents::TrackChanged {
+                current_track_index,
+                track,
+            } => (
+                format!(
+                    "track-changed {current_track_index} {}",
+                    track.as_ref().map(format_track).unwrap_or_default()
+                ),
+                json!({
+                    "event": "track-changed",
+                    "current_track_index": current_track_index,
+                    "track": track.as_ref().map(track_json),
+                }),
+            ),
+            UpdateEvents::PlayStateChanged { status } => (
+                format!("play-state-changed {status}"),
+                json!({ "event": "play-state-changed", "status": status.to_string() }),
+            ),
+            UpdateEvents::VolumeChanged { volume } => (
+                format!("volume-changed {volume}"),
+                json!({ "event": "volume-changed", "volume": volume }),
+            ),
+            UpdateEvents::SpeedChanged { speed } => (
+                format!("speed-changed {}", format_speed(speed)),
+                json!({ "event": "speed-changed", "speed": speed }),
+            ),
+            UpdateEvents::GaplessChanged { gapless } => (
+                format!("gapless-changed {gapless}"),
+                json!({ "event": "gapless-changed", "gapless": gapless }),
+            ),
+            UpdateEvents::Progress(progress) => (
+                format!("progress {}", format_progress(&progress)),
+                json!({ "event": "progress", "progress": progress_json(&progress) }),
+            ),
+            UpdateEvents::RadioTitleChanged { title } => (
+                format!("radio-title-changed {title}"),
+                json!({ "event": "radio-title-changed", "title": title }),
+            ),
+            UpdateEvents::PlaylistChanged => (
+                "playlist-changed".to_string(),
+                json!({ "event": "playlist-changed" }),
+            ),
+            UpdateEvents::LoopModeChanged { loop_mode } => {
+                let mode = loop_mode.display(false);
+                (
+                    format!("loop-mode-changed {mode}"),
+                    json!({ "event": "loop-mode-changed", "loop_mode": mode }),
+                )
+            }
+            UpdateEvents::LibraryChanged => (
+                "library-changed".to_string(),
+                json!({ "event": "library-changed" }),
+            ),
+        };
+        output(json, text, value);
+    }
+
+    Ok(())
+}
+
+/// Connect to the server, without trying to start it
+async fn connect(config: &Settings) -> Result<Playback> {
+    let addr = ServerAddress::from_config(config)?;
+    let channel = addr.connect().await.with_context(|| {
+        format!("could not connect to termusic-server at {addr}, is it running?")
+    })?;
+
+    Ok(Playback::new(connection::new_client(channel, config)?))
+}
+
+/// Make local paths absolute, as the server may run in a different working directory
+fn to_location(uri: &str) -> String {
+    if uri.starts_with("http") || uri.starts_with("file://") {
+        uri.to_string()
+    } else {
+        crate::get_path_export(uri)
+    }
+}
+
+async fn print_current_track(playback: &mut Playback, json: bool) -> Result<()> {
+    let track = playback.get_current_track().await?;
+    output(
+        json,
+        track.as_ref().map(format_track).unwrap_or_default(),
+        json!({ "track": track.as_ref().map(track_json) }),
+    );
+
+    Ok(())
+}
+
+/// Format as "artist - title", falling back to the file for tracks without tags
+fn format_track(track: &TrackInfo) -> String {
+    match (track.artist.is_empty(), track.title.is_empty()) {
+        (_, true) => track.file.clone(),
+        (true, false) => track.title.clone(),
+        (false, false) => format!("{} - {}", track.artist, track.title),
+    }
+}
+
+fn track_json(track: &TrackInfo) -> Value {
+    let media_type = match track.media_type {
+        1 => "podcast",
+        2 => "live-radio",
+        _ => "music",
+    };
+    json!({
+        "title": track.title,
+        "artist": track.artist,
+        "album": track.album,
+        "genre": track.genre,
+        "duration": std::time::Duration::from(track.duration.clone().unwrap_or_default()).as_secs_f64(),
+        "file": track.file,
+        "media_type": media_type,
+        "has_album_art": track.has_album_art,
+    })
+}
+
+fn output(json: bool, text: String, value: Value) {
+    if json {
+        println!("{value}");
+    } else {
+        println!("{text}");
+    }
+}
+
+fn output_ok(json: bool) {
+    output(json, "ok".to_string(), json!({ "ok": true }));
+}
+
+fn format_progress(progress: &PlayerProgress) -> String {
+    format!(
+        "{} / {}",
+        Track::duration_formatted_short(&progress.position),
+        Track::duration_formatted_short(&progress.total_duration.unwrap_or_default())
+    )
+}
+
+fn progress_json(progress: &PlayerProgress) -> Value {
+    json!({
+        "position": progress.position.as_secs_f64(),
+        "total_duration": progress.total_duration.as_ref().map(Duration::as_secs_f64),
+    })
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_speed(speed: i32) -> String {
+    format!("{:.2}", speed as f32 / 100.0)
+}
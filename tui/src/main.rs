@@ -27,20 +27,21 @@
  * SOFTWARE.
  */
 mod cli;
+mod connection;
+mod ctl;
 mod logger;
 mod ui;
 
 use anyhow::Result;
 use clap::Parser;
 use config::Settings;
+use connection::ServerAddress;
 use flexi_logger::LogSpecification;
-use std::net::SocketAddr;
 use std::process;
 use std::time::{Duration, Instant};
 use std::{error::Error, path::Path};
-use termusicplayback::player::music_player_client::MusicPlayerClient;
 
-use sysinfo::{Pid, ProcessStatus, System};
+use sysinfo::{Pid, ProcessStatus};
 use termusiclib::{config, podcast, utils};
 use ui::UI;
 #[macro_use]
@@ -63,10 +64,13 @@ fn main() -> Result<()> {
 async fn actual_main() -> Result<()> {
     let args = cli::Args::parse();
     let mut logger_handle = logger::setup(&args);
+    if let Some(profile) = &args.profile {
+        utils::set_profile(profile)?;
+    }
     let config = get_config(&args)?;
 
     if let Some(action) = args.action {
-        execute_action(action, &config);
+        execute_action(action, &config).await?;
 
         return Ok(());
     }
@@ -74,20 +78,9 @@ async fn actual_main() -> Result<()> {
     // launch the daemon if it isn't already
     let mut termusic_server_prog = std::path::PathBuf::from("termusic-server");
 
-    let mut system = System::new();
-    system.refresh_all();
-    let mut launch_daemon = true;
-    let mut pid = 0;
-    for (id, proc) in system.processes() {
-        let Some(exe) = proc.exe().map(|v| v.display().to_string()) else {
-            continue;
-        };
-        if exe.contains("termusic-server") {
-            pid = id.as_u32();
-            launch_daemon = false;
-            break;
-        }
-    }
+    let running_pid = connection::find_running_server();
+    let launch_daemon = running_pid.is_none();
+    let mut pid = running_pid.unwrap_or_default();
 
     // try to find the server binary adjacent to the currently executing binary path
     let potential_server_exe = {
@@ -114,6 +107,11 @@ async fn actual_main() -> Result<()> {
         server_args.push("--backend");
         server_args.push(&backend);
 
+        if let Some(profile) = &args.profile {
+            server_args.push("--profile");
+            server_args.push(profile);
+        }
+
         let proc = utils::spawn_process(&termusic_server_prog, false, false, &server_args)
             .unwrap_or_else(|_| panic!("Could not find {} binary", termusic_server_prog.display()));
 
@@ -133,11 +131,8 @@ async fn actual_main() -> Result<()> {
     }
 
     info!("Waiting until connected");
-    let client = wait_till_connected(
-        SocketAddr::new(config.player_interface, config.player_port),
-        pid,
-    )
-    .await?;
+    let channel = wait_till_connected(&ServerAddress::from_config(&config)?, pid).await?;
+    let client = connection::new_client(channel, &config)?;
     info!("Connected!");
 
     let mut ui = UI::new(&config, client).await?;
@@ -152,13 +147,13 @@ const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
 const WAIT_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Wait until tonic is connected, or:
-/// - tonic errors anything other than `ConnectionRefused`
+/// - tonic errors anything other than `ConnectionRefused` or `NotFound` (socket not yet created)
 /// - given PID does not exist anymore
 /// - timeout of [`WAIT_TIMEOUT`] reached
 async fn wait_till_connected(
-    socket: SocketAddr,
+    address: &ServerAddress,
     pid: u32,
-) -> Result<MusicPlayerClient<tonic::transport::Channel>> {
+) -> Result<tonic::transport::Channel> {
     let mut sys = sysinfo::System::new();
     let sys_pid = Pid::from_u32(pid);
     let start_time = Instant::now();
@@ -179,18 +174,21 @@ async fn wait_till_connected(
             anyhow::bail!("Process {pid} exited before being able to connect!");
         }
 
-        match MusicPlayerClient::connect(format!("http://{socket}")).await {
+        match address.connect().await {
             Err(err) => {
                 // downcast "tonic::transport::Error" to a "std::io::Error"(kind: Os)
                 if let Some(os_err) = find_source::<std::io::Error>(&err) {
-                    if os_err.kind() == std::io::ErrorKind::ConnectionRefused {
-                        debug!("Connection refused found!");
+                    if matches!(
+                        os_err.kind(),
+                        std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound
+                    ) {
+                        debug!("Server not yet listening on {address}");
                         tokio::time::sleep(WAIT_INTERVAL).await;
                         continue;
                     }
                 }
 
-                // return the error and stop if it is anything other than "Connection Refused"
+                // return the error and stop if it is anything other than "Connection Refused" or "Not Found"
                 anyhow::bail!(err);
             }
             Ok(client) => return Ok(client),
@@ -253,7 +251,7 @@ fn get_path(dir: &str) -> Option<String> {
     music_dir
 }
 
-fn execute_action(action: cli::Action, config: &Settings) {
+async fn execute_action(action: cli::Action, config: &Settings) -> Result<()> {
     match action {
         cli::Action::Import { file } => {
             println!("need to import from file {file}");
@@ -277,7 +275,10 @@ fn execute_action(action: cli::Action, config: &Settings) {
                 }
             }
         }
+        cli::Action::Ctl(ctl_args) => ctl::run(ctl_args, config).await?,
     };
+
+    Ok(())
 }
 
 fn get_path_export(dir: &str) -> String {
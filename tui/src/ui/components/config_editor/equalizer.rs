@@ -0,0 +1,269 @@
+use crate::config::Settings;
+use crate::ui::{ConfigEditorMsg, Msg};
+
+use super::general::{handle_input_ev, handle_radio_ev};
+use termusiclib::config::{EqPreset, EQ_BAND_FREQUENCIES};
+use tui_realm_stdlib::{Input, Radio};
+use tuirealm::props::{Alignment, BorderType, Borders, Color, InputType, Style};
+use tuirealm::{event::NoUserEvent, Component, Event, MockComponent};
+
+#[derive(MockComponent)]
+pub struct EqualizerEnabled {
+    component: Radio,
+    config: Settings,
+}
+
+impl EqualizerEnabled {
+    pub fn new(config: &Settings) -> Self {
+        let enabled = config.player_equalizer.enabled;
+        Self {
+            component: Radio::default()
+                .borders(
+                    Borders::default()
+                        .color(
+                            config
+                                .style_color_symbol
+                                .library_border()
+                                .unwrap_or(Color::LightRed),
+                        )
+                        .modifiers(BorderType::Rounded),
+                )
+                .choices(&["Yes", "No"])
+                .foreground(
+                    config
+                        .style_color_symbol
+                        .library_highlight()
+                        .unwrap_or(Color::LightRed),
+                )
+                .rewind(true)
+                .title(" Enable equalizer? (rusty backend only) ", Alignment::Left)
+                .value(usize::from(!enabled)),
+            config: config.clone(),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for EqualizerEnabled {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        handle_radio_ev(
+            self,
+            ev,
+            &config,
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerEnabledBlurDown),
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerEnabledBlurUp),
+        )
+    }
+}
+
+#[derive(MockComponent)]
+pub struct EqualizerPreset {
+    component: Radio,
+    config: Settings,
+}
+
+impl EqualizerPreset {
+    pub fn new(config: &Settings) -> Self {
+        let choices: Vec<&str> = EqPreset::ALL.iter().map(|v| v.name()).collect();
+        Self {
+            component: Radio::default()
+                .borders(
+                    Borders::default()
+                        .color(
+                            config
+                                .style_color_symbol
+                                .library_border()
+                                .unwrap_or(Color::LightRed),
+                        )
+                        .modifiers(BorderType::Rounded),
+                )
+                .choices(&choices)
+                .foreground(
+                    config
+                        .style_color_symbol
+                        .library_highlight()
+                        .unwrap_or(Color::LightRed),
+                )
+                .rewind(true)
+                .title(" Preset: ", Alignment::Left)
+                .value(config.player_equalizer.preset.as_u32() as usize),
+            config: config.clone(),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for EqualizerPreset {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        handle_radio_ev(
+            self,
+            ev,
+            &config,
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerPresetBlurDown),
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerPresetBlurUp),
+        )
+    }
+}
+
+/// Gain of a single band of the "custom" preset
+#[derive(MockComponent)]
+pub struct EqualizerBand {
+    component: Input,
+    config: Settings,
+    index: usize,
+}
+
+impl EqualizerBand {
+    pub fn new(config: &Settings, index: usize) -> Self {
+        let frequency = EQ_BAND_FREQUENCIES[index];
+        let frequency = if frequency >= 1000.0 {
+            format!("{}kHz", frequency / 1000.0)
+        } else {
+            format!("{frequency}Hz")
+        };
+        Self {
+            component: Input::default()
+                .borders(
+                    Borders::default()
+                        .color(
+                            config
+                                .style_color_symbol
+                                .library_border()
+                                .unwrap_or(Color::LightRed),
+                        )
+                        .modifiers(BorderType::Rounded),
+                )
+                .foreground(
+                    config
+                        .style_color_symbol
+                        .library_highlight()
+                        .unwrap_or(Color::LightRed),
+                )
+                .input_type(InputType::Number)
+                .invalid_style(Style::default().fg(Color::Red))
+                .placeholder(
+                    "between -12 ~ 12",
+                    Style::default().fg(Color::Rgb(128, 128, 128)),
+                )
+                .title(format!(" Custom {frequency} (dB): "), Alignment::Left)
+                .value(format!("{}", config.player_equalizer.custom_gains[index])),
+            config: config.clone(),
+            index,
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for EqualizerBand {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        let index = self.index;
+        handle_input_ev(
+            self,
+            ev,
+            &config,
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerBandBlurDown(index)),
+            Msg::ConfigEditor(ConfigEditorMsg::EqualizerBandBlurUp(index)),
+        )
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ChannelMixMono {
+    component: Radio,
+    config: Settings,
+}
+
+impl ChannelMixMono {
+    pub fn new(config: &Settings) -> Self {
+        let mono = config.player_channel_mix.mono;
+        Self {
+            component: Radio::default()
+                .borders(
+                    Borders::default()
+                        .color(
+                            config
+                                .style_color_symbol
+                                .library_border()
+                                .unwrap_or(Color::LightRed),
+                        )
+                        .modifiers(BorderType::Rounded),
+                )
+                .choices(&["Yes", "No"])
+                .foreground(
+                    config
+                        .style_color_symbol
+                        .library_highlight()
+                        .unwrap_or(Color::LightRed),
+                )
+                .rewind(true)
+                .title(" Mix all channels to mono? ", Alignment::Left)
+                .value(usize::from(!mono)),
+            config: config.clone(),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ChannelMixMono {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        handle_radio_ev(
+            self,
+            ev,
+            &config,
+            Msg::ConfigEditor(ConfigEditorMsg::ChannelMixMonoBlurDown),
+            Msg::ConfigEditor(ConfigEditorMsg::ChannelMixMonoBlurUp),
+        )
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ChannelMixBalance {
+    component: Input,
+    config: Settings,
+}
+
+impl ChannelMixBalance {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: Input::default()
+                .borders(
+                    Borders::default()
+                        .color(
+                            config
+                                .style_color_symbol
+                                .library_border()
+                                .unwrap_or(Color::LightRed),
+                        )
+                        .modifiers(BorderType::Rounded),
+                )
+                .foreground(
+                    config
+                        .style_color_symbol
+                        .library_highlight()
+                        .unwrap_or(Color::LightRed),
+                )
+                .input_type(InputType::Number)
+                .invalid_style(Style::default().fg(Color::Red))
+                .placeholder(
+                    "between -100 (left) ~ 100 (right)",
+                    Style::default().fg(Color::Rgb(128, 128, 128)),
+                )
+                .title(" Balance: ", Alignment::Left)
+                .value(format!("{}", config.player_channel_mix.balance())),
+            config: config.clone(),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ChannelMixBalance {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        handle_input_ev(
+            self,
+            ev,
+            &config,
+            Msg::ConfigEditor(ConfigEditorMsg::ChannelMixBalanceBlurDown),
+            Msg::ConfigEditor(ConfigEditorMsg::ChannelMixBalanceBlurUp),
+        )
+    }
+}
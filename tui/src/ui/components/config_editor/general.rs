@@ -94,7 +94,7 @@ impl Component<Msg, NoUserEvent> for MusicDir {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn handle_input_ev(
+pub(super) fn handle_input_ev(
     component: &mut dyn Component<Msg, NoUserEvent>,
     ev: Event<NoUserEvent>,
     config: &Settings,
@@ -216,7 +216,7 @@ impl Component<Msg, NoUserEvent> for ExitConfirmation {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn handle_radio_ev(
+pub(super) fn handle_radio_ev(
     component: &mut dyn Component<Msg, NoUserEvent>,
     ev: Event<NoUserEvent>,
     config: &Settings,
@@ -1179,6 +1179,14 @@ impl KEModifierSelect {
             IdKey::PodcastSearchAddFeed => keys.podcast_search_add_feed.mod_key(),
             IdKey::PodcastRefreshFeed => keys.podcast_refresh_feed.mod_key(),
             IdKey::PodcastRefreshAllFeeds => keys.podcast_refresh_all_feeds.mod_key(),
+            IdKey::PlaylistMostPlayed => keys.playlist_most_played.mod_key(),
+            IdKey::PlaylistRecentlyPlayed => keys.playlist_recently_played.mod_key(),
+            IdKey::PlaylistNeverPlayed => keys.playlist_never_played.mod_key(),
+            IdKey::DatabaseSort => keys.database_sort.mod_key(),
+            IdKey::DatabaseExportM3u => keys.database_export_m3u.mod_key(),
+            IdKey::TrackRatingUp => keys.track_rating_up.mod_key(),
+            IdKey::TrackRatingDown => keys.track_rating_down.mod_key(),
+            IdKey::TrackFavorite => keys.track_favorite_toggle.mod_key(),
         }
     }
 
@@ -2992,3 +3000,209 @@ impl Component<Msg, NoUserEvent> for ConfigPodcastRefreshAllFeeds {
         self.component.on(ev)
     }
 }
+
+#[derive(MockComponent)]
+pub struct ConfigPlaylistMostPlayed {
+    component: KEModifierSelect,
+}
+
+impl ConfigPlaylistMostPlayed {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Most played ",
+                IdKey::PlaylistMostPlayed,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::PlaylistMostPlayedBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::PlaylistMostPlayedBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigPlaylistMostPlayed {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigPlaylistRecentlyPlayed {
+    component: KEModifierSelect,
+}
+
+impl ConfigPlaylistRecentlyPlayed {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Recently played ",
+                IdKey::PlaylistRecentlyPlayed,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(
+                    KFMsg::PlaylistRecentlyPlayedBlurDown,
+                )),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(
+                    KFMsg::PlaylistRecentlyPlayedBlurUp,
+                )),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigPlaylistRecentlyPlayed {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigPlaylistNeverPlayed {
+    component: KEModifierSelect,
+}
+
+impl ConfigPlaylistNeverPlayed {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Never played ",
+                IdKey::PlaylistNeverPlayed,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(
+                    KFMsg::PlaylistNeverPlayedBlurDown,
+                )),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::PlaylistNeverPlayedBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigPlaylistNeverPlayed {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigDatabaseSort {
+    component: KEModifierSelect,
+}
+
+impl ConfigDatabaseSort {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Sort tracks ",
+                IdKey::DatabaseSort,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::DatabaseSortBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::DatabaseSortBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigDatabaseSort {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigDatabaseExportM3u {
+    component: KEModifierSelect,
+}
+
+impl ConfigDatabaseExportM3u {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Export smart playlist ",
+                IdKey::DatabaseExportM3u,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::DatabaseExportM3uBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::DatabaseExportM3uBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigDatabaseExportM3u {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigTrackRatingUp {
+    component: KEModifierSelect,
+}
+
+impl ConfigTrackRatingUp {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Rating up ",
+                IdKey::TrackRatingUp,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackRatingUpBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackRatingUpBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigTrackRatingUp {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigTrackRatingDown {
+    component: KEModifierSelect,
+}
+
+impl ConfigTrackRatingDown {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Rating down ",
+                IdKey::TrackRatingDown,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackRatingDownBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackRatingDownBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigTrackRatingDown {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
+
+#[derive(MockComponent)]
+pub struct ConfigTrackFavorite {
+    component: KEModifierSelect,
+}
+
+impl ConfigTrackFavorite {
+    pub fn new(config: &Settings) -> Self {
+        Self {
+            component: KEModifierSelect::new(
+                " Toggle favorite ",
+                IdKey::TrackFavorite,
+                config,
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackFavoriteBlurDown)),
+                Msg::ConfigEditor(ConfigEditorMsg::KeyFocus(KFMsg::TrackFavoriteBlurUp)),
+            ),
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for ConfigTrackFavorite {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        self.component.on(ev)
+    }
+}
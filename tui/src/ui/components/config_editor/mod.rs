@@ -22,6 +22,7 @@
  * SOFTWARE.
  */
 mod color;
+mod equalizer;
 mod general;
 mod key_combo;
 mod update;
@@ -31,6 +32,7 @@ use crate::config::Settings;
 use crate::ui::model::ConfigEditorLayout;
 use crate::ui::{ConfigEditorMsg, Msg};
 pub use color::*;
+pub use equalizer::*;
 pub use general::*;
 pub use key_combo::*;
 
@@ -61,6 +63,7 @@ impl CEHeader {
                     "Themes and Colors",
                     "Keys Global",
                     "Keys Other",
+                    "Equalizer & Channels",
                 ])
                 .foreground(
                     config
@@ -79,6 +82,7 @@ impl CEHeader {
                     ConfigEditorLayout::Color => 1,
                     ConfigEditorLayout::Key1 => 2,
                     ConfigEditorLayout::Key2 => 3,
+                    ConfigEditorLayout::Equalizer => 4,
                 }),
         }
     }
@@ -24,6 +24,7 @@ use crate::config::{load_alacritty, BindingForEvent, ColorTermusic};
  */
 use crate::ui::Model;
 use std::path::PathBuf;
+use termusiclib::config::EQ_BAND_COUNT;
 use termusiclib::types::{ConfigEditorMsg, Id, IdConfigEditor, IdKey, KFMsg, Msg};
 use termusicplayback::PlayerCmd;
 
@@ -167,6 +168,60 @@ impl Model {
                 self.umount_config_editor();
             }
 
+            // Focus of equalizer page
+            ConfigEditorMsg::EqualizerEnabledBlurDown | ConfigEditorMsg::EqualizerBandBlurUp(0) => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerPreset))
+                    .ok();
+            }
+            ConfigEditorMsg::EqualizerPresetBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerEnabled))
+                    .ok();
+            }
+            ConfigEditorMsg::EqualizerBandBlurDown(index) if index + 1 >= EQ_BAND_COUNT => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::ChannelMixMono))
+                    .ok();
+            }
+            ConfigEditorMsg::ChannelMixMonoBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(
+                        EQ_BAND_COUNT - 1,
+                    )))
+                    .ok();
+            }
+            ConfigEditorMsg::ChannelMixMonoBlurDown | ConfigEditorMsg::EqualizerEnabledBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::ChannelMixBalance))
+                    .ok();
+            }
+            ConfigEditorMsg::ChannelMixBalanceBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::ChannelMixMono))
+                    .ok();
+            }
+            ConfigEditorMsg::ChannelMixBalanceBlurDown => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerEnabled))
+                    .ok();
+            }
+            ConfigEditorMsg::EqualizerPresetBlurDown => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(0)))
+                    .ok();
+            }
+            ConfigEditorMsg::EqualizerBandBlurDown(index) => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(index + 1)))
+                    .ok();
+            }
+            ConfigEditorMsg::EqualizerBandBlurUp(index) => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(index - 1)))
+                    .ok();
+            }
+
             // Focus of color page
             ConfigEditorMsg::ThemeSelectBlurDown | ConfigEditorMsg::LibraryBackgroundBlurUp => {
                 self.app
@@ -550,7 +605,7 @@ impl Model {
             }
 
             // Focus of key 2 page
-            KFMsg::PodcastSearchAddFeedBlurDown | KFMsg::LibraryDeleteBlurUp => {
+            KFMsg::TrackFavoriteBlurDown | KFMsg::LibraryDeleteBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::LibraryTagEditor,
@@ -606,7 +661,7 @@ impl Model {
                     .ok();
             }
 
-            KFMsg::PlaylistDeleteBlurDown | KFMsg::PlaylistSearchBlurUp => {
+            KFMsg::PlaylistDeleteBlurDown | KFMsg::DatabaseExportM3uBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::PlaylistDeleteAll,
@@ -614,7 +669,14 @@ impl Model {
                     .ok();
             }
 
-            KFMsg::PlaylistDeleteAllBlurDown | KFMsg::PlaylistShuffleBlurUp => {
+            KFMsg::PlaylistDeleteAllBlurDown | KFMsg::PlaylistSearchBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(
+                        IdKey::DatabaseExportM3u,
+                    )))
+                    .ok();
+            }
+            KFMsg::DatabaseExportM3uBlurDown | KFMsg::PlaylistShuffleBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::PlaylistSearch,
@@ -670,7 +732,7 @@ impl Model {
                     .ok();
             }
 
-            KFMsg::DatabaseAddAllBlurDown | KFMsg::PlaylistTqueueBlurUp => {
+            KFMsg::DatabaseAddAllBlurDown | KFMsg::TrackRatingUpBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::PlaylistLqueue,
@@ -678,7 +740,12 @@ impl Model {
                     .ok();
             }
 
-            KFMsg::PlaylistLqueueBlurDown | KFMsg::LibrarySwitchRootBlurUp => {
+            KFMsg::PlaylistLqueueBlurDown | KFMsg::PlaylistTqueueBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingUp)))
+                    .ok();
+            }
+            KFMsg::TrackRatingUpBlurDown | KFMsg::LibrarySwitchRootBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::PlaylistTqueue,
@@ -765,13 +832,51 @@ impl Model {
                     )))
                     .ok();
             }
-            KFMsg::PodcastRefreshAllFeedsBlurDown | KFMsg::LibraryTagEditorBlurUp => {
+            KFMsg::PodcastRefreshAllFeedsBlurDown | KFMsg::PlaylistMostPlayedBlurUp => {
                 self.app
                     .active(&Id::ConfigEditor(IdConfigEditor::Key(
                         IdKey::PodcastSearchAddFeed,
                     )))
                     .ok();
             }
+            KFMsg::PodcastSearchAddFeedBlurDown | KFMsg::PlaylistRecentlyPlayedBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(
+                        IdKey::PlaylistMostPlayed,
+                    )))
+                    .ok();
+            }
+            KFMsg::PlaylistMostPlayedBlurDown | KFMsg::PlaylistNeverPlayedBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(
+                        IdKey::PlaylistRecentlyPlayed,
+                    )))
+                    .ok();
+            }
+            KFMsg::PlaylistRecentlyPlayedBlurDown | KFMsg::DatabaseSortBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(
+                        IdKey::PlaylistNeverPlayed,
+                    )))
+                    .ok();
+            }
+            KFMsg::PlaylistNeverPlayedBlurDown | KFMsg::TrackRatingDownBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseSort)))
+                    .ok();
+            }
+            KFMsg::DatabaseSortBlurDown | KFMsg::TrackFavoriteBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(
+                        IdKey::TrackRatingDown,
+                    )))
+                    .ok();
+            }
+            KFMsg::TrackRatingDownBlurDown | KFMsg::LibraryTagEditorBlurUp => {
+                self.app
+                    .active(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackFavorite)))
+                    .ok();
+            }
         }
     }
 
@@ -855,6 +960,16 @@ impl Model {
             IdKey::PodcastRefreshAllFeeds => {
                 self.ke_key_config.podcast_refresh_all_feeds = *binding;
             }
+            IdKey::PlaylistMostPlayed => self.ke_key_config.playlist_most_played = *binding,
+            IdKey::PlaylistRecentlyPlayed => {
+                self.ke_key_config.playlist_recently_played = *binding;
+            }
+            IdKey::PlaylistNeverPlayed => self.ke_key_config.playlist_never_played = *binding,
+            IdKey::DatabaseSort => self.ke_key_config.database_sort = *binding,
+            IdKey::DatabaseExportM3u => self.ke_key_config.database_export_m3u = *binding,
+            IdKey::TrackRatingUp => self.ke_key_config.track_rating_up = *binding,
+            IdKey::TrackRatingDown => self.ke_key_config.track_rating_down = *binding,
+            IdKey::TrackFavorite => self.ke_key_config.track_favorite_toggle = *binding,
         }
     }
 
@@ -1,6 +1,7 @@
 use crate::ui::components::{
-    AlbumPhotoAlign, CEHeader, CEThemeSelectTable, ConfigCurrentlyPlayingTrackSymbol,
-    ConfigDatabaseAddAll, ConfigGlobalConfig, ConfigGlobalDown, ConfigGlobalGotoBottom,
+    AlbumPhotoAlign, CEHeader, CEThemeSelectTable, ChannelMixBalance, ChannelMixMono,
+    ConfigCurrentlyPlayingTrackSymbol, ConfigDatabaseAddAll, ConfigDatabaseExportM3u,
+    ConfigDatabaseSort, ConfigGlobalConfig, ConfigGlobalDown, ConfigGlobalGotoBottom,
     ConfigGlobalGotoTop, ConfigGlobalHelp, ConfigGlobalLayoutDatabase, ConfigGlobalLayoutPodcast,
     ConfigGlobalLayoutTreeview, ConfigGlobalLeft, ConfigGlobalLyricAdjustBackward,
     ConfigGlobalLyricAdjustForward, ConfigGlobalLyricCycle, ConfigGlobalPlayerNext,
@@ -17,16 +18,19 @@ use crate::ui::components::{
     ConfigLyricBackground, ConfigLyricBorder, ConfigLyricForeground, ConfigLyricTitle,
     ConfigPlaylistBackground, ConfigPlaylistBorder, ConfigPlaylistDelete, ConfigPlaylistDeleteAll,
     ConfigPlaylistForeground, ConfigPlaylistHighlight, ConfigPlaylistHighlightSymbol,
-    ConfigPlaylistLqueue, ConfigPlaylistModeCycle, ConfigPlaylistPlaySelected,
+    ConfigPlaylistLqueue, ConfigPlaylistModeCycle, ConfigPlaylistMostPlayed,
+    ConfigPlaylistNeverPlayed, ConfigPlaylistPlaySelected, ConfigPlaylistRecentlyPlayed,
     ConfigPlaylistSearch, ConfigPlaylistShuffle, ConfigPlaylistSwapDown, ConfigPlaylistSwapUp,
     ConfigPlaylistTitle, ConfigPlaylistTqueue, ConfigPodcastDeleteAllFeeds,
     ConfigPodcastDeleteFeed, ConfigPodcastEpDeleteFile, ConfigPodcastEpDownload,
     ConfigPodcastMarkAllPlayed, ConfigPodcastMarkPlayed, ConfigPodcastRefreshAllFeeds,
     ConfigPodcastRefreshFeed, ConfigPodcastSearchAddFeed, ConfigProgressBackground,
     ConfigProgressBorder, ConfigProgressForeground, ConfigProgressTitle, ConfigSavePopup,
-    ConfigSeekStep, ExitConfirmation, Footer, GlobalListener, KillDaemon, MusicDir, PlayerPort,
-    PlayerUseDiscord, PlayerUseMpris, PlaylistDisplaySymbol, PlaylistRandomAlbum,
-    PlaylistRandomTrack, PodcastDir, PodcastMaxRetries, PodcastSimulDownload, SaveLastPosition,
+    ConfigSeekStep, ConfigTrackFavorite, ConfigTrackRatingDown, ConfigTrackRatingUp, EqualizerBand,
+    EqualizerEnabled, EqualizerPreset, ExitConfirmation, Footer, GlobalListener, KillDaemon,
+    MusicDir, PlayerPort, PlayerUseDiscord, PlayerUseMpris, PlaylistDisplaySymbol,
+    PlaylistRandomAlbum, PlaylistRandomTrack, PodcastDir, PodcastMaxRetries, PodcastSimulDownload,
+    SaveLastPosition,
 };
 use include_dir::DirEntry;
 /**
@@ -52,7 +56,7 @@ use include_dir::DirEntry;
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use termusiclib::config::{LastPosition, SeekStep, Settings};
+use termusiclib::config::{EqPreset, LastPosition, SeekStep, Settings, BALANCE_MAX, EQ_BAND_COUNT};
 use termusiclib::utils::{draw_area_in_absolute, get_app_config_path, get_pin_yin};
 use termusiclib::THEME_DIR;
 
@@ -225,6 +229,89 @@ impl Model {
             .is_ok());
     }
 
+    pub fn view_config_editor_equalizer(&mut self) {
+        assert!(self
+            .terminal
+            .raw_mut()
+            .draw(|f| {
+                let chunks_main = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(0)
+                    .constraints(
+                        [
+                            Constraint::Length(3),
+                            Constraint::Min(3),
+                            Constraint::Length(1),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(f.size());
+
+                let chunks_middle = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .margin(0)
+                    .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)].as_ref())
+                    .split(chunks_main[1]);
+
+                // left: enabled, preset and the lower half of the bands,
+                // right: the upper half, mono and balance
+                let mut constraints = vec![Constraint::Length(3); 7];
+                constraints.push(Constraint::Min(0));
+                let chunks_middle_left = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(0)
+                    .constraints(constraints.clone())
+                    .split(chunks_middle[0]);
+                let chunks_middle_right = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(0)
+                    .constraints(constraints)
+                    .split(chunks_middle[1]);
+
+                self.app
+                    .view(&Id::ConfigEditor(IdConfigEditor::Header), f, chunks_main[0]);
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::EqualizerEnabled),
+                    f,
+                    chunks_middle_left[0],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::EqualizerPreset),
+                    f,
+                    chunks_middle_left[1],
+                );
+                let half = EQ_BAND_COUNT / 2;
+                for index in 0..EQ_BAND_COUNT {
+                    let chunk = if index < half {
+                        chunks_middle_left[index + 2]
+                    } else {
+                        chunks_middle_right[index - half]
+                    };
+                    self.app.view(
+                        &Id::ConfigEditor(IdConfigEditor::EqualizerBand(index)),
+                        f,
+                        chunk,
+                    );
+                }
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::ChannelMixMono),
+                    f,
+                    chunks_middle_right[EQ_BAND_COUNT - half],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::ChannelMixBalance),
+                    f,
+                    chunks_middle_right[EQ_BAND_COUNT - half + 1],
+                );
+
+                self.app
+                    .view(&Id::ConfigEditor(IdConfigEditor::Footer), f, chunks_main[2]);
+
+                Self::view_config_editor_commons(f, &mut self.app);
+            })
+            .is_ok());
+    }
+
     fn view_config_editor_commons(f: &mut Frame<'_>, app: &mut Application<Id, Msg, NoUserEvent>) {
         // -- popups
         if app.mounted(&Id::ConfigEditor(IdConfigEditor::ConfigSavePopup)) {
@@ -1281,6 +1368,65 @@ impl Model {
             _ => 8,
         };
 
+        let playlist_most_played_len = match self.app.state(&Id::ConfigEditor(IdConfigEditor::Key(
+            IdKey::PlaylistMostPlayed,
+        ))) {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let playlist_recently_played_len = match self.app.state(&Id::ConfigEditor(
+            IdConfigEditor::Key(IdKey::PlaylistRecentlyPlayed),
+        )) {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let playlist_never_played_len = match self.app.state(&Id::ConfigEditor(
+            IdConfigEditor::Key(IdKey::PlaylistNeverPlayed),
+        )) {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let database_sort_len = match self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseSort)))
+        {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let database_export_m3u_len = match self.app.state(&Id::ConfigEditor(IdConfigEditor::Key(
+            IdKey::DatabaseExportM3u,
+        ))) {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let track_rating_up_len = match self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingUp)))
+        {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let track_rating_down_len = match self.app.state(&Id::ConfigEditor(IdConfigEditor::Key(
+            IdKey::TrackRatingDown,
+        ))) {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
+        let track_favorite_len = match self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackFavorite)))
+        {
+            Ok(State::One(_)) => 3,
+            _ => 8,
+        };
+
         assert!(self
             .terminal
             .raw_mut()
@@ -1326,6 +1472,7 @@ impl Model {
                             Constraint::Length(select_library_search_youtube_len),
                             Constraint::Length(select_playlist_delete_len),
                             Constraint::Length(select_playlist_delete_all_len),
+                            Constraint::Length(database_export_m3u_len),
                             Constraint::Min(0),
                         ]
                         .as_ref(),
@@ -1344,6 +1491,7 @@ impl Model {
                             Constraint::Length(select_playlist_swap_up_len),
                             Constraint::Length(select_database_add_all_len),
                             Constraint::Length(select_playlist_lqueue_len),
+                            Constraint::Length(track_rating_up_len),
                             Constraint::Min(0),
                         ]
                         .as_ref(),
@@ -1379,11 +1527,12 @@ impl Model {
                             Constraint::Length(podcast_refresh_feed_len),
                             Constraint::Length(podcast_refresh_all_feeds_len),
                             Constraint::Length(podcast_search_add_feed_len),
-                            // Constraint::Length(podcast_mark_played_len),
-                            // Constraint::Length(podcast_mark_all_played_len),
-                            // Constraint::Length(podcast_ep_download_len),
-                            // Constraint::Length(podcast_ep_delete_file_len),
-                            // Constraint::Length(podcast_delete_feed_len),
+                            Constraint::Length(playlist_most_played_len),
+                            Constraint::Length(playlist_recently_played_len),
+                            Constraint::Length(playlist_never_played_len),
+                            Constraint::Length(database_sort_len),
+                            Constraint::Length(track_rating_down_len),
+                            Constraint::Length(track_favorite_len),
                             Constraint::Min(0),
                         ]
                         .as_ref(),
@@ -1550,6 +1699,46 @@ impl Model {
                     f,
                     chunks_middle_column4[3],
                 );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistMostPlayed)),
+                    f,
+                    chunks_middle_column4[4],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistRecentlyPlayed)),
+                    f,
+                    chunks_middle_column4[5],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistNeverPlayed)),
+                    f,
+                    chunks_middle_column4[6],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseSort)),
+                    f,
+                    chunks_middle_column4[7],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseExportM3u)),
+                    f,
+                    chunks_middle_column1[9],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingUp)),
+                    f,
+                    chunks_middle_column2[8],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingDown)),
+                    f,
+                    chunks_middle_column4[8],
+                );
+                self.app.view(
+                    &Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackFavorite)),
+                    f,
+                    chunks_middle_column4[9],
+                );
                 Self::view_config_editor_commons(f, &mut self.app);
             })
             .is_ok());
@@ -1712,6 +1901,50 @@ impl Model {
                 vec![]
             )
             .is_ok());
+
+        // Mount equalizer page
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::EqualizerEnabled),
+                Box::new(EqualizerEnabled::new(&self.config)),
+                vec![]
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::EqualizerPreset),
+                Box::new(EqualizerPreset::new(&self.config)),
+                vec![]
+            )
+            .is_ok());
+        for index in 0..EQ_BAND_COUNT {
+            assert!(self
+                .app
+                .remount(
+                    Id::ConfigEditor(IdConfigEditor::EqualizerBand(index)),
+                    Box::new(EqualizerBand::new(&self.config, index)),
+                    vec![]
+                )
+                .is_ok());
+        }
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::ChannelMixMono),
+                Box::new(ChannelMixMono::new(&self.config)),
+                vec![]
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::ChannelMixBalance),
+                Box::new(ChannelMixBalance::new(&self.config)),
+                vec![]
+            )
+            .is_ok());
         let config = self.config.clone();
         self.remount_config_color(&config);
 
@@ -2454,6 +2687,70 @@ impl Model {
                 vec![],
             )
             .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistMostPlayed)),
+                Box::new(ConfigPlaylistMostPlayed::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistRecentlyPlayed)),
+                Box::new(ConfigPlaylistRecentlyPlayed::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::PlaylistNeverPlayed)),
+                Box::new(ConfigPlaylistNeverPlayed::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseSort)),
+                Box::new(ConfigDatabaseSort::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseExportM3u)),
+                Box::new(ConfigDatabaseExportM3u::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingUp)),
+                Box::new(ConfigTrackRatingUp::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingDown)),
+                Box::new(ConfigTrackRatingDown::new(config)),
+                vec![],
+            )
+            .is_ok());
+        assert!(self
+            .app
+            .remount(
+                Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackFavorite)),
+                Box::new(ConfigTrackFavorite::new(config)),
+                vec![],
+            )
+            .is_ok());
         self.theme_select_sync();
     }
 
@@ -2547,6 +2844,33 @@ impl Model {
             .umount(&Id::ConfigEditor(IdConfigEditor::PlayerPort))
             .is_ok());
 
+        assert!(self
+            .app
+            .umount(&Id::ConfigEditor(IdConfigEditor::EqualizerEnabled))
+            .is_ok());
+
+        assert!(self
+            .app
+            .umount(&Id::ConfigEditor(IdConfigEditor::EqualizerPreset))
+            .is_ok());
+
+        for index in 0..EQ_BAND_COUNT {
+            assert!(self
+                .app
+                .umount(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(index)))
+                .is_ok());
+        }
+
+        assert!(self
+            .app
+            .umount(&Id::ConfigEditor(IdConfigEditor::ChannelMixMono))
+            .is_ok());
+
+        assert!(self
+            .app
+            .umount(&Id::ConfigEditor(IdConfigEditor::ChannelMixBalance))
+            .is_ok());
+
         assert!(self
             .app
             .umount(&Id::ConfigEditor(IdConfigEditor::CEThemeSelect))
@@ -2951,6 +3275,40 @@ impl Model {
                 IdKey::PodcastSearchAddFeed,
             )))
             .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(
+                IdKey::PlaylistMostPlayed,
+            )))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(
+                IdKey::PlaylistRecentlyPlayed,
+            )))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(
+                IdKey::PlaylistNeverPlayed,
+            )))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::DatabaseSort)))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(
+                IdKey::DatabaseExportM3u,
+            )))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackRatingUp)))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(
+                IdKey::TrackRatingDown,
+            )))
+            .ok();
+        self.app
+            .umount(&Id::ConfigEditor(IdConfigEditor::Key(IdKey::TrackFavorite)))
+            .ok();
         assert!(self
             .app
             .remount(
@@ -2971,7 +3329,8 @@ impl Model {
 
             ConfigEditorLayout::Color => self.config_layout = ConfigEditorLayout::Key1,
             ConfigEditorLayout::Key1 => self.config_layout = ConfigEditorLayout::Key2,
-            ConfigEditorLayout::Key2 => self.config_layout = ConfigEditorLayout::General,
+            ConfigEditorLayout::Key2 => self.config_layout = ConfigEditorLayout::Equalizer,
+            ConfigEditorLayout::Equalizer => self.config_layout = ConfigEditorLayout::General,
         }
 
         let layout = self.config_layout.clone();
@@ -3002,6 +3361,10 @@ impl Model {
                     IdKey::LibraryTagEditor,
                 )))
                 .ok(),
+            ConfigEditorLayout::Equalizer => self
+                .app
+                .active(&Id::ConfigEditor(IdConfigEditor::EqualizerEnabled))
+                .ok(),
         };
     }
 
@@ -3182,6 +3545,55 @@ impl Model {
                 }
             }
         }
+
+        if let Ok(State::One(StateValue::Usize(enabled))) = self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::EqualizerEnabled))
+        {
+            self.config.player_equalizer.enabled = matches!(enabled, 0);
+        }
+
+        if let Ok(State::One(StateValue::Usize(preset))) = self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::EqualizerPreset))
+        {
+            if let Some(preset) = EqPreset::ALL.get(preset) {
+                self.config.player_equalizer.preset = *preset;
+            }
+        }
+
+        for index in 0..EQ_BAND_COUNT {
+            if let Ok(State::One(StateValue::String(gain))) = self
+                .app
+                .state(&Id::ConfigEditor(IdConfigEditor::EqualizerBand(index)))
+            {
+                match gain.parse::<f32>() {
+                    Ok(gain) if gain.is_finite() => {
+                        self.config.player_equalizer.custom_gains[index] = gain;
+                    }
+                    _ => bail!(" Equalizer gain \"{gain}\" is not a number. "),
+                }
+            }
+        }
+
+        if let Ok(State::One(StateValue::Usize(mono))) = self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::ChannelMixMono))
+        {
+            self.config.player_channel_mix.mono = matches!(mono, 0);
+        }
+
+        if let Ok(State::One(StateValue::String(balance))) = self
+            .app
+            .state(&Id::ConfigEditor(IdConfigEditor::ChannelMixBalance))
+        {
+            match balance.parse::<i16>() {
+                Ok(balance) if balance.abs() <= BALANCE_MAX => {
+                    self.config.player_channel_mix.balance = balance;
+                }
+                _ => bail!(" Balance \"{balance}\" is not between -100 and 100. "),
+            }
+        }
         Ok(())
     }
 
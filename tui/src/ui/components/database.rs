@@ -1,9 +1,12 @@
 use crate::ui::Model;
+use anyhow::Result;
 use std::path::Path;
 use termusiclib::config::{Keys, Settings};
-use termusiclib::sqlite::SearchCriteria;
-use termusiclib::types::{DBMsg, Id, Msg};
+use termusiclib::sqlite::{SearchCriteria, TrackSort};
+use termusiclib::track::{write_rating_tags, MAX_RATING};
+use termusiclib::types::{DBMsg, Id, Msg, RatingChange};
 use termusiclib::utils::{is_playlist, playlist_get_vec};
+use termusicplayback::Playlist;
 use tui_realm_stdlib::List;
 use tuirealm::command::{Cmd, CmdResult, Direction, Position};
 use tuirealm::props::{Alignment, BorderType, TableBuilder, TextSpan};
@@ -68,6 +71,12 @@ impl DBListCriteria {
                         .add_col(TextSpan::from("Directory"))
                         .add_row()
                         .add_col(TextSpan::from("Playlists"))
+                        .add_row()
+                        .add_col(TextSpan::from("Play count"))
+                        .add_row()
+                        .add_col(TextSpan::from("Last played"))
+                        .add_row()
+                        .add_col(TextSpan::from("Smart playlists"))
                         .build(),
                 ),
             on_key_tab,
@@ -293,6 +302,12 @@ impl Component<Msg, NoUserEvent> for DBListSearchResult {
                 }
                 CmdResult::None
             }
+            Event::Keyboard(keyevent) if keyevent == self.keys.database_export_m3u.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.state() {
+                    return Some(Msg::DataBase(DBMsg::ExportM3u(index)));
+                }
+                CmdResult::None
+            }
             Event::Keyboard(KeyEvent {
                 code: Key::Tab,
                 modifiers: KeyModifiers::NONE,
@@ -438,6 +453,32 @@ impl Component<Msg, NoUserEvent> for DBListSearchTracks {
             Event::Keyboard(keyevent) if keyevent == self.keys.database_add_all.key_event() => {
                 return Some(Msg::DataBase(DBMsg::AddAllToPlaylist))
             }
+            Event::Keyboard(keyevent) if keyevent == self.keys.database_sort.key_event() => {
+                return Some(Msg::DataBase(DBMsg::SortTracks))
+            }
+            Event::Keyboard(keyevent) if keyevent == self.keys.track_rating_up.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.state() {
+                    return Some(Msg::DataBase(DBMsg::Rate(index, RatingChange::Up)));
+                }
+                CmdResult::None
+            }
+            Event::Keyboard(keyevent) if keyevent == self.keys.track_rating_down.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.state() {
+                    return Some(Msg::DataBase(DBMsg::Rate(index, RatingChange::Down)));
+                }
+                CmdResult::None
+            }
+            Event::Keyboard(keyevent)
+                if keyevent == self.keys.track_favorite_toggle.key_event() =>
+            {
+                if let State::One(StateValue::Usize(index)) = self.state() {
+                    return Some(Msg::DataBase(DBMsg::Rate(
+                        index,
+                        RatingChange::ToggleFavorite,
+                    )));
+                }
+                CmdResult::None
+            }
 
             Event::Keyboard(keyevent) if keyevent == self.keys.library_search.key_event() => {
                 return Some(Msg::GeneralSearch(crate::ui::GSMsg::PopupShowDatabase))
@@ -458,9 +499,18 @@ impl Model {
                 table.add_row();
             }
 
+            let stats = format!(
+                "[{:>3}p {:>3}s {:^10}] {:<6}",
+                record.play_count,
+                record.skip_count,
+                record.last_played_formatted(),
+                record.rating_formatted()
+            );
             table
                 .add_col(TextSpan::from(format!("{}", idx + 1)))
                 .add_col(TextSpan::from(" "))
+                .add_col(TextSpan::from(stats))
+                .add_col(TextSpan::from(" "))
                 .add_col(TextSpan::from(record.name.to_string()));
         }
         if self.db_search_results.is_empty() {
@@ -476,6 +526,18 @@ impl Model {
             )
             .ok();
 
+        let title = match self.db_sort {
+            TrackSort::Name => " Tracks ".to_string(),
+            sort => format!(" Tracks (by {sort}) "),
+        };
+        self.app
+            .attr(
+                &Id::DBListSearchTracks,
+                tuirealm::Attribute::Title,
+                tuirealm::AttrValue::Title((title, Alignment::Left)),
+            )
+            .ok();
+
         // self.playlist_update_title();
     }
     pub fn database_sync_results(&mut self) {
@@ -536,18 +598,45 @@ impl Model {
     }
 
     pub fn database_update_search_results(&mut self) {
+        self.database_read_search_results();
+        self.database_sync_results();
+        self.app.active(&Id::DBListSearchResult).ok();
+    }
+
+    fn database_read_search_results(&mut self) {
         match self.db_criteria {
             SearchCriteria::Playlist => {
                 self.db_search_results = self.database_get_playlist();
             }
+            SearchCriteria::SmartPlaylist => {
+                self.db_search_results = self
+                    .config
+                    .library_smart_playlists
+                    .iter()
+                    .map(|playlist| playlist.name.clone())
+                    .collect();
+            }
             _ => {
                 if let Ok(results) = self.db.get_criterias(&self.db_criteria) {
                     self.db_search_results = results;
                 }
             }
         }
+    }
+
+    /// Read the shown search results and tracks again, after the library database changed
+    pub fn database_refresh(&mut self) {
+        if self.db_search_results.is_empty() {
+            return;
+        }
+        self.database_read_search_results();
+        self.db_search_tracks = self
+            .db_search_tracks
+            .iter()
+            .filter_map(|track| self.db.get_record_by_path(&track.file).ok())
+            .collect();
         self.database_sync_results();
-        self.app.active(&Id::DBListSearchResult).ok();
+        self.database_sync_tracks();
     }
 
     fn database_get_playlist(&self) -> Vec<String> {
@@ -582,6 +671,17 @@ impl Model {
                     }
                 }
             }
+            SearchCriteria::SmartPlaylist => {
+                if let Some(playlist) = self.config.library_smart_playlists.get(index) {
+                    match self.db.get_smart_playlist(playlist) {
+                        Ok(vec) => self.db_search_tracks = vec,
+                        Err(e) => {
+                            self.mount_error_popup(format!("smart playlist error: {e:#}"));
+                            return;
+                        }
+                    }
+                }
+            }
             _ => {
                 if let Ok(vec) = self
                     .db
@@ -591,11 +691,87 @@ impl Model {
                 };
             }
         }
+        // tracks are already ordered by name, or by their order in the (smart) playlist
+        if self.db_sort != TrackSort::Name {
+            self.db_sort.sort(&mut self.db_search_tracks);
+        }
 
         self.database_sync_tracks();
         self.app.active(&Id::DBListSearchTracks).ok();
     }
 
+    /// Apply `change` to the rating of the track at `file`, also to its tags if `library_rating_write_tags`
+    pub fn database_rate(&mut self, file: &str, change: RatingChange) {
+        let (mut rating, mut favorite) = self.db.get_rating(file);
+        let result = match change {
+            RatingChange::Up => {
+                rating = (rating + 1).min(MAX_RATING);
+                self.database_set_rating(file, rating)
+            }
+            RatingChange::Down => {
+                rating = rating.saturating_sub(1);
+                self.database_set_rating(file, rating)
+            }
+            RatingChange::ToggleFavorite => self
+                .db
+                .toggle_favorite(file)
+                .map(|v| favorite = v)
+                .map_err(anyhow::Error::from),
+        };
+        if let Err(e) = result {
+            self.mount_error_popup(format!("rating error: {e}"));
+            return;
+        }
+
+        if let Some(record) = self.db_search_tracks.iter_mut().find(|r| r.file == file) {
+            record.rating = rating;
+            record.favorite = favorite;
+            self.database_sync_tracks();
+        }
+        let name = Path::new(file)
+            .file_name()
+            .map_or_else(|| file.into(), |v| v.to_string_lossy());
+        let stars = "★".repeat(usize::from(rating));
+        let heart = if favorite { " ♥" } else { "" };
+        self.show_message_timeout_label_help(format!(" {name}: {stars}{heart} "), None, None, None);
+    }
+
+    fn database_set_rating(&mut self, file: &str, rating: u8) -> Result<()> {
+        self.db.set_rating(file, rating)?;
+        if self.config.library_rating_write_tags {
+            write_rating_tags(Path::new(file), rating)?;
+        }
+        Ok(())
+    }
+
+    /// Export the tracks of the smart playlist at `index` to `<name>.m3u`, saved like the playlist
+    /// next to the selected library node, asking before an existing file is replaced
+    pub fn database_export_m3u(&mut self, index: usize) -> Result<()> {
+        if self.db_criteria != SearchCriteria::SmartPlaylist {
+            return Ok(());
+        }
+        let Some(playlist) = self.config.library_smart_playlists.get(index) else {
+            return Ok(());
+        };
+        let tracks = self.db.get_smart_playlist(playlist)?;
+        let filename = playlist.name.replace('/', "-");
+
+        let mut export = Playlist::default();
+        export.add_playlist(tracks.iter().map(|track| track.file.as_str()).collect())?;
+        self.m3u_export = Some(export);
+        let result = self.playlist_save_m3u_before(&filename);
+        if result.is_err() {
+            self.m3u_export = None;
+        }
+        result
+    }
+
+    pub fn database_sort_tracks(&mut self) {
+        self.db_sort = self.db_sort.next();
+        self.db_sort.sort(&mut self.db_search_tracks);
+        self.database_sync_tracks();
+    }
+
     #[allow(unused)]
     pub fn database_reload(&mut self) {
         assert!(self
@@ -1,5 +1,5 @@
 use crate::config::{Keys, Settings};
-use crate::ui::{Id, LIMsg, Model, Msg, TEMsg, YSMsg};
+use crate::ui::{Id, LIMsg, Model, Msg, RatingChange, TEMsg, YSMsg};
 use crate::utils::get_pin_yin;
 use anyhow::{bail, Context, Result};
 use std::fs::{remove_dir_all, remove_file, rename};
@@ -220,6 +220,29 @@ impl Component<Msg, NoUserEvent> for MusicLibrary {
             {
                 return Some(Msg::YoutubeSearch(YSMsg::InputPopupShow))
             }
+            Event::Keyboard(keyevent) if keyevent == self.keys.track_rating_up.key_event() => {
+                let current_node = self.component.tree_state().selected().unwrap();
+                return Some(Msg::Library(LIMsg::Rate(
+                    current_node.to_string(),
+                    RatingChange::Up,
+                )));
+            }
+            Event::Keyboard(keyevent) if keyevent == self.keys.track_rating_down.key_event() => {
+                let current_node = self.component.tree_state().selected().unwrap();
+                return Some(Msg::Library(LIMsg::Rate(
+                    current_node.to_string(),
+                    RatingChange::Down,
+                )));
+            }
+            Event::Keyboard(keyevent)
+                if keyevent == self.keys.track_favorite_toggle.key_event() =>
+            {
+                let current_node = self.component.tree_state().selected().unwrap();
+                return Some(Msg::Library(LIMsg::Rate(
+                    current_node.to_string(),
+                    RatingChange::ToggleFavorite,
+                )));
+            }
             Event::Keyboard(keyevent)
                 if keyevent == self.keys.library_tag_editor_open.key_event() =>
             {
@@ -289,7 +312,7 @@ impl Model {
     }
 
     pub fn library_reload_with_node_focus(&mut self, node: Option<&str>) {
-        self.db.sync_database(self.path.as_path());
+        self.library_sync();
         self.database_reload();
         self.library_reload_tree();
         if let Some(n) = node {
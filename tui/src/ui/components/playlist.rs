@@ -5,12 +5,12 @@ use rand::seq::SliceRandom;
 use std::borrow::Cow;
 use std::path::Path;
 use termusiclib::sqlite::SearchCriteria;
-use termusiclib::sqlite::TrackForDB;
-use termusiclib::types::{GSMsg, Id, Msg, PLMsg};
+use termusiclib::sqlite::{SmartSelection, TrackForDB};
+use termusiclib::types::{GSMsg, Id, Msg, PLMsg, RatingChange};
 use termusiclib::utils::{filetype_supported, get_parent_folder, is_playlist, playlist_get_vec};
 use termusiclib::{
     config::{Keys, Settings},
-    track::Track,
+    track::{MediaType, Track},
 };
 use termusicplayback::PlayerCmd;
 
@@ -181,6 +181,42 @@ impl Component<Msg, NoUserEvent> for Playlist {
             Event::Keyboard(key) if key == self.keys.playlist_cmus_tqueue.key_event() => {
                 return Some(Msg::Playlist(PLMsg::CmusTQueue));
             }
+            Event::Keyboard(key) if key == self.keys.track_rating_up.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.component.state() {
+                    return Some(Msg::Playlist(PLMsg::Rate(index, RatingChange::Up)));
+                }
+                CmdResult::None
+            }
+            Event::Keyboard(key) if key == self.keys.track_rating_down.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.component.state() {
+                    return Some(Msg::Playlist(PLMsg::Rate(index, RatingChange::Down)));
+                }
+                CmdResult::None
+            }
+            Event::Keyboard(key) if key == self.keys.track_favorite_toggle.key_event() => {
+                if let State::One(StateValue::Usize(index)) = self.component.state() {
+                    return Some(Msg::Playlist(PLMsg::Rate(
+                        index,
+                        RatingChange::ToggleFavorite,
+                    )));
+                }
+                CmdResult::None
+            }
+            Event::Keyboard(key) if key == self.keys.playlist_most_played.key_event() => {
+                return Some(Msg::Playlist(PLMsg::SmartSelection(
+                    SmartSelection::MostPlayed,
+                )));
+            }
+            Event::Keyboard(key) if key == self.keys.playlist_recently_played.key_event() => {
+                return Some(Msg::Playlist(PLMsg::SmartSelection(
+                    SmartSelection::RecentlyPlayed,
+                )));
+            }
+            Event::Keyboard(key) if key == self.keys.playlist_never_played.key_event() => {
+                return Some(Msg::Playlist(PLMsg::SmartSelection(
+                    SmartSelection::NeverPlayed,
+                )));
+            }
             _ => CmdResult::None,
         };
         Some(Msg::None)
@@ -262,13 +298,23 @@ impl Model {
 
     fn playlist_add_playlist(&mut self, current_node: &str) -> Result<()> {
         let vec = playlist_get_vec(current_node)?;
-        let vec_str: Vec<&str> = vec.iter().map(std::convert::AsRef::as_ref).collect();
-        self.playlist.add_playlist(vec_str)?;
-        self.player_sync_playlist()?;
-        self.playlist_sync();
+        self.playlist_add_uris(vec);
         Ok(())
     }
 
+    /// Ask the server to append the given file paths / urls to the playlist
+    ///
+    /// The local playlist gets updated once the server reports the change.
+    fn playlist_add_uris(&mut self, tracks: Vec<String>) {
+        if tracks.is_empty() {
+            return;
+        }
+        self.command(&PlayerCmd::PlaylistAddTracks {
+            tracks,
+            at_index: None,
+        });
+    }
+
     pub fn playlist_add_episode(&mut self, episode_index: usize) -> Result<()> {
         if self.podcasts.is_empty() {
             return Ok(());
@@ -281,9 +327,8 @@ impl Model {
             .episodes
             .get(episode_index)
             .ok_or_else(|| anyhow!("get episode selected failed."))?;
-        self.playlist.add_episode(episode_selected);
-        self.player_sync_playlist()?;
-        self.playlist_sync();
+        let url = episode_selected.url.clone();
+        self.playlist_add_uris(vec![url]);
         Ok(())
     }
 
@@ -294,17 +339,10 @@ impl Model {
         }
         if p.is_dir() {
             let new_items_vec = Self::library_dir_children(p);
-            let new_items_str_vec: Vec<&str> = new_items_vec
-                .iter()
-                .map(std::convert::AsRef::as_ref)
-                .collect();
-            self.playlist.add_playlist(new_items_str_vec)?;
-            self.player_sync_playlist()?;
-            self.playlist_sync();
+            self.playlist_add_uris(new_items_vec);
             return Ok(());
         }
         self.playlist_add_item(current_node)?;
-        self.playlist_sync();
         Ok(())
     }
 
@@ -313,22 +351,13 @@ impl Model {
             self.playlist_add_playlist(current_node)?;
             return Ok(());
         }
-        let vec = vec![current_node];
-        self.playlist.add_playlist(vec)?;
-        self.player_sync_playlist()?;
+        self.playlist_add_uris(vec![current_node.to_string()]);
         Ok(())
     }
 
     pub fn playlist_add_all_from_db(&mut self, vec: &[TrackForDB]) {
-        let vec2: Vec<String> = vec.iter().map(|f| f.file.clone()).collect();
-        let vec3: Vec<&str> = vec2.iter().map(std::convert::AsRef::as_ref).collect();
-        if let Err(e) = self.playlist.add_playlist(vec3) {
-            self.mount_error_popup(format!("Error add all from db: {e}"));
-        }
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
-        }
-        self.playlist_sync();
+        let files: Vec<String> = vec.iter().map(|f| f.file.clone()).collect();
+        self.playlist_add_uris(files);
     }
 
     pub fn playlist_add_cmus_lqueue(&mut self) {
@@ -345,6 +374,16 @@ impl Model {
         self.playlist_add_all_from_db(&vec);
     }
 
+    pub fn playlist_add_smart_selection(&mut self, selection: SmartSelection) {
+        match self
+            .db
+            .get_smart_selection(selection, self.config.playlist_select_random_track_quantity)
+        {
+            Ok(vec) => self.playlist_add_all_from_db(&vec),
+            Err(e) => self.mount_error_popup(format!("error in smart selection: {e}")),
+        }
+    }
+
     fn playlist_sync_podcasts(&mut self) {
         let mut table: TableBuilder = TableBuilder::default();
 
@@ -447,35 +486,61 @@ impl Model {
         if self.playlist.is_empty() {
             return;
         }
-        self.playlist.remove(index);
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
-        }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistRemoveTracks {
+            at_index: index,
+            amount: 1,
+        });
     }
 
     pub fn playlist_clear(&mut self) {
-        self.playlist.clear();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
-        }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistClear);
     }
 
     pub fn playlist_shuffle(&mut self) {
-        self.playlist.shuffle();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
+        self.command(&PlayerCmd::PlaylistShuffle);
+    }
+
+    pub fn playlist_swap_down(&mut self, index: usize) {
+        if index + 1 >= self.playlist.len() {
+            return;
         }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistSwapTracks {
+            index_a: index,
+            index_b: index + 1,
+        });
+    }
+
+    pub fn playlist_swap_up(&mut self, index: usize) {
+        if index == 0 || index >= self.playlist.len() {
+            return;
+        }
+        self.command(&PlayerCmd::PlaylistSwapTracks {
+            index_a: index,
+            index_b: index - 1,
+        });
     }
 
+    /// Remove all local tracks from the playlist whose file does not exist anymore
     pub fn playlist_update_library_delete(&mut self) {
-        self.playlist.remove_deleted_items();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
+        let deleted: Vec<usize> = self
+            .playlist
+            .tracks()
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| {
+                track.media_type == Some(MediaType::Music)
+                    && track.file().map_or(false, |p| !Path::new(p).exists())
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        // remove from the back, so that the earlier indexes stay valid
+        for index in deleted.into_iter().rev() {
+            self.command(&PlayerCmd::PlaylistRemoveTracks {
+                at_index: index,
+                amount: 1,
+            });
         }
-        self.playlist_sync();
     }
 
     pub fn playlist_update_title(&mut self) {
@@ -495,11 +560,7 @@ impl Model {
             .ok();
     }
     pub fn playlist_play_selected(&mut self, index: usize) {
-        self.playlist.set_current_track_index(index);
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(format!("sync playlist error: {e}"));
-        }
-        self.command(&PlayerCmd::PlaySelected);
+        self.command(&PlayerCmd::PlayIndex(index));
     }
 
     pub fn playlist_update_search(&mut self, input: &str) {
@@ -622,7 +683,10 @@ impl Model {
     }
 
     pub fn playlist_save_m3u(&mut self, filename: &str) -> Result<()> {
-        self.playlist.save_m3u(filename)?;
+        match self.m3u_export.take() {
+            Some(playlist) => playlist.save_m3u(filename)?,
+            None => self.playlist.save_m3u(filename)?,
+        }
 
         self.library_reload_with_node_focus(Some(filename));
 
@@ -379,6 +379,19 @@ impl HelpPopup {
                         ]))
                         .add_col(Self::comment("Select random tracks/albums to playlist"))
                         .add_row()
+                        .add_col(Self::key(&[keys.track_rating_up, keys.track_rating_down]))
+                        .add_col(Self::comment("Rate selected track up/down"))
+                        .add_row()
+                        .add_col(Self::key(&[keys.track_favorite_toggle]))
+                        .add_col(Self::comment("Toggle selected track as favorite"))
+                        .add_row()
+                        .add_col(Self::key(&[
+                            keys.playlist_most_played,
+                            keys.playlist_recently_played,
+                            keys.playlist_never_played,
+                        ]))
+                        .add_col(Self::comment("Add most/recently/never played tracks"))
+                        .add_row()
                         .add_col(TextSpan::new("Database").bold().fg(Color::LightYellow))
                         .add_row()
                         .add_col(Self::key(&[keys.global_right, keys.database_add_all]))
@@ -387,6 +400,12 @@ impl HelpPopup {
                         .add_col(Self::key(&[keys.library_search]))
                         .add_col(Self::comment("Search in database"))
                         .add_row()
+                        .add_col(Self::key(&[keys.database_sort]))
+                        .add_col(Self::comment("Sort tracks by name/plays/skips/last played"))
+                        .add_row()
+                        .add_col(Self::key(&[keys.database_export_m3u]))
+                        .add_col(Self::comment("Export smart playlist to m3u"))
+                        .add_row()
                         .add_col(TextSpan::new("Podcast").bold().fg(Color::LightYellow))
                         .add_row()
                         .add_col(Self::key(&[keys.podcast_search_add_feed]))
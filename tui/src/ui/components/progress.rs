@@ -43,9 +43,9 @@ impl Progress {
                 .label("Progress")
                 .title(
                     format!(
-                        " Status: Stopped | Volume: {} | Speed: {:^.1} ",
+                        " Status: Stopped | Volume: {} | Speed: {:^.2} ",
                         config.player_volume,
-                        config.player_speed as f32 / 10.0,
+                        config.player_speed_percent as f32 / 100.0,
                     ),
                     Alignment::Center,
                 )
@@ -85,20 +85,20 @@ impl Model {
             match track.media_type {
                 Some(MediaType::Music | MediaType::LiveRadio) => {
                     progress_title = format!(
-                        " Status: {} | Volume: {} | Speed: {:^.1} | Gapless: {} ",
+                        " Status: {} | Volume: {} | Speed: {:^.2} | Gapless: {} ",
                         self.playlist.status(),
                         self.config.player_volume,
-                        self.config.player_speed as f32 / 10.0,
+                        self.config.player_speed_percent as f32 / 100.0,
                         gapless,
                     );
                 }
                 Some(MediaType::Podcast) => {
                     progress_title = format!(
-                        " Status: {} {:^.20} | Volume: {} | Speed: {:^.1} | Gapless: {} ",
+                        " Status: {} {:^.20} | Volume: {} | Speed: {:^.2} | Gapless: {} ",
                         self.playlist.status(),
                         track.title().unwrap_or("Unknown title"),
                         self.config.player_volume,
-                        self.config.player_speed as f32 / 10.0,
+                        self.config.player_speed_percent as f32 / 100.0,
                         gapless,
                     );
                 }
@@ -23,24 +23,27 @@
  */
 pub mod components;
 pub mod model;
-mod playback;
+pub mod playback;
 
+use crate::connection::Client;
 use anyhow::Result;
 use model::{Model, TermusicLayout};
 use playback::Playback;
 use std::time::Duration;
-use sysinfo::System;
+use sysinfo::{Pid, System};
 use termusiclib::config::Settings;
 pub use termusiclib::types::*;
-use termusicplayback::player::music_player_client::MusicPlayerClient;
-use termusicplayback::{PlayerCmd, PlayerProgress, Status};
-use tokio::sync::mpsc::{self, UnboundedReceiver};
-use tonic::transport::Channel;
+use termusicplayback::player::StreamUpdates;
+use termusicplayback::{PlayerCmd, PlayerProgress, Status, UpdateEvents};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tonic::Streaming;
 use tuirealm::application::PollStrategy;
 use tuirealm::{Application, Update};
 // -- internal
 
 const FORCED_REDRAW_INTERVAL: Duration = Duration::from_millis(1000);
+/// Longest wait between attempts to subscribe to the server updates again
+const RESUBSCRIBE_MAX_DELAY: Duration = Duration::from_secs(10);
 
 // Let's define the messages handled by our app. NOTE: it must derive `PartialEq`
 
@@ -49,6 +52,7 @@ pub struct UI {
     model: Model,
     playback: Playback,
     cmd_rx: UnboundedReceiver<PlayerCmd>,
+    stream_rx: UnboundedReceiver<UpdateEvents>,
 }
 
 impl UI {
@@ -61,23 +65,101 @@ impl UI {
         // }
     }
     /// Instantiates a new Ui
-    pub async fn new(config: &Settings, client: MusicPlayerClient<Channel>) -> Result<Self> {
+    pub async fn new(config: &Settings, client: Client) -> Result<Self> {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
         let mut model = Model::new(config, cmd_tx).await;
+        let mut playback = Playback::new(client.clone());
+        // before the first sync, which is left to the server if it watches the library
+        match playback.get_library_watch().await {
+            Ok(dirs) => model.library_watched_dirs = dirs,
+            Err(e) => warn!("Error getting the library watch of the server: {e:#}"),
+        }
         model.init_config();
-        let playback = Playback::new(client);
+        let (stream_tx, stream_rx) = mpsc::unbounded_channel();
+        let stream = playback
+            .subscribe_to_stream_updates()
+            .await
+            .map_err(crate::connection::explain_auth_error)?;
+        tokio::spawn(Self::forward_stream_updates(
+            Playback::new(client),
+            stream,
+            stream_tx,
+        ));
         Ok(Self {
             model,
             playback,
             cmd_rx,
+            stream_rx,
         })
     }
 
+    /// Forward all events from the server stream to the UI loop, until the UI loop stops
+    ///
+    /// If the stream ends, for example because the server restarted, it subscribes again and lets
+    /// the UI loop re-sync with a [`UpdateEvents::MissedEvents`].
+    async fn forward_stream_updates(
+        mut playback: Playback,
+        mut stream: Streaming<StreamUpdates>,
+        stream_tx: UnboundedSender<UpdateEvents>,
+    ) {
+        loop {
+            let reason = match stream.message().await {
+                Ok(Some(ev)) => {
+                    match UpdateEvents::try_from(ev) {
+                        Ok(ev) => {
+                            if stream_tx.send(ev).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => warn!("Failed to parse server update: {err}"),
+                    }
+                    continue;
+                }
+                Ok(None) => "closed by the server".to_string(),
+                Err(err) => err.to_string(),
+            };
+
+            warn!("Lost the server update stream, subscribing again: {reason}");
+            let Some(new_stream) = Self::resubscribe(&mut playback, &stream_tx).await else {
+                break;
+            };
+            stream = new_stream;
+            // the amount of events sent while not subscribed is unknown
+            if stream_tx
+                .send(UpdateEvents::MissedEvents { amount: 0 })
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Subscribe to the server updates again, waiting longer after every failed attempt
+    ///
+    /// Returns [`None`] once the UI loop stopped.
+    async fn resubscribe(
+        playback: &mut Playback,
+        stream_tx: &UnboundedSender<UpdateEvents>,
+    ) -> Option<Streaming<StreamUpdates>> {
+        let mut delay = Duration::from_millis(500);
+        while !stream_tx.is_closed() {
+            tokio::time::sleep(delay).await;
+            match playback.subscribe_to_stream_updates().await {
+                Ok(stream) => return Some(stream),
+                Err(err) => warn!("Error subscribing to server updates: {err:#}"),
+            }
+            delay = (delay * 2).min(RESUBSCRIBE_MAX_DELAY);
+        }
+        None
+    }
+
     /// ### run
     ///
     /// Main loop for Ui thread
     pub async fn run(&mut self) -> Result<()> {
         self.model.init_terminal();
+        // initial sync, all changes after this are pushed via the update stream
+        self.model.command(&PlayerCmd::GetProgress);
 
         let res = self.run_inner().await;
 
@@ -91,6 +173,7 @@ impl UI {
     ///
     /// This function does NOT handle initializing and finializing the terminal
     async fn run_inner(&mut self) -> Result<()> {
+        self.sync_playlist_from_server().await?;
         // Main loop
         let mut progress_interval = 0;
         while !self.model.quit {
@@ -104,6 +187,7 @@ impl UI {
                 self.model.run();
             }
             self.run_playback().await?;
+            self.handle_stream_events().await?;
             progress_interval += 1;
             if progress_interval >= 80 {
                 progress_interval = 0;
@@ -138,15 +222,13 @@ impl UI {
         //     error!("error when saving config: {e}");
         // };
         if self.model.config.kill_daemon_when_quit {
-            let mut system = System::new();
-            system.refresh_all();
-            for proc in system.processes().values() {
-                let Some(exe) = proc.exe().map(|v| v.display().to_string()) else {
-                    continue;
-                };
-                if exe.contains("termusic-server") {
+            // only kill the server of the current profile
+            if let Some(pid) = crate::connection::find_running_server() {
+                let mut system = System::new();
+                let sys_pid = Pid::from_u32(pid);
+                system.refresh_process(sys_pid);
+                if let Some(proc) = system.process(sys_pid) {
                     proc.kill_with(sysinfo::Signal::Term);
-                    break;
                 }
             }
         }
@@ -208,6 +290,79 @@ impl UI {
         }
     }
 
+    /// Replace the local playlist mirror with the playlist the server owns
+    async fn sync_playlist_from_server(&mut self) -> Result<()> {
+        let response = self.playback.get_playlist().await?;
+        if let Err(e) = self
+            .model
+            .playlist
+            .replace_tracks_from_uris(response.current_track_index as usize, &response.tracks)
+        {
+            self.model
+                .mount_error_popup(format!("Error loading playlist: {e}"));
+        }
+        self.model.playlist_sync();
+        Ok(())
+    }
+
+    /// Apply all events the server pushed since the last call
+    async fn handle_stream_events(&mut self) -> Result<()> {
+        while let Ok(ev) = self.stream_rx.try_recv() {
+            match ev {
+                UpdateEvents::MissedEvents { amount } => {
+                    warn!("Missed {amount} server updates, re-syncing");
+                    self.sync_playlist_from_server().await?;
+                    self.model.command(&PlayerCmd::GetProgress);
+                }
+                UpdateEvents::TrackChanged {
+                    current_track_index,
+                    ..
+                } => self.handle_current_track_index(current_track_index as usize),
+                UpdateEvents::PlayStateChanged { status } => {
+                    self.handle_status(status);
+                    self.model.progress_update_title();
+                }
+                UpdateEvents::VolumeChanged { volume } => {
+                    self.model.config.player_volume = volume;
+                    self.model.progress_update_title();
+                }
+                UpdateEvents::SpeedChanged { speed } => {
+                    self.model.config.player_speed_percent = speed;
+                    self.model.progress_update_title();
+                }
+                UpdateEvents::GaplessChanged { gapless } => {
+                    self.model.config.player_gapless = gapless;
+                    self.model.progress_update_title();
+                }
+                UpdateEvents::Progress(pprogress) => {
+                    self.model.progress_update(
+                        pprogress.position,
+                        pprogress.total_duration.unwrap_or_default(),
+                    );
+                }
+                UpdateEvents::RadioTitleChanged { title } => {
+                    self.model.lyric_update_for_radio(&title);
+                }
+                UpdateEvents::PlaylistChanged => self.sync_playlist_from_server().await?,
+                UpdateEvents::LoopModeChanged { loop_mode } => {
+                    self.model.config.player_loop_mode = loop_mode;
+                    self.model.playlist.set_loop_mode(loop_mode);
+                    self.model.playlist_update_title();
+                }
+                UpdateEvents::LibraryChanged => self.model.library_changed(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Show playlist edit errors (like a out-of-bounds index) instead of exiting
+    fn handle_playlist_result(&mut self, res: Result<()>) {
+        if let Err(e) = res {
+            self.model
+                .mount_error_popup(format!("Error editing playlist: {e}"));
+        }
+    }
+
     async fn run_playback(&mut self) -> Result<()> {
         if let Ok(cmd) = self.cmd_rx.try_recv() {
             match cmd {
@@ -227,9 +382,7 @@ impl UI {
                         pprogress.position,
                         pprogress.total_duration.unwrap_or_default(),
                     );
-                    if response.current_track_updated {
-                        self.handle_current_track_index(response.current_track_index as usize);
-                    }
+                    self.handle_current_track_index(response.current_track_index as usize);
 
                     self.model.lyric_update_for_radio(&response.radio_title);
 
@@ -243,9 +396,55 @@ impl UI {
                     // This line is required to show current playing message
                     // self.model.playlist.set_current_track_index(None);
                 }
+                // clamped to u32::MAX, playlists will not get that big
+                #[allow(clippy::cast_possible_truncation)]
+                PlayerCmd::PlayIndex(index) => {
+                    let res = self.playback.play_index(index as u32).await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlayUri { uri, mode } => {
+                    let res = self.playback.play_uri(uri, mode).await;
+                    self.handle_playlist_result(res);
+                }
                 PlayerCmd::SkipPrevious => self.playback.skip_previous().await?,
+                PlayerCmd::PlaylistAddTracks { tracks, at_index } => {
+                    let res = self.playback.playlist_add_tracks(tracks, at_index).await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlaylistRemoveTracks { at_index, amount } => {
+                    let res = self.playback.playlist_remove_tracks(at_index, amount).await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlaylistMoveTrack { from, to } => {
+                    let res = self.playback.playlist_move_track(from, to).await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlaylistSwapTracks { index_a, index_b } => {
+                    let res = self.playback.playlist_swap_tracks(index_a, index_b).await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlaylistClear => {
+                    let res = self.playback.playlist_clear().await;
+                    self.handle_playlist_result(res);
+                }
+                PlayerCmd::PlaylistShuffle => {
+                    let res = self.playback.playlist_shuffle().await;
+                    self.handle_playlist_result(res);
+                }
                 PlayerCmd::ReloadConfig => self.playback.reload_config().await?,
+                PlayerCmd::AnalyzeLoudness {
+                    paths,
+                    write_tags,
+                    force,
+                } => {
+                    self.playback
+                        .analyze_loudness(paths, write_tags, force)
+                        .await?;
+                }
                 PlayerCmd::ReloadPlaylist => self.playback.reload_playlist().await?,
+                PlayerCmd::RateCurrentTrack(rating) => {
+                    self.playback.rate_current_track(rating).await?;
+                }
                 PlayerCmd::SeekBackward => {
                     let pprogress = self.playback.seek_backward().await?;
                     self.model.progress_update(
@@ -263,11 +462,11 @@ impl UI {
                     self.model.force_redraw();
                 }
                 PlayerCmd::SpeedDown => {
-                    self.model.config.player_speed = self.playback.speed_down().await?;
+                    self.model.config.player_speed_percent = self.playback.speed_down().await?;
                     self.model.progress_update_title();
                 }
                 PlayerCmd::SpeedUp => {
-                    self.model.config.player_speed = self.playback.speed_up().await?;
+                    self.model.config.player_speed_percent = self.playback.speed_up().await?;
                     self.model.progress_update_title();
                 }
                 PlayerCmd::ToggleGapless => {
@@ -25,7 +25,7 @@ mod update;
 mod view;
 mod youtube_options;
 use crate::ui::Application;
-use termusiclib::sqlite::{DataBase, SearchCriteria};
+use termusiclib::sqlite::{DataBase, SearchCriteria, TrackSort};
 use termusiclib::types::{Id, Msg, SearchLyricState, YoutubeOptions};
 
 #[cfg(feature = "cover")]
@@ -35,7 +35,6 @@ use termusiclib::{
     track::{MediaType, Track},
 };
 
-use anyhow::Result;
 use std::path::PathBuf;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
@@ -63,6 +62,7 @@ pub enum ConfigEditorLayout {
     Color,
     Key1,
     Key2,
+    Equalizer,
 }
 
 pub struct Model {
@@ -96,6 +96,7 @@ pub struct Model {
     pub db_criteria: SearchCriteria,
     pub db_search_results: Vec<String>,
     pub db_search_tracks: Vec<TrackForDB>,
+    pub db_sort: TrackSort,
     pub layout: TermusicLayout,
     pub config_layout: ConfigEditorLayout,
     pub config_changed: bool,
@@ -108,6 +109,10 @@ pub struct Model {
     pub rx_to_main: Receiver<Msg>,
     pub podcast_search_vec: Option<Vec<PodcastFeed>>,
     pub playlist: Playlist,
+    /// Smart playlist waiting for the m3u file to save it to, instead of the playlist
+    pub m3u_export: Option<Playlist>,
+    /// Music directories the server reported to keep the library database of up to date
+    pub library_watched_dirs: Vec<PathBuf>,
     pub cmd_tx: UnboundedSender<PlayerCmd>,
 }
 
@@ -192,6 +197,7 @@ impl Model {
             db_criteria,
             db_search_results: Vec::new(),
             db_search_tracks: Vec::new(),
+            db_sort: TrackSort::default(),
             config_changed: false,
             podcasts,
             podcasts_index: 0,
@@ -202,6 +208,8 @@ impl Model {
             download_tracker: DownloadTracker::default(),
             podcast_search_vec: None,
             playlist,
+            m3u_export: None,
+            library_watched_dirs: Vec::new(),
             cmd_tx,
             current_song: None,
         }
@@ -219,12 +227,44 @@ impl Model {
         PathBuf::from(full_path)
     }
 
+    /// Add new and changed files of the library to the database.
+    ///
+    /// Left to the server if it watches the library or also analyzes the loudness of new tracks.
+    pub fn library_sync(&mut self) {
+        if self.library_watched() {
+            return;
+        }
+        if self.config.library_analyze_loudness {
+            self.command(&PlayerCmd::AnalyzeLoudness {
+                paths: vec![self.path.to_string_lossy().to_string()],
+                write_tags: self.config.library_loudness_write_tags,
+                force: false,
+            });
+        } else {
+            self.db.sync_database(&self.path);
+        }
+    }
+
+    /// Whether the server watches the shown music directory, which may be given on the command
+    /// line of either one
+    fn library_watched(&self) -> bool {
+        self.library_watched_dirs
+            .iter()
+            .any(|dir| self.path.starts_with(dir))
+    }
+
+    /// Show the changes of the library database the server applied
+    pub fn library_changed(&mut self) {
+        self.library_reload_tree();
+        self.database_refresh();
+    }
+
     pub fn init_config(&mut self) {
         if let Err(e) = Self::theme_select_save() {
             self.mount_error_popup(format!("theme save error: {e}"));
         }
         self.mount_label_help();
-        self.db.sync_database(&self.path);
+        self.library_sync();
         self.playlist_sync();
     }
 
@@ -256,17 +296,10 @@ impl Model {
     }
 
     pub fn run(&mut self) {
-        self.command(&PlayerCmd::GetProgress);
         self.progress_update_title();
         self.lyric_update_title();
     }
 
-    pub fn player_sync_playlist(&mut self) -> Result<()> {
-        self.playlist.save()?;
-        self.command(&PlayerCmd::ReloadPlaylist);
-        Ok(())
-    }
-
     pub fn player_update_current_track_after(&mut self) {
         self.time_pos = Duration::default();
         if let Err(e) = self.update_photo() {
@@ -3,10 +3,11 @@ use std::thread::{self, sleep};
 use std::time::Duration;
 use termusiclib::sqlite::SearchCriteria;
 // use termusiclib::track::MediaType;
-use termusiclib::track::MediaType;
+use termusiclib::track::{MediaType, Track};
 use termusiclib::types::{
     DBMsg, DLMsg, GSMsg, Id, IdTagEditor, LIMsg, LyricMsg, Msg, PCMsg, PLMsg, XYWHMsg, YSMsg,
 };
+use termusiclib::utils::filetype_supported;
 use termusicplayback::PlayerCmd;
 /**
  * MIT License
@@ -157,6 +158,7 @@ impl Update<Msg> for Model {
                     None
                 }
                 Msg::SavePlaylistConfirmCloseCancel => {
+                    self.m3u_export = None;
                     self.umount_save_playlist_confirm();
                     None
                 }
@@ -582,6 +584,18 @@ impl Model {
                 let db_search_tracks = self.db_search_tracks.clone();
                 self.playlist_add_all_from_db(&db_search_tracks);
             }
+            DBMsg::SortTracks => self.database_sort_tracks(),
+            DBMsg::ExportM3u(index) => {
+                if let Err(e) = self.database_export_m3u(*index) {
+                    self.mount_error_popup(format!("export m3u error: {e}"));
+                }
+            }
+            DBMsg::Rate(index, change) => {
+                if let Some(track) = self.db_search_tracks.get(*index) {
+                    let file = track.file.clone();
+                    self.database_rate(&file, *change);
+                }
+            }
         }
         None
     }
@@ -597,6 +611,11 @@ impl Model {
             LIMsg::TreeGoToUpperDir => {
                 self.library_stepout();
             }
+            LIMsg::Rate(path, change) => {
+                if filetype_supported(path) {
+                    self.database_rate(path, *change);
+                }
+            }
             LIMsg::Yank => {
                 self.library_yank();
             }
@@ -802,18 +821,10 @@ impl Model {
                 self.player_previous();
             }
             PLMsg::SwapDown(index) => {
-                self.playlist.swap_down(*index);
-                self.playlist_sync();
-                if let Err(e) = self.player_sync_playlist() {
-                    self.mount_error_popup(format!("Error sync playlist: {e}"));
-                }
+                self.playlist_swap_down(*index);
             }
             PLMsg::SwapUp(index) => {
-                self.playlist.swap_up(*index);
-                self.playlist_sync();
-                if let Err(e) = self.player_sync_playlist() {
-                    self.mount_error_popup(format!("Error sync playlist: {e}"));
-                }
+                self.playlist_swap_up(*index);
             }
             PLMsg::CmusLQueue => {
                 self.playlist_add_cmus_lqueue();
@@ -821,6 +832,21 @@ impl Model {
             PLMsg::CmusTQueue => {
                 self.playlist_add_cmus_tqueue();
             }
+            PLMsg::SmartSelection(selection) => {
+                self.playlist_add_smart_selection(*selection);
+            }
+            PLMsg::Rate(index, change) => {
+                if let Some(file) = self
+                    .playlist
+                    .tracks()
+                    .get(*index)
+                    .filter(|track| track.media_type == Some(MediaType::Music))
+                    .and_then(Track::file)
+                {
+                    let file = file.to_string();
+                    self.database_rate(&file, *change);
+                }
+            }
             PLMsg::PlaylistTableBlurUp => match self.layout {
                 TermusicLayout::TreeView => assert!(self.app.active(&Id::Library).is_ok()),
                 TermusicLayout::DataBase => {
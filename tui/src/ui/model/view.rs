@@ -169,6 +169,7 @@ impl Model {
                     ConfigEditorLayout::Color => self.view_config_editor_color(),
                     ConfigEditorLayout::Key1 => self.view_config_editor_key1(),
                     ConfigEditorLayout::Key2 => self.view_config_editor_key2(),
+                    ConfigEditorLayout::Equalizer => self.view_config_editor_equalizer(),
                 }
                 return;
             }
@@ -1,20 +1,32 @@
-use anyhow::Result;
-use termusicplayback::player::music_player_client::MusicPlayerClient;
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+use termusiclib::config::{ChannelMixSettings, EqPreset, EqualizerSettings, Loop};
 use termusicplayback::player::{
-    CycleLoopRequest, GetProgressRequest, GetProgressResponse, PlaySelectedRequest,
-    ReloadConfigRequest, ReloadPlaylistRequest, SeekBackwardRequest, SeekForwardRequest,
-    SkipNextRequest, SkipPreviousRequest, SpeedDownRequest, SpeedUpRequest, ToggleGaplessRequest,
-    TogglePauseRequest, VolumeDownRequest, VolumeUpRequest,
+    AnalyzeLoudnessRequest, ChannelMixReply, CycleLoopRequest, EqualizerReply,
+    GetChannelMixRequest, GetCurrentTrackRequest, GetEqualizerRequest, GetLibraryWatchRequest,
+    GetPlaylistRequest, GetProgressRequest, GetProgressResponse, ListOutputDevicesRequest,
+    LoopModeReply, OutputDevicesReply, PlayIndexRequest, PlaySelectedRequest, PlayUriRequest,
+    PlaylistAddTracksRequest, PlaylistClearRequest, PlaylistIndex, PlaylistMoveTrackRequest,
+    PlaylistRemoveTracksRequest, PlaylistShuffleRequest, PlaylistSwapTracksRequest, PlaylistTracks,
+    RateCurrentTrackRequest, ReloadConfigRequest, ReloadPlaylistRequest, SeekBackwardRequest,
+    SeekForwardRequest, SeekRelativeRequest, SeekRequest, SetChannelMixRequest,
+    SetEqualizerRequest, SetGaplessRequest, SetLoopModeRequest, SetOutputDeviceRequest,
+    SetSpeedRequest, SetVolumeRequest, SkipNextRequest, SkipPreviousRequest, SpeedDownRequest,
+    SpeedUpRequest, StreamUpdates, SubscribeServerUpdatesRequest, ToggleGaplessRequest,
+    TogglePauseRequest, TrackInfo, VolumeDownRequest, VolumeUpRequest,
 };
-use termusicplayback::{PlayerProgress, Status};
-use tonic::transport::Channel;
+use termusicplayback::{speed_to_tenths, EnqueueMode, PlayerProgress, Status};
+use tonic::Streaming;
+
+use crate::connection::Client;
 
 pub struct Playback {
-    client: MusicPlayerClient<Channel>,
+    client: Client,
 }
 
 impl Playback {
-    pub fn new(client: MusicPlayerClient<Channel>) -> Self {
+    pub fn new(client: Client) -> Self {
         Self { client }
     }
     pub async fn toggle_pause(&mut self) -> Result<Status> {
@@ -74,7 +86,7 @@ impl Playback {
         let response = self.client.speed_up(request).await?;
         let response = response.into_inner();
         info!("Got response from server: {:?}", response);
-        Ok(response.speed)
+        Ok(response.speed_percent)
     }
 
     pub async fn speed_down(&mut self) -> Result<i32> {
@@ -82,7 +94,7 @@ impl Playback {
         let response = self.client.speed_down(request).await?;
         let response = response.into_inner();
         info!("Got response from server: {:?}", response);
-        Ok(response.speed)
+        Ok(response.speed_percent)
     }
 
     pub async fn toggle_gapless(&mut self) -> Result<bool> {
@@ -109,6 +121,155 @@ impl Playback {
         Ok(response.into())
     }
 
+    pub async fn seek_to(&mut self, position: Duration) -> Result<PlayerProgress> {
+        let request = tonic::Request::new(SeekRequest {
+            position: Some(position.into()),
+        });
+        let response = self.client.seek(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.into())
+    }
+
+    /// Seek by `offset` seconds, negative to seek backward
+    pub async fn seek_relative(&mut self, offset: i64) -> Result<PlayerProgress> {
+        let request = tonic::Request::new(SeekRelativeRequest { offset });
+        let response = self.client.seek_relative(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.into())
+    }
+
+    pub async fn set_volume(&mut self, volume: u16) -> Result<u16> {
+        let request = tonic::Request::new(SetVolumeRequest {
+            volume: u32::from(volume),
+        });
+        let response = self.client.set_volume(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        // clamped to u16::MAX, also send is a u16, but protobuf does not support u16 directly
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(response.volume.min(u32::from(u16::MAX)) as u16)
+    }
+
+    pub async fn set_speed(&mut self, speed: i32) -> Result<i32> {
+        let request = tonic::Request::new(SetSpeedRequest {
+            speed: speed_to_tenths(speed),
+            speed_percent: speed,
+        });
+        let response = self.client.set_speed(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.speed_percent)
+    }
+
+    pub async fn set_loop_mode(&mut self, loop_mode: Loop) -> Result<Loop> {
+        let request = tonic::Request::new(SetLoopModeRequest {
+            loop_mode: loop_mode.as_u32(),
+        });
+        let response = self.client.set_loop_mode(request).await?;
+        let LoopModeReply { loop_mode } = response.into_inner();
+        info!("Got response from server: {:?}", loop_mode);
+        Loop::from_u32(loop_mode).ok_or_else(|| anyhow!("Got a unknown loop mode: {loop_mode}"))
+    }
+
+    pub async fn set_gapless(&mut self, gapless: bool) -> Result<bool> {
+        let request = tonic::Request::new(SetGaplessRequest { gapless });
+        let response = self.client.set_gapless(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.gapless)
+    }
+
+    pub async fn get_equalizer(&mut self) -> Result<EqualizerSettings> {
+        let request = tonic::Request::new(GetEqualizerRequest {});
+        let response = self.client.get_equalizer(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        equalizer_from_reply(response)
+    }
+
+    pub async fn set_equalizer(
+        &mut self,
+        equalizer: &EqualizerSettings,
+    ) -> Result<EqualizerSettings> {
+        let request = tonic::Request::new(SetEqualizerRequest {
+            enabled: equalizer.enabled,
+            preset: equalizer.preset.as_u32(),
+            custom_gains: equalizer.custom_gains.to_vec(),
+        });
+        let response = self.client.set_equalizer(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        equalizer_from_reply(response)
+    }
+
+    pub async fn get_channel_mix(&mut self) -> Result<ChannelMixSettings> {
+        let request = tonic::Request::new(GetChannelMixRequest {});
+        let response = self.client.get_channel_mix(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        channel_mix_from_reply(&response)
+    }
+
+    pub async fn set_channel_mix(
+        &mut self,
+        channel_mix: ChannelMixSettings,
+    ) -> Result<ChannelMixSettings> {
+        let request = tonic::Request::new(SetChannelMixRequest {
+            mono: channel_mix.mono,
+            balance: i32::from(channel_mix.balance),
+        });
+        let response = self.client.set_channel_mix(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        channel_mix_from_reply(&response)
+    }
+
+    pub async fn list_output_devices(&mut self) -> Result<OutputDevicesReply> {
+        let request = tonic::Request::new(ListOutputDevicesRequest {});
+        let response = self.client.list_output_devices(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    /// Move playback to the output device called `name`, empty for the default device
+    pub async fn set_output_device(&mut self, name: String) -> Result<OutputDevicesReply> {
+        let request = tonic::Request::new(SetOutputDeviceRequest { name });
+        let response = self.client.set_output_device(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn get_current_track(&mut self) -> Result<Option<TrackInfo>> {
+        let request = tonic::Request::new(GetCurrentTrackRequest {});
+        let response = self.client.get_current_track(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.track)
+    }
+
+    pub async fn play_index(&mut self, index: u32) -> Result<()> {
+        let request = tonic::Request::new(PlayIndexRequest { index });
+        let response = self.client.play_index(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn play_uri(&mut self, uri: String, mode: EnqueueMode) -> Result<()> {
+        let request = tonic::Request::new(PlayUriRequest {
+            uri,
+            enqueue_mode: mode.as_u32(),
+        });
+        let response = self.client.play_uri(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
     pub async fn reload_config(&mut self) -> Result<()> {
         let request = tonic::Request::new(ReloadConfigRequest {});
         let response = self.client.reload_config(request).await?;
@@ -117,6 +278,44 @@ impl Playback {
         Ok(())
     }
 
+    /// Let the server add `paths` to the library and analyze the loudness of their tracks
+    pub async fn analyze_loudness(
+        &mut self,
+        paths: Vec<String>,
+        write_tags: bool,
+        force: bool,
+    ) -> Result<()> {
+        let request = tonic::Request::new(AnalyzeLoudnessRequest {
+            paths,
+            write_tags,
+            force,
+        });
+        let response = self.client.analyze_loudness(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    /// Get the music directories the server keeps the library database of up to date
+    pub async fn get_library_watch(&mut self) -> Result<Vec<PathBuf>> {
+        let request = tonic::Request::new(GetLibraryWatchRequest {});
+        let response = self.client.get_library_watch(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.dirs.into_iter().map(PathBuf::from).collect())
+    }
+
+    /// Set the star `rating` of the current track
+    pub async fn rate_current_track(&mut self, rating: u8) -> Result<()> {
+        let request = tonic::Request::new(RateCurrentTrackRequest {
+            rating: u32::from(rating),
+        });
+        let response = self.client.rate_current_track(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
     pub async fn reload_playlist(&mut self) -> Result<()> {
         let request = tonic::Request::new(ReloadPlaylistRequest {});
         let response = self.client.reload_playlist(request).await?;
@@ -138,4 +337,113 @@ impl Playback {
         info!("Got response from server: {:?}", response);
         Ok(())
     }
+
+    pub async fn subscribe_to_stream_updates(&mut self) -> Result<Streaming<StreamUpdates>> {
+        let request = tonic::Request::new(SubscribeServerUpdatesRequest {});
+        let response = self.client.subscribe_server_updates(request).await?;
+        info!("Subscribed to server updates");
+        Ok(response.into_inner())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn playlist_add_tracks(
+        &mut self,
+        tracks: Vec<String>,
+        at_index: Option<usize>,
+    ) -> Result<()> {
+        let request = tonic::Request::new(PlaylistAddTracksRequest {
+            tracks,
+            at_index: at_index.map(|index| PlaylistIndex {
+                index: index as u32,
+            }),
+        });
+        let response = self.client.playlist_add_tracks(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn playlist_remove_tracks(&mut self, at_index: usize, amount: usize) -> Result<()> {
+        let request = tonic::Request::new(PlaylistRemoveTracksRequest {
+            at_index: at_index as u32,
+            amount: amount as u32,
+        });
+        let response = self.client.playlist_remove_tracks(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn playlist_move_track(&mut self, from: usize, to: usize) -> Result<()> {
+        let request = tonic::Request::new(PlaylistMoveTrackRequest {
+            from_index: from as u32,
+            to_index: to as u32,
+        });
+        let response = self.client.playlist_move_track(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn playlist_swap_tracks(&mut self, index_a: usize, index_b: usize) -> Result<()> {
+        let request = tonic::Request::new(PlaylistSwapTracksRequest {
+            index_a: index_a as u32,
+            index_b: index_b as u32,
+        });
+        let response = self.client.playlist_swap_tracks(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn playlist_clear(&mut self) -> Result<()> {
+        let request = tonic::Request::new(PlaylistClearRequest {});
+        let response = self.client.playlist_clear(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn playlist_shuffle(&mut self) -> Result<()> {
+        let request = tonic::Request::new(PlaylistShuffleRequest {});
+        let response = self.client.playlist_shuffle(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn get_playlist(&mut self) -> Result<PlaylistTracks> {
+        let request = tonic::Request::new(GetPlaylistRequest {});
+        let response = self.client.get_playlist(request).await?;
+        let response = response.into_inner();
+        Ok(response)
+    }
+}
+
+fn equalizer_from_reply(reply: EqualizerReply) -> Result<EqualizerSettings> {
+    let preset = EqPreset::from_u32(reply.preset)
+        .ok_or_else(|| anyhow!("unknown equalizer preset {}", reply.preset))?;
+    let custom_gains = reply
+        .custom_gains
+        .try_into()
+        .map_err(|_| anyhow!("unexpected amount of equalizer gains"))?;
+
+    Ok(EqualizerSettings {
+        enabled: reply.enabled,
+        preset,
+        custom_gains,
+    })
+}
+
+fn channel_mix_from_reply(reply: &ChannelMixReply) -> Result<ChannelMixSettings> {
+    let balance = i16::try_from(reply.balance)
+        .map_err(|_| anyhow!("unexpected balance {}", reply.balance))?;
+
+    Ok(ChannelMixSettings {
+        mono: reply.mono,
+        balance,
+    })
 }